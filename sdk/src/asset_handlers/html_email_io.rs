@@ -0,0 +1,454 @@
+use std::{io::Cursor, ops::Range};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use memchr::memmem;
+
+use crate::{
+    asset_handlers::html_io::HtmlIO,
+    asset_io::{CAIRead, CAIReadWrite, CAIReader, CAIWriter},
+    error::{Error, Result},
+};
+
+/// longest encoded line written into a part, as required by RFC 2045
+const MAX_ENCODED_LINE_LEN: usize = 76;
+
+/// how many levels of nested multipart entities are followed when looking for the html part
+const MAX_MULTIPART_DEPTH: usize = 16;
+
+/// Embeds manifests into the `text/html` body part of an RFC 822 email message
+/// (`.eml`, `message/rfc822`).
+///
+/// The message is searched for its first `text/html` part, walking nested `multipart`
+/// entities. The part is decoded according to its `Content-Transfer-Encoding`
+/// (`quoted-printable`, `base64` or none), the manifest is written into it with the
+/// wrapped [`HtmlIO`] and the part is encoded again the same way. Everything outside
+/// the html part is copied unchanged. Unlike MHTML the html is not a resource of the
+/// message but one of its alternative bodies.
+#[derive(Clone, Debug, Default)]
+pub struct HtmlEmailIO {
+    html_io: HtmlIO,
+}
+
+impl HtmlEmailIO {
+    /// Creates a handler that writes the html part with `html_io`, so its configuration
+    /// also applies to email.
+    pub fn new(html_io: HtmlIO) -> Self {
+        HtmlEmailIO { html_io }
+    }
+
+    /// Returns the decoded html of the first `text/html` part of the message.
+    pub fn read_html_part(&self, input_stream: &mut dyn CAIRead) -> Result<Vec<u8>> {
+        let message = read_message(input_stream)?;
+        let part = find_html_part(&message)?;
+
+        part.transfer_encoding.decode(&message[part.body])
+    }
+
+    /// Writes `store_bytes` into the html part of the message read from `input_stream`
+    /// and writes the updated message to `output_stream`.
+    pub fn write_cai(
+        &self,
+        input_stream: &mut dyn CAIRead,
+        output_stream: &mut dyn CAIReadWrite,
+        store_bytes: &[u8],
+    ) -> Result<()> {
+        let message = read_message(input_stream)?;
+        let part = find_html_part(&message)?;
+        let html = part.transfer_encoding.decode(&message[part.body.clone()])?;
+
+        let mut updated_html = Cursor::new(Vec::new());
+        self.html_io
+            .write_cai(&mut Cursor::new(html), &mut updated_html, store_bytes)?;
+        let eol = line_ending(&message);
+        let encoded = part.transfer_encoding.encode(updated_html.get_ref(), eol);
+
+        output_stream.rewind()?;
+        output_stream.write_all(&message[..part.body.start])?;
+        output_stream.write_all(&encoded)?;
+        output_stream.write_all(&message[part.body.end..])?;
+        Ok(())
+    }
+}
+
+impl CAIReader for HtmlEmailIO {
+    fn read_cai(&self, input_stream: &mut dyn CAIRead) -> Result<Vec<u8>> {
+        let html = self.read_html_part(input_stream)?;
+
+        self.html_io.read_cai(&mut Cursor::new(html))
+    }
+
+    fn read_xmp(&self, input_stream: &mut dyn CAIRead) -> Option<String> {
+        let html = self.read_html_part(input_stream).ok()?;
+
+        self.html_io.read_xmp(&mut Cursor::new(html))
+    }
+}
+
+/// `Content-Transfer-Encoding` of a body part
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TransferEncoding {
+    /// `7bit`, `8bit` or `binary`, the body is stored as is
+    Identity,
+    QuotedPrintable,
+    Base64,
+}
+
+impl TransferEncoding {
+    fn from_header(value: Option<&str>) -> Result<Self> {
+        match value.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+            None | Some("7bit") | Some("8bit") | Some("binary") => Ok(TransferEncoding::Identity),
+            Some("quoted-printable") => Ok(TransferEncoding::QuotedPrintable),
+            Some("base64") => Ok(TransferEncoding::Base64),
+            Some(other) => Err(Error::InvalidAsset(format!(
+                "unsupported email Content-Transfer-Encoding: {other}"
+            ))),
+        }
+    }
+
+    fn decode(self, body: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            TransferEncoding::Identity => Ok(body.to_vec()),
+            TransferEncoding::QuotedPrintable => Ok(decode_quoted_printable(body)),
+            TransferEncoding::Base64 => {
+                let encoded: Vec<u8> = body
+                    .iter()
+                    .copied()
+                    .filter(|b| !b.is_ascii_whitespace())
+                    .collect();
+                STANDARD.decode(encoded).map_err(|_| {
+                    Error::InvalidAsset("email html part has bad base64 encoding".into())
+                })
+            }
+        }
+    }
+
+    fn encode(self, body: &[u8], eol: &[u8]) -> Vec<u8> {
+        match self {
+            TransferEncoding::Identity => body.to_vec(),
+            TransferEncoding::QuotedPrintable => encode_quoted_printable(body, eol),
+            TransferEncoding::Base64 => STANDARD
+                .encode(body)
+                .as_bytes()
+                .chunks(MAX_ENCODED_LINE_LEN)
+                .collect::<Vec<_>>()
+                .join(eol),
+        }
+    }
+}
+
+/// the body of the html part within the message and how it is encoded
+#[derive(Debug)]
+struct HtmlPart {
+    body: Range<usize>,
+    transfer_encoding: TransferEncoding,
+}
+
+fn read_message(input_stream: &mut dyn CAIRead) -> Result<Vec<u8>> {
+    let mut message = Vec::new();
+    input_stream.rewind()?;
+    input_stream.read_to_end(&mut message)?;
+    Ok(message)
+}
+
+/// the line ending used by the message, lines end with CRLF on the wire but files often
+/// use bare LF
+fn line_ending(message: &[u8]) -> &'static [u8] {
+    match memchr::memchr(b'\n', message) {
+        Some(pos) if pos > 0 && message[pos - 1] == b'\r' => b"\r\n",
+        _ => b"\n",
+    }
+}
+
+fn find_html_part(message: &[u8]) -> Result<HtmlPart> {
+    find_html_part_in(message, 0..message.len(), 0)?
+        .ok_or_else(|| Error::InvalidAsset("email message has no text/html part".into()))
+}
+
+fn find_html_part_in(
+    message: &[u8],
+    entity: Range<usize>,
+    depth: usize,
+) -> Result<Option<HtmlPart>> {
+    let (headers, body) = split_entity(message, entity);
+    let content_type = header_value(&headers, "content-type").unwrap_or_default();
+    let (media_type, boundary) = parse_content_type(&content_type);
+
+    if media_type == "text/html" {
+        let transfer_encoding = TransferEncoding::from_header(
+            header_value(&headers, "content-transfer-encoding").as_deref(),
+        )?;
+        return Ok(Some(HtmlPart {
+            body,
+            transfer_encoding,
+        }));
+    }
+
+    if media_type.starts_with("multipart/") && depth < MAX_MULTIPART_DEPTH {
+        let boundary = boundary
+            .ok_or_else(|| Error::InvalidAsset("email multipart entity has no boundary".into()))?;
+        for part in multipart_parts(message, body, boundary.as_bytes()) {
+            if let Some(html_part) = find_html_part_in(message, part, depth + 1)? {
+                return Ok(Some(html_part));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// split an entity into its unfolded header text and the range of its body
+fn split_entity(message: &[u8], entity: Range<usize>) -> (String, Range<usize>) {
+    let bytes = &message[entity.clone()];
+
+    // an entity that starts with an empty line has no headers
+    let (header_len, separator_len) = if bytes.starts_with(b"\r\n") {
+        (0, 2)
+    } else if bytes.starts_with(b"\n") {
+        (0, 1)
+    } else {
+        match (
+            memmem::find(bytes, b"\r\n\r\n"),
+            memmem::find(bytes, b"\n\n"),
+        ) {
+            (Some(crlf), Some(lf)) if lf < crlf => (lf, 2),
+            (Some(crlf), _) => (crlf, 4),
+            (None, Some(lf)) => (lf, 2),
+            (None, None) => (bytes.len(), 0),
+        }
+    };
+
+    let headers = String::from_utf8_lossy(&bytes[..header_len]).replace("\r\n", "\n");
+    // continuation lines start with white space
+    let headers = headers.replace("\n ", " ").replace("\n\t", " ");
+    let body_start = (entity.start + header_len + separator_len).min(entity.end);
+    (headers, body_start..entity.end)
+}
+
+fn header_value(headers: &str, name: &str) -> Option<String> {
+    headers.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().to_owned())
+    })
+}
+
+/// the lowercase media type and the boundary parameter of a `Content-Type` value
+fn parse_content_type(value: &str) -> (String, Option<String>) {
+    let mut params = value.split(';');
+    let media_type = params
+        .next()
+        .map(|t| t.trim().to_ascii_lowercase())
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| "text/plain".to_owned());
+    let boundary = params.find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("boundary")
+            .then(|| value.trim().trim_matches('"').to_owned())
+    });
+    (media_type, boundary)
+}
+
+/// the ranges of the parts of a multipart body, without the line break that belongs to the
+/// following delimiter
+fn multipart_parts(message: &[u8], body: Range<usize>, boundary: &[u8]) -> Vec<Range<usize>> {
+    let delimiter = [b"--", boundary].concat();
+    let bytes = &message[body.clone()];
+
+    // delimiters must start a line
+    let delimiters: Vec<usize> = memmem::find_iter(bytes, &delimiter)
+        .filter(|&pos| pos == 0 || bytes[pos - 1] == b'\n')
+        .collect();
+
+    let mut parts = Vec::new();
+    for (i, &pos) in delimiters.iter().enumerate() {
+        let after = pos + delimiter.len();
+        if bytes[after..].starts_with(b"--") {
+            break; // close delimiter
+        }
+        let Some(next) = delimiters.get(i + 1) else {
+            break;
+        };
+        let start = match memchr::memchr(b'\n', &bytes[after..]) {
+            Some(eol) => after + eol + 1,
+            None => break,
+        };
+        let mut end = *next;
+        if end > start && bytes[end - 1] == b'\n' {
+            end -= 1;
+            if end > start && bytes[end - 1] == b'\r' {
+                end -= 1;
+            }
+        }
+        parts.push(body.start + start..body.start + end.max(start));
+    }
+    parts
+}
+
+fn decode_quoted_printable(body: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(body.len());
+    let mut i = 0;
+    while i < body.len() {
+        match body[i] {
+            b'=' if body[i + 1..].starts_with(b"\r\n") => i += 3, // soft line break
+            b'=' if body[i + 1..].starts_with(b"\n") => i += 2,
+            b'=' => {
+                match body
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 3;
+                    }
+                    None => {
+                        decoded.push(b'=');
+                        i += 1;
+                    }
+                }
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
+fn encode_quoted_printable(body: &[u8], eol: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(body.len() + body.len() / 8);
+    let lines: Vec<&[u8]> = body.split(|&b| b == b'\n').collect();
+
+    for (n, line) in lines.iter().enumerate() {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let mut line_len = 0;
+        for (i, &byte) in line.iter().enumerate() {
+            let is_last = i + 1 == line.len();
+            let literal = matches!(byte, b'!'..=b'<' | b'>'..=b'~')
+                || (matches!(byte, b' ' | b'\t') && !is_last);
+            let width = if literal { 1 } else { 3 };
+
+            // keep room for the "=" of a soft line break
+            if line_len + width > MAX_ENCODED_LINE_LEN - 1 {
+                encoded.push(b'=');
+                encoded.extend_from_slice(eol);
+                line_len = 0;
+            }
+            if literal {
+                encoded.push(byte);
+            } else {
+                encoded.extend_from_slice(format!("={byte:02X}").as_bytes());
+            }
+            line_len += width;
+        }
+        if n + 1 < lines.len() {
+            encoded.extend_from_slice(eol);
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+pub mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    const ALTERNATIVE_EMAIL: &str = "From: sender@example.com\r\n\
+        To: recipient@example.com\r\n\
+        Subject: Provenance\r\n\
+        MIME-Version: 1.0\r\n\
+        Content-Type: multipart/alternative;\r\n\
+        \tboundary=\"alt-boundary\"\r\n\
+        \r\n\
+        --alt-boundary\r\n\
+        Content-Type: text/plain; charset=utf-8\r\n\
+        \r\n\
+        Hello\r\n\
+        --alt-boundary\r\n\
+        Content-Type: text/html; charset=utf-8\r\n\
+        Content-Transfer-Encoding: quoted-printable\r\n\
+        \r\n\
+        <html><head><title>Hello</title></head><body style=3D\"color: red\"><p>Hell=\r\n\
+        o</p></body></html>\r\n\
+        --alt-boundary--\r\n";
+
+    fn embed(message: &[u8], store_bytes: &[u8]) -> Vec<u8> {
+        let mut output = Cursor::new(Vec::new());
+        HtmlEmailIO::default()
+            .write_cai(&mut Cursor::new(message), &mut output, store_bytes)
+            .unwrap();
+        output.into_inner()
+    }
+
+    #[test]
+    fn test_embed_in_alternative_html_part() {
+        let store = vec![0x5a; 500];
+        let written = embed(ALTERNATIVE_EMAIL.as_bytes(), &store);
+
+        let email_io = HtmlEmailIO::default();
+        assert_eq!(
+            email_io.read_cai(&mut Cursor::new(&written)).unwrap(),
+            store
+        );
+
+        // the rest of the message is untouched and the part is still quoted-printable
+        let text = String::from_utf8(written).unwrap();
+        assert!(text.starts_with(&ALTERNATIVE_EMAIL[..ALTERNATIVE_EMAIL.find("<html>").unwrap()]));
+        assert!(text.ends_with("\r\n--alt-boundary--\r\n"));
+        assert!(text.contains("type=3D\"application/c2pa-manifest\""));
+        assert!(text
+            .split("\r\n")
+            .all(|line| line.len() <= MAX_ENCODED_LINE_LEN));
+
+        let html = email_io.read_html_part(&mut Cursor::new(&text)).unwrap();
+        let html = String::from_utf8(html).unwrap();
+        assert!(html.contains("<body style=\"color: red\"><p>Hello</p>"));
+    }
+
+    #[test]
+    fn test_embed_in_base64_html_part() {
+        let html = STANDARD.encode("<html><head></head><body><p>Hi</p></body></html>");
+        let message = format!(
+            "Content-Type: multipart/mixed; boundary=outer\n\n--outer\n\
+             Content-Type: multipart/alternative; boundary=inner\n\n--inner\n\
+             Content-Type: text/html\nContent-Transfer-Encoding: base64\n\n{html}\n\
+             --inner--\n\n--outer--\n"
+        );
+        let store = vec![0xa5; 300];
+        let written = embed(message.as_bytes(), &store);
+
+        assert_eq!(
+            HtmlEmailIO::default()
+                .read_cai(&mut Cursor::new(&written))
+                .unwrap(),
+            store
+        );
+        let text = String::from_utf8(written).unwrap();
+        assert!(!text.contains("c2pa-manifest")); // only inside the base64 part
+        assert!(text.lines().all(|line| line.len() <= MAX_ENCODED_LINE_LEN));
+    }
+
+    #[test]
+    fn test_message_without_html_part() {
+        let message = "Content-Type: text/plain\r\n\r\nHello\r\n";
+        let result = HtmlEmailIO::default().read_cai(&mut Cursor::new(message));
+        assert!(matches!(result, Err(Error::InvalidAsset(_))));
+    }
+
+    #[test]
+    fn test_quoted_printable_round_trip() {
+        let body = "caf\u{e9} = 100% \t\n<p style=\"x\">trailing space \n".repeat(5);
+        let encoded = encode_quoted_printable(body.as_bytes(), b"\r\n");
+        assert!(encoded
+            .split(|&b| b == b'\n')
+            .all(|line| line.len() <= MAX_ENCODED_LINE_LEN + 1));
+        assert_eq!(
+            decode_quoted_printable(&encoded),
+            body.replace('\n', "\r\n").as_bytes()
+        );
+    }
+}
@@ -1,305 +1,9177 @@
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    ops::Range,
+    path::Path,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use serde::Serialize;
+use x509_parser::pem::Pem;
+
+pub use super::html_scan::ManifestLocation;
+use super::html_scan::{
+    self, elements_named, find_ascii_ci, find_attribute, find_manifest_element, is_active_marker,
+    is_block_comment, is_manifest_type, is_tag_name_end, manifest_elements, manifest_text_in,
+    manifest_type_encoding, parse_tag_attributes, script_elements, ManifestLookup, ScriptElement,
+    C2PA_ACTIVE_ATTRIBUTE, C2PA_HEX_SCRIPT_TYPE, C2PA_SCRIPT_TYPE, CDATA_CLOSE, CDATA_OPEN,
+    JSON_SCRIPT_TYPE, SCRIPT_CLOSE, SCRIPT_OPEN, SENTINEL_BEGIN, SENTINEL_END, TEMPLATE_CLOSE,
+    TEMPLATE_OPEN,
+};
 use crate::{
-    asset_io::{rename_or_move, AssetIO, CAIRead, CAIReadWrite, CAIReader, CAIWriter,
-        HashObjectPositions, HashBlockObjectType},
+    assertions::DataHash,
+    asset_io::{
+        rename_or_move, AssetIO, CAIRead, CAIReadWrite, CAIReader, CAIWriter, ComposedManifestRef,
+        HashBlockObjectType, HashObjectPositions,
+    },
+    crypto::cose::CertificateTrustPolicy,
     error::{Error, Result},
+    jumbf::boxes::{BMFFBox, Cai},
+    status_tracker::StatusTracker,
+    store::Store,
     utils::{
-        io_utils::{tempfile_builder},
+        hash_utils::{hash_stream_by_alg, HashRange},
+        io_utils::tempfile_builder,
+        mime::format_from_path,
+        xmp_inmemory_utils::{add_provenance, extract_provenance},
     },
-};
-use std::{
-    fs::{File},
-    path::{Path},
+    Builder, Reader, Signer, ValidationState,
 };
 
-use base64::engine::general_purpose::STANDARD;
-use base64::Engine;
+static SUPPORTED_TYPES: [&str; 6] = [
+    "html",
+    "htm",
+    "text/html",
+    "xhtml",
+    "xht",
+    "application/xhtml+xml",
+];
 
-use regex::Regex;
+// names of the manifest encodings, see [`ManifestEncoding::name`]
+static SUPPORTED_ENCODINGS: [&str; 2] = ["base64", "hex"];
 
-static SUPPORTED_TYPES: [&str; 2] = [
-    "html",
-    "text/html"
+// <object data="data:application/c2pa;base64,BASE64_ENCODED_MANIFEST"> // read with HtmlIoConfig::read_data_uris
+const C2PA_DATA_URI_TYPE: &str = "application/c2pa";
+
+// `rel` of the <link rel="c2pa-manifest" href="MANIFEST_URL"> element referencing a remote manifest
+const C2PA_LINK_REL: &str = "c2pa-manifest";
+
+// tag markers used by the tokenizer, the script and template markers are in `html_scan`
+const LINK_OPEN: &[u8] = b"<link";
+const BASE_OPEN: &[u8] = b"<base";
+const IFRAME_OPEN: &[u8] = b"<iframe";
+const OBJECT_OPEN: &[u8] = b"<object";
+const EMBED_OPEN: &[u8] = b"<embed";
+const IMG_OPEN: &[u8] = b"<img";
+const META_OPEN: &[u8] = b"<meta";
+const TITLE_OPEN: &[u8] = b"<title";
+const TITLE_CLOSE: &[u8] = b"</title";
+
+// XHTML markers, manifests in XHTML documents are wrapped in a CDATA section
+const XML_DECLARATION: &str = "<?xml";
+const DOCTYPE_OPEN: &[u8] = b"<!doctype";
+
+// markers of an XMP packet embedded in the document
+const XPACKET_BEGIN: &str = "<?xpacket begin=";
+const XPACKET_END: &str = "<?xpacket end=";
+
+// <meta> names and properties that are mapped to Dublin Core properties, see
+// `HtmlIoConfig::synthesize_meta_xmp`
+const META_XMP_PROPERTIES: [(&str, &str); 7] = [
+    ("og:title", "dc:title"),
+    ("author", "dc:creator"),
+    ("article:author", "dc:creator"),
+    ("og:description", "dc:description"),
+    ("description", "dc:description"),
+    ("og:site_name", "dc:publisher"),
+    ("og:url", "dc:source"),
 ];
 
-// type of the later into the HTML injected script tag
-const C2PA_SCRIPT_TYPE: &str = "application/c2pa-manifest";
+// tags the manifest is placed at, matched case insensitively
+const HEAD_OPEN: &[u8] = b"<head";
+const BODY_OPEN: &[u8] = b"<body";
+const BODY_CLOSE: &[u8] = b"</body>";
+const HTML_CLOSE: &[u8] = b"</html";
 
-// Regex patterns
-/// <script type="application/c2pa-manifest">BASE64_ENCODED_MANIFEST</script> // this is the format
-const C2PA_REGEX_CAPTURE: &str = r#"(?s)<script[^>]*type=["']application/c2pa-manifest["'][^>]*>(.*?)</script>"#;
-const C2PA_REGEX_FULL: &str = r#"(?s)\s*<script[^>]*type=["']application/c2pa-manifest["'][^>]*>.*?</script>\s*"#;
-const HTML_HEAD_TAG: &str = r#"(?i)<head[^>]*>"#;
+// bytes around a manifest block that are searched for sentinel comments when removing
+const SENTINEL_WINDOW: usize = 1024;
 
 static DEBUG: bool = false;
 
-pub struct HtmlIO {}
+/// Options controlling how [`HtmlIO`] embeds manifests into HTML documents.
+#[derive(Clone, Debug, Default)]
+pub struct HtmlIoConfig {
+    /// Text of an HTML comment written directly in front of the manifest script, for example
+    /// `C2PA manifest: verify at https://example.com/verify`.
+    ///
+    /// A comment with this text (or a `<!--c2pa-begin-->` sentinel) that directly precedes
+    /// the manifest script (with no whitespace in between) is treated as part of the manifest
+    /// block, it is replaced when a new manifest is written and dropped when the manifest is
+    /// removed. Any other comment in front of the script is left alone. The comment is not
+    /// excluded from the hash.
+    pub manifest_comment: Option<String>,
 
+    /// Prefix of the `id` attribute given to written manifest scripts, for example
+    /// `c2pa-manifest` produces `id="c2pa-manifest-1"`.
+    ///
+    /// A replaced manifest keeps its id, a new manifest gets the next number after the
+    /// highest one already used in the document.
+    pub manifest_id_prefix: Option<String>,
 
-impl CAIReader for HtmlIO {
-    
-    /// read manifest data from HTML stream with embedded manifest
-    fn read_cai(&self, asset_reader: &mut dyn CAIRead) -> Result<Vec<u8>> {
-        if DEBUG { println!("read_cai"); }
-        
-        let (manifest_opt, _insertion_point) = detect_manifest_location(asset_reader)?;
+    /// Log a warning for every other `<script>` element when writing a manifest.
+    ///
+    /// Everything but the manifest is covered by the hard binding, so scripts that are
+    /// swapped or rewritten after signing (ad tags, analytics snippets) break validation.
+    pub warn_hashed_scripts: bool,
 
-        match manifest_opt {
-            Some(data) if !data.is_empty() => Ok(data),
-            _ => Err(Error::JumbfNotFound),
+    /// Also look for manifests inside the `srcdoc` documents of `<iframe>` elements, as used
+    /// by email and embed scenarios, when the document itself has none.
+    ///
+    /// Only reading is supported, `srcdoc` documents are never written to.
+    pub read_iframe_srcdoc: bool,
+
+    /// Also look for manifests in `data:application/c2pa;base64,...` URIs referenced by the
+    /// `data` attribute of `<object>` or the `src` attribute of `<embed>` and `<img>`
+    /// elements, when the document has no manifest script.
+    ///
+    /// Only reading is supported, manifests are always written as manifest scripts.
+    pub read_data_uris: bool,
+
+    /// Decode the character references (`&lt;`, `&amp;`, ...) of the whole document before
+    /// looking for the manifest in `read_cai`, for signed documents that are displayed
+    /// escaped, e.g. inside a `<pre>` element of a documentation page.
+    ///
+    /// Only reading is supported, the hard binding covers the unescaped document.
+    pub read_escaped_html: bool,
+
+    /// Make `read_xmp` return a minimal XMP packet synthesized from `<meta>` tags
+    /// (`og:title`, `author`, `og:description`, ...) when the document has no XMP packet.
+    ///
+    /// This gives a fallback author or source hint, it is off by default since the result
+    /// is not XMP written by the author of the document.
+    pub synthesize_meta_xmp: bool,
+
+    /// Markup rules used when writing manifests, taken from the asset type or detected from
+    /// the document when `None`.
+    pub markup_mode: Option<MarkupMode>,
+
+    /// Largest document accepted, in bytes. Reading a larger document fails with
+    /// [`Error::InvalidAsset`] (`"document too large"`) as soon as the stream reports or
+    /// delivers more bytes, before it is buffered completely.
+    pub max_document_bytes: Option<usize>,
+
+    /// Largest decoded manifest store accepted when reading, in bytes. Larger manifests are
+    /// rejected with [`Error::ManifestTooLarge`] before they reach the JUMBF parser.
+    pub max_decoded_bytes: Option<usize>,
+
+    /// Encoding of the manifest bytes in written manifest scripts. Reading accepts both
+    /// encodings, they are told apart by the script type.
+    pub manifest_encoding: ManifestEncoding,
+
+    /// Break the text of written manifest scripts into lines of this many characters for
+    /// readable diffs, e.g. `Some(76)` as in MIME. The line breaks are part of the region
+    /// excluded from the hard binding and are ignored when reading. Manifests in a
+    /// [`Self::json_bootstrap`] script are never wrapped, `None` and `Some(0)` write a
+    /// single line.
+    pub wrap_base64: Option<usize>,
+
+    /// Normalization applied to the document before the manifest is inserted, for example
+    /// to strip tracking scripts, so the signed bytes are the canonical ones.
+    ///
+    /// Signing writes the document more than once (first with a placeholder manifest), so the
+    /// transform must be deterministic and give the same result when applied to its own
+    /// output, otherwise the signed hash will not match.
+    pub pre_write_transform: Option<PreWriteTransform>,
+
+    /// Remove the HTML comments of the document before the manifest is inserted (and
+    /// before [`Self::pre_write_transform`]).
+    ///
+    /// Comments are part of the hashed document, so a publisher that strips them in
+    /// production breaks the binding of a document signed with them. With this option the
+    /// signed bytes are the stripped ones and stay valid through such a pipeline. Text that
+    /// looks like a comment inside `<script>` or `<style>` is kept. A
+    /// [`Self::manifest_comment`] would be stripped by the pipeline as well, so it should
+    /// not be combined with this option.
+    pub strip_comments: bool,
+
+    /// Quote character used for the attributes of written manifest scripts. Reading accepts
+    /// either style.
+    pub quote_style: QuoteStyle,
+
+    /// Read and write the manifest as a base64 string field of an inline
+    /// `<script type="application/json">` bootstrap script instead of a manifest script.
+    ///
+    /// `read_cai` falls back to manifest scripts when the document has no such field.
+    /// `write_cai` replaces the field value in place, adds the field when it is missing and
+    /// adds the script when there is none. The field is always base64,
+    /// [`HtmlIoConfig::manifest_encoding`], the comment and the id prefix do not apply.
+    pub json_bootstrap: Option<JsonBootstrap>,
+
+    /// Put an inserted manifest script on its own line, indented one level deeper than its
+    /// parent with the indentation (tabs or the space width) and newline style the document
+    /// already uses. A replaced manifest keeps the white space around it.
+    pub match_indentation: bool,
+
+    /// Name of a custom element holding written manifests instead of `<script>`, for content
+    /// security policies that block every script, e.g. `c2pa-manifest` gives
+    /// `<c2pa-manifest type="application/c2pa-manifest">BASE64</c2pa-manifest>`.
+    ///
+    /// The name must be a valid custom element name (lowercase, starting with a letter and
+    /// containing a `-`). Reading finds manifests in this element as well as in `<script>`.
+    pub manifest_element: Option<String>,
+
+    /// Which manifest is read, replaced and removed when the document embeds several.
+    pub active_manifest: ActiveManifest,
+
+    /// Largest number of markup tokens (tags, comments and declarations) in a document that
+    /// is tokenized. Reading a document with more fails with [`Error::InvalidAsset`]
+    /// (`"document too complex"`) before it is tokenized, so deeply nested documents cannot
+    /// keep a server busy.
+    pub max_tokens: Option<usize>,
+
+    /// Require the manifest to be inside `<head>` when verifying. A manifest in `<body>` or
+    /// appended to the document fails [`HtmlIO::verify_binding`] with
+    /// [`Error::ManifestNotInHead`].
+    pub require_head_placement: bool,
+
+    /// Keep the attributes of a replaced manifest element that are not written by this
+    /// handler, for example `data-cfasync="false"` added by a CDN. By default a replaced
+    /// manifest is written with a fresh open tag. Reading ignores such attributes either way.
+    pub keep_manifest_attributes: bool,
+
+    /// How far the signature time stamp may lie ahead of the local clock before
+    /// [`HtmlIO::signature_timestamp`] reports it as [`TimestampReport::in_future`]. Time stamps
+    /// are not compared with the clock when `None`.
+    pub timestamp_tolerance: Option<std::time::Duration>,
+
+    /// PEM certificates of the only signers trusted by [`HtmlIO::verify_trust`] and
+    /// [`FileVerification::trusted`], for closed ecosystems that accept specific signing
+    /// certificates only. The trust list of the settings is used when `None`.
+    pub trust_anchors: Option<String>,
+
+    /// Replace invalid UTF-8 sequences with U+FFFD when looking for the manifest in
+    /// `read_cai`, so a few bad bytes in a scraped page do not block reading its provenance.
+    /// The manifest text itself is ASCII and comes out unchanged.
+    ///
+    /// Only reading is supported, writing always decodes the document strictly and refuses
+    /// documents with invalid bytes rather than replacing them.
+    pub lossy_utf8: bool,
+
+    /// URL written as a `<link rel="c2pa-manifest" href="...">` element into `<head>` next
+    /// to the inline manifest, for deployments that serve the manifest store from a CDN and
+    /// keep the inline copy as a fallback. An existing manifest link is pointed at the URL so
+    /// the document never references two manifests.
+    ///
+    /// The URL has to serve the bytes that are embedded inline. Verifiers read the inline
+    /// manifest first, `read_xmp` reports an absolute link as the `dcterms:provenance` of
+    /// the document so the remote manifest is used when the inline one is missing.
+    pub manifest_link: Option<String>,
+
+    /// Retry the rename that replaces the asset with the written temporary file in
+    /// `save_cai_store`, for file systems where it fails transiently, e.g. while a virus
+    /// scanner holds the file on Windows. Without a policy a failed rename falls back to
+    /// copying right away. With a policy, when the copy fails as well the temporary file is kept
+    /// and its path is part of the returned error.
+    pub rename_retry: Option<RenameRetry>,
+
+    /// Remove Server-Sent-Events framing (`data: ` line prefixes, other event fields and the
+    /// blank lines between events) before looking for the manifest in `read_cai`, for HTML
+    /// that a streaming framework cached as an event stream. The data of the events is joined
+    /// back into the document, documents that do not start with an event field are read as
+    /// they are.
+    ///
+    /// Only reading is supported, the hard binding covers the unframed document.
+    pub read_sse_framed: bool,
+
+    /// Look for manifests inside `<template>` elements, for workflows that keep the manifest
+    /// inert in a `<template id="c2pa">`. A manifest in a template is then read from and
+    /// replaced within it.
+    ///
+    /// By default the content of templates is skipped: a manifest copy kept in a template is
+    /// not the active manifest and a new one is written outside of it.
+    pub read_from_template: bool,
+}
+
+impl HtmlIoConfig {
+    fn lookup(&self) -> ManifestLookup<'_> {
+        ManifestLookup {
+            element: self.manifest_element.as_deref(),
+            active: self.active_manifest,
+            templates: self.read_from_template,
+            comment: self.manifest_comment.as_deref(),
         }
     }
+}
+
+/// Which of several embedded manifests is the active one, see
+/// [`HtmlIoConfig::active_manifest`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ActiveManifest {
+    /// The manifest element marked with `data-c2pa-active="true"`, otherwise the last one.
+    #[default]
+    MarkedOrLast,
+    /// The first manifest, markers are ignored.
+    First,
+    /// The last manifest, markers are ignored.
+    Last,
+}
 
-    /// HTML contains no xmp
-    fn read_xmp(&self, _reader: &mut dyn CAIRead) -> Option<String> {
-        if DEBUG { println!("read_xmp"); }
-        None
+/// How often `save_cai_store` retries a failed rename, see [`HtmlIoConfig::rename_retry`].
+///
+/// A rename across devices is not retried, the file is copied over the asset (truncating
+/// it) instead. The copy is also the last resort when all retries failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenameRetry {
+    /// Number of retries after the first failed rename.
+    pub retries: u32,
+    /// Wait before the first retry, doubled for each further one.
+    pub backoff: std::time::Duration,
+}
+
+impl RenameRetry {
+    /// Creates a policy of `retries` retries starting with a wait of `backoff`.
+    pub fn new(retries: u32, backoff: std::time::Duration) -> Self {
+        RenameRetry { retries, backoff }
     }
 }
 
-impl CAIWriter for HtmlIO {
+/// A JSON bootstrap script holding the manifest, see [`HtmlIoConfig::json_bootstrap`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JsonBootstrap {
+    /// The `id` attribute of the script, for example `__C2PA__`.
+    pub script_id: String,
+    /// Name of the top-level field holding the base64 encoded manifest store.
+    pub field: String,
+}
 
-    /// embed the base64 encoded manifest bytes inside a script tag into the HTML stream
-    fn write_cai(
-        &self,
-        input_stream: &mut dyn CAIRead,
-        output_stream: &mut dyn CAIReadWrite,
-        store_bytes: &[u8],
-    ) -> Result<()> {
-        if DEBUG { println!("write_cai"); }
+impl JsonBootstrap {
+    /// Creates the settings for the script with the given `id` and field name.
+    pub fn new(script_id: impl Into<String>, field: impl Into<String>) -> Self {
+        JsonBootstrap {
+            script_id: script_id.into(),
+            field: field.into(),
+        }
+    }
+}
 
-        let mut input_html = String::new();
-        input_stream.rewind()?;
-        input_stream.read_to_string(&mut input_html)?;
+/// Quote character for attribute values, see [`HtmlIoConfig::quote_style`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QuoteStyle {
+    /// `type="application/c2pa-manifest"`
+    #[default]
+    Double,
+    /// `type='application/c2pa-manifest'`
+    Single,
+}
+
+impl QuoteStyle {
+    fn quote(&self) -> char {
+        match self {
+            QuoteStyle::Double => '"',
+            QuoteStyle::Single => '\'',
+        }
+    }
+}
 
-        let manifest_b64 = STANDARD.encode(store_bytes); // encode the manifest bytes in base64 to ensure the HTML will not break
-        let manifest_script = format!(r#"<script type="{C2PA_SCRIPT_TYPE}">{manifest_b64}</script>"#);
+/// A document transform for [`HtmlIoConfig::pre_write_transform`].
+#[derive(Clone)]
+pub struct PreWriteTransform(pub std::sync::Arc<dyn Fn(&str) -> String + Send + Sync>);
 
-        // Regex to match optional whitespace before </body>
-        let re_body = Regex::new(r"(?i)\s*</body>").unwrap();
+impl PreWriteTransform {
+    /// Wraps a transform function.
+    pub fn new(transform: impl Fn(&str) -> String + Send + Sync + 'static) -> Self {
+        PreWriteTransform(std::sync::Arc::new(transform))
+    }
+}
 
-        let re = regex::Regex::new(C2PA_REGEX_FULL)
-            .map_err(|_| Error::InvalidAsset("Regex error".into()))?;
+impl std::fmt::Debug for PreWriteTransform {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("PreWriteTransform")
+    }
+}
 
-        let updated_html = if re.is_match(&input_html) { // replace any existing c2pa script tag and manifest
-            re.replace(&input_html, &manifest_script).into_owned()
-        } else if re_body.is_match(&input_html) {
-            // Case 2: Insert before </body>, removing leading whitespace
-            re_body
-                .replace(&input_html, format!("{manifest_script}</body>"))
-                .into_owned()
-        } else {
-            // fallback: if HTML is broken insert manifest at the end
-            let trimmed = input_html.trim_end();
-            format!("{}{}", trimmed, manifest_script)
-        };
+/// Text encoding of the manifest store inside the manifest script.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ManifestEncoding {
+    /// Base64, in a `<script type="application/c2pa-manifest">`.
+    #[default]
+    Base64,
+    /// Lowercase hex, in a `<script type="application/c2pa-manifest+hex">`.
+    Hex,
+}
 
-        output_stream.rewind()?;
-        output_stream.write_all(updated_html.as_bytes())?;
-        Ok(())
+impl ManifestEncoding {
+    /// The name of the encoding as listed by [`HtmlIO::supported_encodings`].
+    pub fn name(&self) -> &'static str {
+        match self {
+            ManifestEncoding::Base64 => "base64",
+            ManifestEncoding::Hex => "hex",
+        }
     }
 
-    /// locate the position of the embedded manifest to exclude it from hashing
-    fn get_object_locations_from_stream(
-        &self,
-        input_stream: &mut dyn CAIRead,
-    ) -> Result<Vec<HashObjectPositions>> {
-        if DEBUG { println!("get_object_locations_from_stream"); }
-        
-        let mut buffer: Vec<u8> = Vec::new();
-        {
-            let mut output_stream = std::io::Cursor::new(&mut buffer);
-            // make sure the input stream is in the correct format (contains at least a dummy manifest)
-            add_required_segs_to_stream(input_stream, &mut output_stream)?;
+    /// The encoding with the given name (ASCII case insensitive), see [`Self::name`].
+    pub fn from_name(name: &str) -> Option<Self> {
+        [ManifestEncoding::Base64, ManifestEncoding::Hex]
+            .into_iter()
+            .find(|encoding| encoding.name().eq_ignore_ascii_case(name))
+    }
+
+    fn script_type(&self) -> &'static str {
+        match self {
+            ManifestEncoding::Base64 => C2PA_SCRIPT_TYPE,
+            ManifestEncoding::Hex => C2PA_HEX_SCRIPT_TYPE,
         }
+    }
 
-        let mut buffer_cursor = std::io::Cursor::new(&buffer);
-        let (manifest_opt, insertion_point) =
-            detect_manifest_location(&mut buffer_cursor)?;
+    fn encode(&self, store_bytes: &[u8]) -> String {
+        match self {
+            ManifestEncoding::Base64 => STANDARD.encode(store_bytes),
+            ManifestEncoding::Hex => hex::encode(store_bytes),
+        }
+    }
 
-        let manifest = manifest_opt.ok_or(Error::JumbfNotFound)?;
-        let b64_len = STANDARD.encode(&manifest).len(); // length of only the manifest data (without script tag)
-        let start = insertion_point; // insertion point is the start of the base64 encoded manifest in the html stream
-        let html_len = buffer.len();
-
-        Ok(vec![
-            HashObjectPositions {
-                offset: start,
-                length: b64_len,
-                htype: HashBlockObjectType::Cai, // this will be excluded from hashing
-            },
-            HashObjectPositions {
-                offset: 0,
-                length: start,
-                htype: HashBlockObjectType::Other, // part before manifest
-            },
-            HashObjectPositions {
-                offset: start + b64_len,
-                length: html_len.saturating_sub(start + b64_len), // until the end of the stream
-                htype: HashBlockObjectType::Other, // part after manifest
-            },
-        ])
+    /// length of the encoded text of `len` manifest bytes
+    fn encoded_len(&self, len: usize) -> usize {
+        match self {
+            ManifestEncoding::Base64 => len.div_ceil(3) * 4,
+            ManifestEncoding::Hex => len * 2,
+        }
     }
 
-    /// remove the manifest from the html stream
-    fn remove_cai_store_from_stream(
-        &self,
-        input_stream: &mut dyn CAIRead,
-        output_stream: &mut dyn CAIReadWrite,
-    ) -> Result<()> {
-        if DEBUG { println!("remove_cai_store_from_stream"); }
+    fn decode(&self, encoded: &str) -> Result<Vec<u8>> {
+        // wrapped manifests, see [`HtmlIoConfig::wrap_base64`]
+        let encoded: Cow<'_, str> = match encoded.bytes().any(|b| b.is_ascii_whitespace()) {
+            true => Cow::Owned(encoded.split_ascii_whitespace().collect()),
+            false => Cow::Borrowed(encoded),
+        };
+        let encoded = encoded.as_ref();
+        match self {
+            ManifestEncoding::Base64 => STANDARD
+                .decode(encoded)
+                .map_err(|_| Error::InvalidAsset("HTML manifest bad base64 encoding".into())),
+            ManifestEncoding::Hex => hex::decode(encoded)
+                .map_err(|_| Error::InvalidAsset("HTML manifest bad hex encoding".into())),
+        }
+    }
+}
+
+/// Markup rules applied when writing a manifest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarkupMode {
+    /// HTML syntax, the manifest is written as plain script text.
+    Html,
+    /// XHTML (XML) syntax, the manifest is wrapped in a CDATA section so the document stays
+    /// well-formed.
+    Xhtml,
+}
 
-        let mut html = String::new();
-        input_stream.read_to_string(&mut html)?;
+/// Result of [`HtmlIO::binding_report`], serialized as
+/// `{"binding_ok": false, "first_diff": 1234}` for JavaScript callers.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct BindingReport {
+    /// `true` when the document bytes still match what was signed.
+    pub binding_ok: bool,
+    /// Offset of the first difference when it could be located, see
+    /// [`HtmlIO::verify_binding`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_diff: Option<usize>,
+}
 
-        let re = regex::Regex::new(C2PA_REGEX_FULL)
-            .map_err(|_| Error::InvalidAsset("Regex error".into()))?;
+/// What [`HtmlIO::write_cai_with_report`] found in the document it wrote to.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WriteReport {
+    /// `true` when a non empty manifest that could not be decoded to a manifest store was
+    /// replaced, which may be a sign of tampering. `write_cai` logs a warning for it.
+    pub replaced_undecodable_manifest: bool,
+}
 
-        let cleaned = re.replace(&html, "").into_owned(); // remove the script tag from the stream
+/// Binding status of one of the manifests in a document, see [`HtmlIO::validate_all`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ManifestBindingReport {
+    /// The `id` attribute of the manifest script, if present.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// `true` when the document bytes hashed by this manifest still match what was signed.
+    pub binding_ok: bool,
+    /// Organization of the certificate that signed the manifest, if it names one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+    /// Offset of the first difference when the binding failed and it could be located.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_diff: Option<usize>,
+    /// Why the manifest could not be checked, e.g. it is empty, not valid base64 or not a
+    /// manifest store. `binding_ok` is `false` then.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
 
-        output_stream.rewind()?;
-        output_stream.write_all(cleaned.as_bytes())?;
-        Ok(())
-    }
+/// The signature time stamp of the active manifest, see [`FileVerification::timestamp`] and
+/// [`HtmlIO::signature_timestamp`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct TimestampReport {
+    /// The time of the RFC 3161 time stamp of the signature, `None` when the signature has
+    /// no time stamp.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time: Option<DateTime<Utc>>,
+    /// `true` when the time lies further ahead of the local clock than
+    /// [`HtmlIoConfig::timestamp_tolerance`].
+    pub in_future: bool,
+    /// `true` when the time is outside the validity period of the signing certificate.
+    pub outside_validity: bool,
 }
 
-impl AssetIO for HtmlIO {
-    fn new(_asset_type: &str) -> Self {
-        if DEBUG { println!("new"); }
+/// A manifest and the manifests of its ingredients, see [`HtmlIO::provenance_tree`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ProvenanceNode {
+    /// The label of the manifest, `None` for an ingredient that has no manifest.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// The title of the manifest or, for an ingredient, the title of the ingredient.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// `false` when validation reported a failure for this manifest or ingredient.
+    pub valid: bool,
+    /// The ingredients in the order the manifest lists them.
+    pub children: Vec<ProvenanceNode>,
+}
+
+/// The verification result of one file, see [`HtmlIO::verify_directory`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct FileVerification {
+    /// The path of the file.
+    pub path: std::path::PathBuf,
+    /// `true` when the file embeds a manifest.
+    pub has_manifest: bool,
+    /// `true` when the file has a manifest and validation reported no failure for it.
+    pub valid: bool,
+    /// `true` when the signer of the active manifest is trusted, see [`HtmlIO::verify_trust`].
+    pub trusted: bool,
+    /// The label of the active manifest.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_label: Option<String>,
+    /// The signature time stamp of the active manifest, taken from the same verification.
+    pub timestamp: TimestampReport,
+    /// The error that stopped the verification of the file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Page metadata returned by [`HtmlIO::extract_page_metadata`], for example to be added to
+/// the manifest as metadata assertions when signing.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PageMeta {
+    /// Text of the `<title>` element, with white space collapsed.
+    pub title: Option<String>,
+    /// Content of `<meta name="description">`, or of `og:description` when there is none.
+    pub description: Option<String>,
+    /// The `href` of `<link rel="canonical">` as written in the document.
+    pub canonical_url: Option<String>,
+}
+
+/// A manifest store embedded in an HTML document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EmbeddedManifest {
+    /// The `id` attribute of the manifest script, if present.
+    pub id: Option<String>,
+    /// The decoded manifest store bytes.
+    pub store_bytes: Vec<u8>,
+}
+
+/// A `<script>` element of an HTML document, see [`HtmlIO::script_ranges`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScriptInfo {
+    /// The `type` attribute as written, `None` for classic scripts without one.
+    pub script_type: Option<String>,
+    /// Byte range of the element in the stream, from its open tag to the end of its end tag.
+    pub range: Range<usize>,
+    /// `true` when the element has a `nonce` attribute.
+    pub has_nonce: bool,
+    /// `true` for the manifest script, including the [`HtmlIoConfig::json_bootstrap`] script.
+    pub is_manifest: bool,
+}
 
-        HtmlIO {}
+/// Where the manifest is, or would be written, in an HTML document.
+///
+/// Documents without a manifest get one in front of `</body>` when there is one, or where a
+/// `<body>` whose end tag is omitted ends, otherwise directly after the `<head>` open tag,
+/// otherwise it is appended to the end of the document.
+/// Fragments without `<html>`, `<head>` or `<body>` (for example a `<div>` partial) are
+/// supported this way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ManifestPlacement {
+    /// An existing manifest script, `offset` is the start of its encoded content.
+    Existing { offset: usize },
+    /// Inserted directly after the `<head>` open tag that ends at `offset`, the first one
+    /// when a malformed document has several.
+    Head { offset: usize },
+    /// Inserted in front of `</body>`, `offset` is the start of the whitespace before it.
+    /// Without the end tag the body ends in front of `</html>` or at the end of the document.
+    Body { offset: usize },
+    /// Appended at `offset`, the end of the document without its trailing whitespace.
+    Append { offset: usize },
+}
+
+/// Where [`HtmlIO::relocate_manifest`] moves a manifest to, see [`ManifestPlacement`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlacementTarget {
+    /// Directly after the first `<head>` open tag.
+    Head,
+    /// In front of `</body>`.
+    Body,
+    /// At the end of the document.
+    Append,
+}
+
+impl MarkupMode {
+    /// The markup implied by an asset type, `None` for the HTML types since documents
+    /// served as HTML may still be XHTML.
+    pub fn from_asset_type(asset_type: &str) -> Option<Self> {
+        match asset_type.to_lowercase().as_str() {
+            "xhtml" | "xht" | "application/xhtml+xml" => Some(MarkupMode::Xhtml),
+            _ => None,
+        }
     }
 
-    fn get_handler(&self, asset_type: &str) -> Box<dyn AssetIO> {
-        if DEBUG { println!("get_handler"); }
+    /// Detects the markup of a document, documents starting with an XML declaration or
+    /// using one of the XHTML doctypes are XHTML, everything else (including the HTML5
+    /// `<!DOCTYPE html>`) is HTML.
+    pub fn detect(html: &str) -> Self {
+        let prolog = html.trim_start_matches(['\u{feff}', ' ', '\t', '\r', '\n']);
+        if prolog.starts_with(XML_DECLARATION) {
+            return MarkupMode::Xhtml;
+        }
 
-        Box::new(HtmlIO::new(asset_type))
+        let bytes = prolog.as_bytes();
+        let doctype = find_ascii_ci(bytes, DOCTYPE_OPEN, 0)
+            .filter(|start| find_ascii_ci(bytes, b"<html", 0).is_none_or(|html| *start < html))
+            .and_then(|start| {
+                let end = start + prolog[start..].find('>')?;
+                Some(&bytes[start..end])
+            });
+        match doctype {
+            Some(doctype) if find_ascii_ci(doctype, b"xhtml", 0).is_some() => MarkupMode::Xhtml,
+            _ => MarkupMode::Html,
+        }
     }
+}
 
-    fn get_reader(&self) -> &dyn CAIReader {
-        if DEBUG { println!("get_reader"); }
+impl ManifestPlacement {
+    /// The byte offset of the manifest content or of the insertion point.
+    pub fn offset(&self) -> usize {
+        match self {
+            ManifestPlacement::Existing { offset }
+            | ManifestPlacement::Head { offset }
+            | ManifestPlacement::Body { offset }
+            | ManifestPlacement::Append { offset } => *offset,
+        }
+    }
+}
 
-        self
+/// Process wide counts of the branches taken by [`HtmlIO`] handlers, see [`HtmlIO::stats`].
+///
+/// Placements are counted for every manifest written with a manifest script, detections
+/// for every detection pass. Signing runs several of those, so the detection counts are
+/// best compared with each other rather than with the number of documents.
+#[cfg(feature = "html_stats")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Writes that replaced an existing manifest.
+    pub replaced: u64,
+    /// Writes that inserted the manifest into `<head>`.
+    pub head: u64,
+    /// Writes that inserted the manifest before `</body>`.
+    pub body: u64,
+    /// Writes that appended the manifest to the document.
+    pub appended: u64,
+    /// Detections that found a manifest inside `<head>`.
+    pub detected_in_head: u64,
+    /// Detections that found a manifest outside of `<head>`.
+    pub detected_elsewhere: u64,
+    /// Detections that found no manifest.
+    pub not_detected: u64,
+}
+
+#[cfg(feature = "html_stats")]
+struct StatCounters {
+    replaced: std::sync::atomic::AtomicU64,
+    head: std::sync::atomic::AtomicU64,
+    body: std::sync::atomic::AtomicU64,
+    appended: std::sync::atomic::AtomicU64,
+    detected_in_head: std::sync::atomic::AtomicU64,
+    detected_elsewhere: std::sync::atomic::AtomicU64,
+    not_detected: std::sync::atomic::AtomicU64,
+}
+
+#[cfg(feature = "html_stats")]
+static STATS: StatCounters = StatCounters {
+    replaced: std::sync::atomic::AtomicU64::new(0),
+    head: std::sync::atomic::AtomicU64::new(0),
+    body: std::sync::atomic::AtomicU64::new(0),
+    appended: std::sync::atomic::AtomicU64::new(0),
+    detected_in_head: std::sync::atomic::AtomicU64::new(0),
+    detected_elsewhere: std::sync::atomic::AtomicU64::new(0),
+    not_detected: std::sync::atomic::AtomicU64::new(0),
+};
+
+#[cfg(feature = "html_stats")]
+fn count(counter: &std::sync::atomic::AtomicU64) {
+    counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+}
+
+#[cfg(feature = "html_stats")]
+fn count_placement(placement: ManifestPlacement) {
+    count(match placement {
+        ManifestPlacement::Existing { .. } => &STATS.replaced,
+        ManifestPlacement::Head { .. } => &STATS.head,
+        ManifestPlacement::Body { .. } => &STATS.body,
+        ManifestPlacement::Append { .. } => &STATS.appended,
+    });
+}
+
+/// Outcome of a binding check kept by [`VerificationCache`], `None` when the binding held.
+#[cfg(feature = "html_verification_cache")]
+type CachedBinding = Option<(Vec<u8>, Vec<u8>, Option<usize>)>;
+
+/// A least recently used cache of [`HtmlIO::verify_binding_cached`] results keyed by the
+/// SHA-256 of the whole document, so unchanged documents are not validated again.
+///
+/// Only completed checks are cached, documents that fail to parse are retried each time.
+#[cfg(feature = "html_verification_cache")]
+#[derive(Debug)]
+pub struct VerificationCache {
+    capacity: usize,
+    entries: std::collections::HashMap<Vec<u8>, CachedBinding>,
+    order: std::collections::VecDeque<Vec<u8>>,
+    hits: usize,
+    misses: usize,
+}
+
+#[cfg(feature = "html_verification_cache")]
+impl VerificationCache {
+    /// Creates a cache holding the results of at most `capacity` documents.
+    pub fn new(capacity: usize) -> Self {
+        VerificationCache {
+            capacity,
+            entries: std::collections::HashMap::new(),
+            order: std::collections::VecDeque::new(),
+            hits: 0,
+            misses: 0,
+        }
     }
 
-    fn get_writer(&self, _asset_type: &str) -> Option<Box<dyn CAIWriter>> {
-        if DEBUG { println!("get_writer"); }
+    /// The maximum number of documents kept.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
 
-        Some(Box::new(HtmlIO {}))
+    /// The number of documents currently cached.
+    pub fn len(&self) -> usize {
+        self.entries.len()
     }
 
-    fn read_cai_store(&self, asset_path: &Path) -> Result<Vec<u8>> {
-        if DEBUG { println!("read_cai_store: {}", asset_path.display()); }
-        
-        let mut f = File::open(asset_path)?;
-        self.read_cai(&mut f) // simply call the function that operates on the stream...
+    /// Returns `true` if no documents are cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
     }
 
-    fn save_cai_store(&self, asset_path: &Path, store_bytes: &[u8]) -> Result<()> {
-        if DEBUG { println!("save_cai_store: {}", asset_path.display()); }
-        
-        let mut input_stream = std::fs::OpenOptions::new()
-            .read(true)
-            .open(asset_path)
-            .map_err(Error::IoError)?;
-        let mut temp_file = tempfile_builder("c2pa_temp")?; // create a temp file while writing
-        self.write_cai(&mut input_stream, &mut temp_file, store_bytes)?;
-        rename_or_move(temp_file, asset_path)
+    /// The number of lookups answered from the cache.
+    pub fn hits(&self) -> usize {
+        self.hits
     }
 
-    fn get_object_locations(&self, asset_path: &Path) -> Result<Vec<HashObjectPositions>> {
-        if DEBUG { println!("get_object_locations: {}", asset_path.display()); }
-        
-        let mut input_stream = std::fs::File::open(asset_path).map_err(|_err| Error::EmbeddingError)?;
-        self.get_object_locations_from_stream(&mut input_stream)
+    /// The number of lookups that needed a full validation.
+    pub fn misses(&self) -> usize {
+        self.misses
     }
 
-    fn remove_cai_store(&self, asset_path: &Path) -> Result<()> {
-        if DEBUG { println!("remove_cai_store: {}", asset_path.display()); }
-        
-        let mut input_file = File::open(asset_path)?;
-        let mut temp_file = tempfile_builder("c2pa_temp")?;
-        self.remove_cai_store_from_stream(&mut input_file, &mut temp_file)?;
-        rename_or_move(temp_file, asset_path)
+    /// Drops every cached result.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
     }
 
-    fn supported_types(&self) -> &[&str] {
-        if DEBUG { println!("supported_types"); }
+    fn get(&mut self, key: &[u8]) -> Option<CachedBinding> {
+        let result = self.entries.get(key).cloned();
+        match result {
+            Some(_) => {
+                self.hits += 1;
+                if let Some(pos) = self.order.iter().position(|k| k == key) {
+                    if let Some(k) = self.order.remove(pos) {
+                        self.order.push_back(k);
+                    }
+                }
+            }
+            None => self.misses += 1,
+        }
+        result
+    }
 
-        &SUPPORTED_TYPES
+    fn insert(&mut self, key: Vec<u8>, value: CachedBinding) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.entries.len() >= self.capacity {
+            match self.order.pop_front() {
+                Some(oldest) => self.entries.remove(&oldest),
+                None => break,
+            };
+        }
+        self.order.push_back(key.clone());
+        self.entries.insert(key, value);
     }
 }
 
-/// prepare the html stream by including a dummy manifest if no manifest is present
-fn add_required_segs_to_stream(
-    input_stream: &mut dyn CAIRead,
-    output_stream: &mut dyn CAIReadWrite,
-) -> Result<()> {
-    if DEBUG { println!("add_required_segs_to_stream"); }
+/// Reads and writes manifests embedded in HTML documents.
+///
+/// Use [`HtmlIO::default`] for the default handler or [`HtmlIO::with_config`] to customize
+/// it. The asset type given to [`AssetIO::new`] only selects the markup rules, XHTML types
+/// (`application/xhtml+xml`, `xhtml`, `xht`) always use [`MarkupMode::Xhtml`] while for the
+/// HTML types the rules are detected from the document.
+///
+/// Reading (`read_cai`, `read_xmp`, [`HtmlIO::has_manifest`] and the other read methods)
+/// only seeks and reads the source, it is never written to or truncated.
+///
+/// When the document already holds a manifest whose encoded content has exactly the length
+/// of the new one (in the configured [`ManifestEncoding`]), `write_cai` overwrites just
+/// those bytes and leaves the rest of the document, including the comment and `id` of the
+/// script, untouched. This supports reserving the manifest space in a first pass and
+/// filling it after hashing the rest of the document.
+///
+/// Manifest scripts inside `<template>` elements are skipped unless
+/// [`HtmlIoConfig::read_from_template`] is set.
+#[derive(Clone, Debug, Default)]
+pub struct HtmlIO {
+    config: HtmlIoConfig,
+    asset_type_mode: Option<MarkupMode>, // markup implied by the asset type
+}
 
-    let (encoded_manifest_opt, _insertion_point) =
-        detect_manifest_location(input_stream)?;
+impl HtmlIO {
+    /// Creates an HTML handler using the given configuration.
+    pub fn with_config(config: HtmlIoConfig) -> Self {
+        HtmlIO {
+            config,
+            asset_type_mode: None,
+        }
+    }
 
-    let need_manifest = if let Some(encoded_manifest) = encoded_manifest_opt {
-        encoded_manifest.is_empty() // if there is already a manifest and it is not empty we don't need one
-    } else {
-        true
-    };
+    /// The configuration used by this handler.
+    pub fn config(&self) -> &HtmlIoConfig {
+        &self.config
+    }
 
-    if need_manifest {
-        // Placeholder manifest to be inserted into HTML
-        let data: &str = "placeholder manifest";
+    /// Names of the manifest encodings this build can read and write, see
+    /// [`ManifestEncoding::from_name`].
+    pub fn supported_encodings() -> &'static [&'static str] {
+        &SUPPORTED_ENCODINGS
+    }
 
-        let html = HtmlIO::new("html");
-        let html_writer = html.get_writer("html").ok_or(Error::UnsupportedType)?;
+    /// Reads every manifest embedded in the document in document order, along with the
+    /// `id` of its script where present.
+    pub fn read_manifests(&self, input_stream: &mut dyn CAIRead) -> Result<Vec<EmbeddedManifest>> {
+        let html = read_html(input_stream, &self.config)?;
 
-        html_writer.write_cai(input_stream, output_stream, data.as_bytes())?;
-    } else {
-        // Just clone the input to the output
-        input_stream.rewind()?;
-        output_stream.rewind()?;
-        std::io::copy(input_stream, output_stream)?;
+        let mut manifests =
+            embedded_manifests(&html, self.config.max_decoded_bytes, self.config.lookup())?;
+        if manifests.is_empty() && self.config.read_data_uris {
+            manifests = data_uri_manifests(&html, self.config.max_decoded_bytes)?;
+        }
+        if manifests.is_empty() && self.config.read_iframe_srcdoc {
+            for srcdoc in srcdoc_documents(&html) {
+                manifests.extend(embedded_manifests(
+                    &srcdoc,
+                    self.config.max_decoded_bytes,
+                    self.config.lookup(),
+                )?);
+            }
+        }
+        Ok(manifests)
     }
 
-    Ok(())
-}
+    /// Returns the embedded manifest store for writing as a `.c2pa` sidecar, or `None` if the
+    /// document has no manifest. Use `remove_cai_store_from_stream` afterwards to de-embed it.
+    pub fn extract_to_sidecar(&self, input_stream: &mut dyn CAIRead) -> Result<Option<Vec<u8>>> {
+        match self.read_cai(input_stream) {
+            Ok(store_bytes) => Ok(Some(store_bytes)),
+            Err(Error::JumbfNotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
 
-/// find the location of the manifest inside the html stream
-/// returns the manifest_opt and the location of the manifest content (not the location of the script tag)
-fn detect_manifest_location(
-    input_stream: &mut dyn CAIRead,
-) -> Result<(Option<Vec<u8>>, usize)> {
-    if DEBUG { println!("detect_manifest_location"); }
+    /// Returns `true` if the document contains a non-empty manifest script, the manifest is
+    /// not decoded.
+    pub fn has_manifest(&self, input_stream: &mut dyn CAIRead) -> Result<bool> {
+        let html = read_html(input_stream, &self.config)?;
 
-    input_stream.rewind()?;
+        Ok(find_manifest_element(&html, self.config.lookup())
+            .is_some_and(|element| !element.manifest_text(&html).is_empty()))
+    }
 
-    let mut html = String::new();
-    input_stream.read_to_string(&mut html)?;
+    /// Returns the encoding of the embedded manifest, or `None` when the document has no
+    /// manifest, so tools can report how a page embeds its provenance. A manifest in the
+    /// field of a [`HtmlIoConfig::json_bootstrap`] script is always base64.
+    pub fn manifest_encoding(
+        &self,
+        input_stream: &mut dyn CAIRead,
+    ) -> Result<Option<ManifestEncoding>> {
+        let (manifest, _, encoding) = detect_manifest_location(input_stream, &self.config)?;
+        Ok(manifest.map(|_| encoding))
+    }
 
-    let mut output: Option<Vec<u8>> = None;
-    let mut insertion_point: usize = 0;
+    /// Returns the base64 text of the first manifest script as stored, without surrounding
+    /// whitespace, so it can be copied to another document without decoding it. The text of
+    /// a [`ManifestEncoding::Hex`] manifest is hex.
+    pub fn read_manifest_base64(&self, input_stream: &mut dyn CAIRead) -> Result<Option<String>> {
+        let html = read_html(input_stream, &self.config)?;
 
-    // Try to capture existing manifest content
-    let manifest_re = Regex::new(C2PA_REGEX_CAPTURE).unwrap();
-    if let Some(caps) = manifest_re.captures(&html) {
-        if let Some(encoded) = caps.get(1) {
-            let trimmed = encoded.as_str().trim();
-            if !trimmed.is_empty() {
-                output = Some(STANDARD.decode(trimmed).map_err(|_| {
-                    Error::InvalidAsset("HTML manifest bad base64 encoding".into())
-                })?);
-                insertion_point = encoded.start(); // Position of base64 encoded manifest bytes (not the position of the tag)
-            }
-        }
+        Ok(find_manifest_element(&html, self.config.lookup())
+            .map(|element| html[element.manifest_text(&html)].to_owned()))
     }
 
-    // fallback if no manifest found, try to locate <head> tag for insertion -> this should NOT happen
-    if output.is_none() {
-        if DEBUG { println!("no manifest found"); }
-        let head_re = Regex::new(HTML_HEAD_TAG).unwrap();
-        if let Some(head_match) = head_re.find(&html) {
-            insertion_point = head_match.end(); // Right after the <head> tag
-        }
+    /// Returns the open tag of the first manifest element exactly as it is written, e.g.
+    /// `<script type="application/c2pa-manifest" nonce="r4nd0m">`, to audit the attributes and
+    /// quoting a page declares the manifest with, or `None` when the document has no
+    /// manifest.
+    pub fn manifest_open_tag(&self, input_stream: &mut dyn CAIRead) -> Result<Option<String>> {
+        let html = read_html(input_stream, &self.config)?;
+
+        Ok(find_manifest_element(&html, self.config.lookup())
+            .map(|element| html[element.start..element.content_start].to_owned()))
+    }
+
+    /// Returns every `<script>` element of the document in document order, the manifest and
+    /// all others, so the Content Security Policy of a signed page can be checked against
+    /// each of them.
+    pub fn script_ranges(&self, input_stream: &mut dyn CAIRead) -> Result<Vec<ScriptInfo>> {
+        let (html, text_encoding) = read_html_document(input_stream, &self.config)?;
+
+        Ok(script_elements(&html)
+            .into_iter()
+            .map(|element| {
+                let script_type = element.attribute("type");
+                let is_bootstrap = self
+                    .config
+                    .json_bootstrap
+                    .as_ref()
+                    .is_some_and(|bootstrap| {
+                        script_type.is_some_and(|t| t.trim().eq_ignore_ascii_case(JSON_SCRIPT_TYPE))
+                            && element.attribute("id") == Some(bootstrap.script_id.as_str())
+                    });
+                ScriptInfo {
+                    script_type: script_type.map(str::to_owned),
+                    range: text_encoding.byte_offset(&html, element.start)
+                        ..text_encoding.byte_offset(&html, element.end),
+                    has_nonce: element.attribute("nonce").is_some(),
+                    is_manifest: element.is_manifest() || is_bootstrap,
+                }
+            })
+            .collect())
+    }
+
+    /// Reads the manifest whose script has the given `id`.
+    pub fn read_manifest_by_id(&self, input_stream: &mut dyn CAIRead, id: &str) -> Result<Vec<u8>> {
+        self.read_manifests(input_stream)?
+            .into_iter()
+            .find(|m| m.id.as_deref() == Some(id))
+            .map(|m| m.store_bytes)
+            .ok_or(Error::JumbfNotFound)
+    }
+
+    /// Removes the manifest element at `index` in document order and keeps the others, for
+    /// example to revoke one signer of a document signed more than once.
+    pub fn remove_manifest_by_index(
+        &self,
+        input_stream: &mut dyn CAIRead,
+        output_stream: &mut dyn CAIReadWrite,
+        index: usize,
+    ) -> Result<()> {
+        self.remove_manifest_where(input_stream, output_stream, |position, _| position == index)
+    }
+
+    /// Removes the manifest whose active manifest has the given label (e.g.
+    /// `urn:uuid:...`) and keeps the others. Manifests that cannot be parsed are kept.
+    pub fn remove_manifest_by_label(
+        &self,
+        input_stream: &mut dyn CAIRead,
+        output_stream: &mut dyn CAIReadWrite,
+        label: &str,
+    ) -> Result<()> {
+        let max_decoded_bytes = self.config.max_decoded_bytes;
+        self.remove_manifest_where(input_stream, output_stream, |_, (text, encoding)| {
+            decode_manifest_content(text, encoding, max_decoded_bytes)
+                .ok()
+                .flatten()
+                .and_then(|store_bytes| {
+                    Store::from_jumbf(&store_bytes, &mut StatusTracker::default()).ok()
+                })
+                .and_then(|store| store.provenance_label())
+                .is_some_and(|l| l == label)
+        })
+    }
+
+    /// remove the block of the first manifest element `pick` selects, it is given the index
+    /// of the element and its encoded text
+    fn remove_manifest_where(
+        &self,
+        input_stream: &mut dyn CAIRead,
+        output_stream: &mut dyn CAIReadWrite,
+        mut pick: impl FnMut(usize, (&str, ManifestEncoding)) -> bool,
+    ) -> Result<()> {
+        let (mut html, text_encoding) = read_html_document(input_stream, &self.config)?;
+
+        let block = manifest_elements(&html, self.config.lookup())
+            .iter()
+            .enumerate()
+            .find(|(index, e)| pick(*index, (&html[e.manifest_text(&html)], e.encoding())))
+            .map(|(_, e)| e.block_range(&html, self.config.manifest_comment.as_deref()))
+            .ok_or(Error::JumbfNotFound)?;
+        html.replace_range(block, "");
+
+        rewind_output(output_stream)?;
+        output_stream.write_all(&text_encoding.encode(&html))?;
+        Ok(())
+    }
+
+    /// Writes the manifest into the document held by `buffer`, for callers that keep a
+    /// single buffer as both the input and the output.
+    ///
+    /// `write_cai` reads the whole input before it writes to the output, so two streams
+    /// over the same buffer work as long as the document grows. The output stream cannot be
+    /// truncated though, and when the document shrinks (a smaller manifest replaces a larger
+    /// one) its old tail would stay behind. This method sets `buffer` to exactly the written
+    /// document.
+    pub fn write_cai_in_place(&self, buffer: &mut Vec<u8>, store_bytes: &[u8]) -> Result<()> {
+        let mut output = std::io::Cursor::new(Vec::with_capacity(buffer.len()));
+        self.write_cai(
+            &mut std::io::Cursor::new(&buffer[..]),
+            &mut output,
+            store_bytes,
+        )?;
+        *buffer = output.into_inner();
+        Ok(())
     }
 
-    Ok((output, insertion_point))
-}
\ No newline at end of file
+    /// Same as `write_cai`, also reporting what happened to the manifest the document already
+    /// had, see [`WriteReport`].
+    pub fn write_cai_with_report(
+        &self,
+        input_stream: &mut dyn CAIRead,
+        output_stream: &mut dyn CAIReadWrite,
+        store_bytes: &[u8],
+    ) -> Result<WriteReport> {
+        let (input_html, text_encoding) = read_html_to_write(input_stream, &self.config)?;
+        let input_html = self.prepare_html(&input_html)?;
+
+        if self.config.warn_hashed_scripts {
+            for warning in hashed_script_warnings(&input_html) {
+                log::warn!("{warning}");
+            }
+        }
+
+        // a replaced manifest that is not a manifest store may have been tampered with
+        let replaced_undecodable_manifest =
+            find_manifest_element(&input_html, self.config.lookup()).is_some_and(|element| {
+                let text = &input_html[element.manifest_text(&input_html)];
+                !text.is_empty()
+                    && !decode_manifest_content(text, element.encoding(), None)
+                        .is_ok_and(|store| store.is_some_and(|bytes| is_jumbf(&bytes)))
+            });
+
+        let updated_html = self.updated_html(&input_html, store_bytes)?;
+
+        rewind_output(output_stream)?;
+        output_stream.write_all(&text_encoding.encode(&updated_html))?;
+        Ok(WriteReport {
+            replaced_undecodable_manifest,
+        })
+    }
+
+    /// Same as `write_cai`, but leaves `output_stream` untouched and returns `false` when the
+    /// document already embeds `store_bytes` exactly as they would be written.
+    pub fn write_cai_if_changed(
+        &self,
+        input_stream: &mut dyn CAIRead,
+        output_stream: &mut dyn CAIReadWrite,
+        store_bytes: &[u8],
+    ) -> Result<bool> {
+        let (input_html, text_encoding) = read_html_to_write(input_stream, &self.config)?;
+        let html = self.prepare_html(&input_html)?;
+        let updated_html = self.updated_html(&html, store_bytes)?;
+        // the content is compared, preparing the document copies it even when nothing changed
+        if updated_html.as_ref() == input_html.as_str() {
+            return Ok(false);
+        }
+
+        rewind_output(output_stream)?;
+        output_stream.write_all(&text_encoding.encode(&updated_html))?;
+        Ok(true)
+    }
+
+    /// the document with `store_bytes` written into it, borrowed when it is unchanged
+    fn updated_html<'a>(&self, html: &'a str, store_bytes: &[u8]) -> Result<Cow<'a, str>> {
+        match self.placeholder_range(html, store_bytes)? {
+            // fill a reserved manifest of the same size without touching the bytes around it
+            Some(range) => {
+                let encoding = match self.config.json_bootstrap {
+                    Some(_) => ManifestEncoding::Base64,
+                    None => self.config.manifest_encoding,
+                };
+                let manifest = self.encoded_manifest(encoding, store_bytes);
+                #[cfg(feature = "html_stats")]
+                count(&STATS.replaced);
+                if html[range.clone()] == manifest {
+                    return Ok(Cow::Borrowed(html));
+                }
+                let mut html = html.to_owned();
+                html.replace_range(range, &manifest);
+                Ok(Cow::Owned(html))
+            }
+            None => match &self.config.json_bootstrap {
+                Some(bootstrap) => self
+                    .write_json_manifest(html, bootstrap, store_bytes)
+                    .map(Cow::Owned),
+                None => {
+                    let placement = plan_manifest_placement(html, self.config.lookup())?;
+                    #[cfg(feature = "html_stats")]
+                    count_placement(placement);
+                    if let Some(warning) = insertion_context_warning(html, placement) {
+                        log::warn!("{warning}");
+                    }
+                    let manifest_block = self.manifest_block(html, placement, store_bytes)?;
+                    let updated =
+                        splice_manifest(html, placement, &manifest_block, self.config.lookup())?;
+                    // the written element has to be found again and removing it has to
+                    // restore the document, otherwise the insertion broke the markup around it
+                    if !matches!(placement, ManifestPlacement::Existing { .. }) {
+                        let restored = find_manifest_element(&updated, self.config.lookup())
+                            .map(|element| {
+                                element
+                                    .block_range(&updated, self.config.manifest_comment.as_deref())
+                            })
+                            .map(|block| {
+                                format!("{}{}", &updated[..block.start], &updated[block.end..])
+                            });
+                        if restored.as_deref() != Some(html) {
+                            return Err(Error::InvalidAsset(
+                                "manifest insertion would break the HTML markup".into(),
+                            ));
+                        }
+                    }
+                    Ok(updated)
+                }
+            },
+        }
+    }
+
+    /// Returns the markup rules used when writing a manifest into the document, either the
+    /// configured [`HtmlIoConfig::markup_mode`], the one implied by the asset type or the
+    /// one detected from the document.
+    pub fn markup_mode(&self, input_stream: &mut dyn CAIRead) -> Result<MarkupMode> {
+        let html = read_html(input_stream, &self.config)?;
+
+        Ok(self.markup_mode_for(&html))
+    }
+
+    /// Returns the number of bytes writing `store_bytes` into the document would add, a
+    /// replaced manifest and the whitespace dropped around the insertion point are
+    /// subtracted so the result can be negative.
+    pub fn predict_added_size(
+        &self,
+        store_bytes: &[u8],
+        input_stream: &mut dyn CAIRead,
+    ) -> Result<isize> {
+        let original = read_html(input_stream, &self.config)?;
+        let original_len = original.len() as isize;
+        let html = self.prepare_html(&original)?;
+        if self.placeholder_range(&html, store_bytes)?.is_some() {
+            return Ok(html.len() as isize - original_len);
+        }
+        if let Some(bootstrap) = &self.config.json_bootstrap {
+            let updated = self.write_json_manifest(&html, bootstrap, store_bytes)?;
+            return Ok(updated.len() as isize - original_len);
+        }
+        let placement = plan_manifest_placement(&html, self.config.lookup())?;
+        let manifest_block = self.manifest_block(&html, placement, store_bytes)?;
+
+        let replaced = match placement {
+            ManifestPlacement::Existing { .. } => {
+                find_manifest_element(&html, self.config.lookup())
+                    .map(|element| {
+                        element
+                            .block_range(&html, self.config.manifest_comment.as_deref())
+                            .len()
+                    })
+                    .ok_or(Error::JumbfNotFound)?
+            }
+            _ => 0,
+        };
+
+        Ok(html.len() as isize - original_len + manifest_block.len() as isize - replaced as isize)
+    }
+
+    /// the encoded content of an existing manifest that `store_bytes` can overwrite in place
+    fn placeholder_range(&self, html: &str, store_bytes: &[u8]) -> Result<Option<Range<usize>>> {
+        let (text, encoding) = match &self.config.json_bootstrap {
+            Some(bootstrap) => match find_json_manifest(html, bootstrap)? {
+                Some(JsonManifest {
+                    value: Some(value), ..
+                }) => (value, ManifestEncoding::Base64),
+                _ => return Ok(None),
+            },
+            None => match find_manifest_element(html, self.config.lookup()) {
+                Some(element) if element.encoding() == self.config.manifest_encoding => {
+                    (element.manifest_text(html), element.encoding())
+                }
+                _ => return Ok(None),
+            },
+        };
+
+        Ok(
+            (!text.is_empty() && text.len() == self.encoded_manifest_len(encoding, store_bytes))
+                .then_some(text),
+        )
+    }
+
+    /// the text of the manifest in a written document, wrapped as configured
+    fn encoded_manifest(&self, encoding: ManifestEncoding, store_bytes: &[u8]) -> String {
+        let text = encoding.encode(store_bytes);
+        match self.wrap_width() {
+            Some(width) => text
+                .as_bytes()
+                .chunks(width)
+                .map(|line| std::str::from_utf8(line).unwrap_or_default())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            None => text,
+        }
+    }
+
+    /// the length of [`Self::encoded_manifest`]
+    fn encoded_manifest_len(&self, encoding: ManifestEncoding, store_bytes: &[u8]) -> usize {
+        let len = encoding.encoded_len(store_bytes.len());
+        match self.wrap_width() {
+            Some(width) => len + len.saturating_sub(1) / width,
+            None => len,
+        }
+    }
+
+    fn wrap_width(&self) -> Option<usize> {
+        self.config
+            .wrap_base64
+            .filter(|&width| width > 0 && self.config.json_bootstrap.is_none())
+    }
+
+    /// the document with `store_bytes` written into its JSON bootstrap script
+    fn write_json_manifest(
+        &self,
+        html: &str,
+        bootstrap: &JsonBootstrap,
+        store_bytes: &[u8],
+    ) -> Result<String> {
+        let manifest_b64 = STANDARD.encode(store_bytes);
+
+        match find_json_manifest(html, bootstrap)? {
+            Some(JsonManifest {
+                value: Some(value), ..
+            }) => Ok(format!(
+                "{}{manifest_b64}{}",
+                &html[..value.start],
+                &html[value.end..]
+            )),
+            Some(JsonManifest { content, .. }) => {
+                let mut json: serde_json::Value = serde_json::from_str(&html[content.clone()])?;
+                let object = json.as_object_mut().ok_or_else(|| {
+                    Error::InvalidAsset("HTML JSON bootstrap script is not a JSON object".into())
+                })?;
+                object.insert(bootstrap.field.clone(), manifest_b64.into());
+                // keep string values from closing the script
+                let json = serde_json::to_string(&json)?.replace("</", "<\\/");
+                Ok(format!(
+                    "{}{json}{}",
+                    &html[..content.start],
+                    &html[content.end..]
+                ))
+            }
+            None => {
+                if bootstrap.script_id.contains(['"', '\'', '<', '>']) {
+                    return Err(Error::BadParam(
+                        "HTML JSON bootstrap script id cannot contain quotes or angle brackets"
+                            .into(),
+                    ));
+                }
+                let q = self.config.quote_style.quote();
+                let json = serde_json::to_string(&serde_json::json!({
+                    bootstrap.field.as_str(): manifest_b64
+                }))?
+                .replace("</", "<\\/");
+                let script = format!(
+                    "<script type={q}{JSON_SCRIPT_TYPE}{q} id={q}{}{q}>{json}</script>",
+                    bootstrap.script_id
+                );
+                splice_manifest(
+                    html,
+                    plan_manifest_placement(html, self.config.lookup())?,
+                    &script,
+                    self.config.lookup(),
+                )
+                .map(Cow::into_owned)
+            }
+        }
+    }
+
+    /// the document that the manifest is written into, see
+    /// [`HtmlIoConfig::pre_write_transform`]
+    fn prepare_html<'a>(&self, html: &'a str) -> Result<Cow<'a, str>> {
+        let html = match self.config.strip_comments {
+            true => Cow::Owned(strip_html_comments(html)),
+            false => Cow::Borrowed(html),
+        };
+        let html = match &self.config.manifest_link {
+            Some(href) => with_manifest_link(html, href, self.config.quote_style.quote())?,
+            None => html,
+        };
+        Ok(match &self.config.pre_write_transform {
+            Some(transform) => Cow::Owned((transform.0)(&html)),
+            None => html,
+        })
+    }
+
+    /// the manifest script written at the placement, with the white space around it when
+    /// [`HtmlIoConfig::match_indentation`] is set
+    fn manifest_block(
+        &self,
+        html: &str,
+        placement: ManifestPlacement,
+        store_bytes: &[u8],
+    ) -> Result<String> {
+        let manifest_script = self.manifest_script(html, store_bytes)?;
+        if !self.config.match_indentation {
+            return Ok(manifest_script);
+        }
+
+        let eol = if html.contains("\r\n") { "\r\n" } else { "\n" };
+        Ok(match placement {
+            ManifestPlacement::Existing { .. } => {
+                let element = find_manifest_element(html, self.config.lookup())
+                    .ok_or(Error::JumbfNotFound)?;
+                let block = element.block_range(html, self.config.manifest_comment.as_deref());
+                let start = element
+                    .comment_start(html, self.config.manifest_comment.as_deref())
+                    .unwrap_or(element.start);
+                format!(
+                    "{}{manifest_script}{}",
+                    &html[block.start..start],
+                    &html[element.end..block.end]
+                )
+            }
+            ManifestPlacement::Head { offset } => {
+                let indent = line_indent(html, offset);
+                let unit = indent_unit(html);
+                format!("{eol}{indent}{unit}{manifest_script}")
+            }
+            ManifestPlacement::Body { offset } => {
+                let body_end = offset + (html[offset..].len() - html[offset..].trim_start().len());
+                let indent = line_indent(html, body_end);
+                let unit = indent_unit(html);
+                format!("{eol}{indent}{unit}{manifest_script}")
+            }
+            ManifestPlacement::Append { .. } => format!("{eol}{manifest_script}"),
+        })
+    }
+
+    /// the manifest script (with its comment) written into the html
+    fn manifest_script(&self, html: &str, store_bytes: &[u8]) -> Result<String> {
+        let encoding = self.config.manifest_encoding;
+        let manifest_b64 = self.encoded_manifest(encoding, store_bytes); // encode the manifest bytes in base64 (or hex) to ensure the HTML will not break
+        let manifest_text = match self.markup_mode_for(html) {
+            MarkupMode::Html => manifest_b64,
+            MarkupMode::Xhtml => format!("{CDATA_OPEN}{manifest_b64}{CDATA_CLOSE}"),
+        };
+        let manifest_comment = match &self.config.manifest_comment {
+            Some(text) if text.contains("--") || text.contains('>') => {
+                return Err(Error::BadParam(
+                    "HTML manifest comment cannot contain \"--\" or \">\"".into(),
+                ))
+            }
+            Some(text) => format!("<!-- {text} -->"),
+            None => String::new(),
+        };
+        let q = self.config.quote_style.quote();
+        let manifest_id = match &self.config.manifest_id_prefix {
+            Some(prefix) if prefix.contains(['"', '\'', '<', '>']) => {
+                return Err(Error::BadParam(
+                    "HTML manifest id prefix cannot contain quotes or angle brackets".into(),
+                ))
+            }
+            Some(prefix) => format!(
+                " id={q}{}{q}",
+                next_manifest_id(html, prefix, self.config.lookup())
+            ),
+            None => String::new(),
+        };
+        let element = match self.config.manifest_element.as_deref() {
+            Some(name) if !is_custom_element_name(name) => {
+                return Err(Error::BadParam(format!(
+                    "HTML manifest element \"{name}\" is not a custom element name"
+                )))
+            }
+            Some(name) => name,
+            None => "script",
+        };
+        let replaced = find_manifest_element(html, self.config.lookup());
+        // a replaced manifest that was marked active stays the active one
+        let marker = match &replaced {
+            Some(e) if is_active_marker(e.attribute(C2PA_ACTIVE_ATTRIBUTE)) => {
+                format!(" {C2PA_ACTIVE_ATTRIBUTE}={q}true{q}")
+            }
+            _ => String::new(),
+        };
+        let kept: String = match &replaced {
+            Some(e) if self.config.keep_manifest_attributes => e
+                .attributes
+                .iter()
+                .filter(|(name, _)| match name.to_ascii_lowercase().as_str() {
+                    "type" | C2PA_ACTIVE_ATTRIBUTE => false,
+                    "id" => manifest_id.is_empty(),
+                    _ => true,
+                })
+                .map(|(name, value)| {
+                    // a value taken from the other quote style keeps its quotes
+                    let q = match (value.contains(q), q) {
+                        (true, '"') => '\'',
+                        (true, _) => '"',
+                        (false, q) => q,
+                    };
+                    format!(" {name}={q}{value}{q}")
+                })
+                .collect(),
+            _ => String::new(),
+        };
+        Ok(format!(
+            "{manifest_comment}<{element} type={q}{}{q}{manifest_id}{kept}{marker}>{manifest_text}</{element}>",
+            encoding.script_type()
+        ))
+    }
+
+    fn markup_mode_for(&self, html: &str) -> MarkupMode {
+        self.config
+            .markup_mode
+            .or(self.asset_type_mode)
+            .unwrap_or_else(|| MarkupMode::detect(html))
+    }
+
+    /// Moves the existing manifest to the `target` placement, the manifest script (and the
+    /// comment in front of it) is moved as it is without re-encoding the manifest.
+    ///
+    /// Returns [`Error::JumbfNotFound`] if the document has no manifest and
+    /// [`Error::BadParam`] if it lacks the tag the target refers to.
+    pub fn relocate_manifest(
+        &self,
+        input_stream: &mut dyn CAIRead,
+        output_stream: &mut dyn CAIReadWrite,
+        target: PlacementTarget,
+    ) -> Result<()> {
+        if DEBUG {
+            println!("relocate_manifest");
+        }
+
+        let (html, text_encoding) = read_html_document(input_stream, &self.config)?;
+        let element =
+            find_manifest_element(&html, self.config.lookup()).ok_or(Error::JumbfNotFound)?;
+        let block = element.block_range(&html, self.config.manifest_comment.as_deref());
+        let manifest_block = &html[element
+            .comment_start(&html, self.config.manifest_comment.as_deref())
+            .unwrap_or(element.start)..element.end];
+        let stripped = format!("{}{}", &html[..block.start], &html[block.end..]);
+
+        let placement = target_placement(&stripped, target)?.ok_or_else(|| {
+            Error::BadParam(format!(
+                "HTML document has no place for a {target:?} manifest"
+            ))
+        })?;
+        let updated_html =
+            splice_manifest(&stripped, placement, manifest_block, self.config.lookup())?;
+
+        rewind_output(output_stream)?;
+        output_stream.write_all(&text_encoding.encode(&updated_html))?;
+        Ok(())
+    }
+
+    /// Returns where the manifest is found in the document or, if there is none, where
+    /// it would be inserted when writing.
+    pub fn manifest_placement(&self, input_stream: &mut dyn CAIRead) -> Result<ManifestPlacement> {
+        let html = read_html(input_stream, &self.config)?;
+
+        plan_manifest_placement(&html, self.config.lookup())
+    }
+
+    /// Recomputes the hard binding over the current HTML bytes and compares it with the
+    /// data hash stored in the embedded manifest. The digest is the algorithm declared by the
+    /// data hash assertion, or the one of the claim when the assertion does not declare one.
+    ///
+    /// Returns [`Error::BindingMismatch`] when the document was modified after signing.
+    /// `first_diff_offset` is only known when the manifest no longer sits at the excluded
+    /// range recorded at signing time, a digest cannot locate edits within the hashed bytes.
+    pub fn verify_binding(&self, input_stream: &mut dyn CAIRead) -> Result<()> {
+        if DEBUG {
+            println!("verify_binding");
+        }
+
+        if self.config.require_head_placement {
+            let html = read_html(input_stream, &self.config)?;
+            let element =
+                find_manifest_element(&html, self.config.lookup()).ok_or(Error::JumbfNotFound)?;
+            if !head_range(&html)
+                .is_some_and(|head| head.start <= element.start && element.end <= head.end)
+            {
+                return Err(Error::ManifestNotInHead);
+            }
+        }
+
+        let store_bytes = self.read_cai(input_stream)?;
+        let store = Store::from_jumbf(&store_bytes, &mut StatusTracker::default())?;
+
+        check_data_hash(&store, input_stream, |stream| {
+            Ok(self
+                .get_object_locations_from_stream(stream)?
+                .iter()
+                .find(|o| o.htype == HashBlockObjectType::Cai)
+                .map(|o| o.offset..o.offset + o.length))
+        })
+    }
+
+    /// Returns the title, description and canonical URL of the document. Character
+    /// references are decoded, elements inside scripts are ignored.
+    pub fn extract_page_metadata(&self, input_stream: &mut dyn CAIRead) -> Result<PageMeta> {
+        let html = read_html(input_stream, &self.config)?;
+
+        let metas = open_tags(&html, META_OPEN);
+        let meta_content = |name: &str| {
+            metas.iter().find_map(|attributes| {
+                find_attribute(attributes, "name")
+                    .or_else(|| find_attribute(attributes, "property"))
+                    .filter(|n| n.eq_ignore_ascii_case(name))
+                    .and_then(|_| find_attribute(attributes, "content"))
+                    .map(|content| decode_html_entities(content.trim()))
+                    .filter(|content| !content.is_empty())
+            })
+        };
+        let canonical_url = open_tags(&html, LINK_OPEN)
+            .into_iter()
+            .find_map(|attributes| {
+                find_attribute(&attributes, "rel")
+                    .is_some_and(|rel| {
+                        rel.split_ascii_whitespace()
+                            .any(|r| r.eq_ignore_ascii_case("canonical"))
+                    })
+                    .then(|| find_attribute(&attributes, "href"))
+                    .flatten()
+                    .map(|href| decode_html_entities(href.trim()))
+            });
+
+        Ok(PageMeta {
+            title: page_title(&html),
+            description: meta_content("description").or_else(|| meta_content("og:description")),
+            canonical_url,
+        })
+    }
+
+    /// Verifies the document and returns the [`Reader`] holding the validation results
+    /// together with the embedded manifest store bytes.
+    ///
+    /// The stream is read once, this is the same as calling [`Reader::from_stream`] and
+    /// `read_cai` but without reading the document twice.
+    pub fn verify_and_extract(&self, input_stream: &mut dyn CAIRead) -> Result<(Reader, Vec<u8>)> {
+        if DEBUG {
+            println!("verify_and_extract");
+        }
+
+        let html = read_html_bytes(input_stream, self.config.max_document_bytes)?;
+        let store_bytes = self.read_cai(&mut std::io::Cursor::new(&html))?;
+        let format = match self.asset_type_mode {
+            Some(MarkupMode::Xhtml) => "application/xhtml+xml",
+            _ => "text/html",
+        };
+        let reader = Reader::from_manifest_data_and_stream(
+            &store_bytes,
+            format,
+            std::io::Cursor::new(&html),
+        )?;
+
+        Ok((reader, store_bytes))
+    }
+
+    /// Verifies every HTML and XHTML file directly inside `dir` like
+    /// [`HtmlIO::verify_and_extract`] and returns one entry per file ordered by path, for
+    /// audits that need a machine readable report (the entries serialize to JSON).
+    ///
+    /// Files are picked by their extension. A file that cannot be read or verified gets an
+    /// entry with the error, only a directory that cannot be listed fails the call.
+    pub fn verify_directory(&self, dir: &Path) -> Result<Vec<FileVerification>> {
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let mode = format_from_path(&path)
+                .filter(|_| is_html_path(&path))
+                .map(|format| MarkupMode::from_asset_type(&format));
+            if let Some(mode) = mode.filter(|_| path.is_file()) {
+                paths.push((path, mode));
+            }
+        }
+        paths.sort_by(|a, b| a.0.cmp(&b.0));
+
+        Ok(paths
+            .into_iter()
+            .map(|(path, mode)| {
+                let html_io = HtmlIO {
+                    asset_type_mode: mode,
+                    ..self.clone()
+                };
+                let mut verification = FileVerification {
+                    path,
+                    has_manifest: false,
+                    valid: false,
+                    trusted: false,
+                    active_label: None,
+                    timestamp: TimestampReport::default(),
+                    error: None,
+                };
+                let result = File::open(&verification.path)
+                    .map_err(Error::from)
+                    .and_then(|mut file| Ok((html_io.has_manifest(&mut file)?, file)));
+                match result {
+                    Ok((true, mut file)) => {
+                        verification.has_manifest = true;
+                        match html_io.verify_and_extract(&mut file) {
+                            Ok((reader, _)) => {
+                                verification.valid =
+                                    reader.validation_state() != ValidationState::Invalid;
+                                verification.active_label = reader.active_label().map(Into::into);
+                                verification.timestamp = html_io.signature_timestamp(&reader);
+                                match html_io.signer_trusted(&reader) {
+                                    Ok(trusted) => verification.trusted = trusted,
+                                    Err(e) => verification.error = Some(e.to_string()),
+                                }
+                            }
+                            Err(e) => verification.error = Some(e.to_string()),
+                        }
+                    }
+                    Ok((false, _)) => {}
+                    Err(e) => verification.error = Some(e.to_string()),
+                }
+                verification
+            })
+            .collect())
+    }
+
+    /// Verifies the document like [`HtmlIO::verify_and_extract`] and returns `true` when the
+    /// certificate chain of the active manifest's signer leads to one of the
+    /// [`HtmlIoConfig::trust_anchors`].
+    ///
+    /// Trust is reported separately from validity, a manifest can be valid and signed by an
+    /// untrusted certificate. Without configured anchors this is the trust check of the
+    /// settings, as reported by [`ValidationState::Trusted`].
+    pub fn verify_trust(&self, input_stream: &mut dyn CAIRead) -> Result<bool> {
+        let (reader, _) = self.verify_and_extract(input_stream)?;
+        self.signer_trusted(&reader)
+    }
+
+    fn signer_trusted(&self, reader: &Reader) -> Result<bool> {
+        let Some(anchors) = &self.config.trust_anchors else {
+            return Ok(reader.validation_state() == ValidationState::Trusted);
+        };
+        let mut policy = CertificateTrustPolicy::new();
+        policy
+            .add_trust_anchors(anchors.as_bytes())
+            .map_err(|e| Error::BadParam(format!("HTML trust anchors: {e}")))?;
+
+        let Some(info) = reader.active_manifest().and_then(|m| m.signature_info()) else {
+            return Ok(false);
+        };
+        let chain = Pem::iter_from_buffer(info.cert_chain().as_bytes())
+            .map(|pem| pem.map(|pem| pem.contents))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|_| Error::CoseInvalidCert)?;
+        let Some((end_entity, intermediates)) = chain.split_first() else {
+            return Ok(false);
+        };
+        let signing_time = info
+            .time
+            .as_deref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.timestamp());
+        Ok(policy
+            .check_certificate_trust(intermediates, end_entity, signing_time)
+            .is_ok())
+    }
+
+    /// Returns the time stamp of the active manifest's signature in `reader`, for example the
+    /// reader of [`HtmlIO::verify_and_extract`] to show when a page was signed, checked
+    /// against [`HtmlIoConfig::timestamp_tolerance`] and the signing certificate.
+    ///
+    /// An absent time stamp is not an error, the report then has no time and no flags set.
+    pub fn signature_timestamp(&self, reader: &Reader) -> TimestampReport {
+        let Some(info) = reader.active_manifest().and_then(|m| m.signature_info()) else {
+            return TimestampReport::default();
+        };
+
+        let time = info
+            .time
+            .as_deref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.with_timezone(&Utc));
+        let validity = Pem::iter_from_buffer(info.cert_chain().as_bytes())
+            .next()
+            .and_then(|pem| {
+                let pem = pem.ok()?;
+                let validity = pem.parse_x509().ok()?.validity().clone();
+                Some(validity.not_before.timestamp()..=validity.not_after.timestamp())
+            });
+
+        timestamp_report(time, validity, Utc::now(), self.config.timestamp_tolerance)
+    }
+
+    /// Verifies the document like [`HtmlIO::verify_and_extract`] and returns the active
+    /// manifest with its ingredients as a tree, for provenance viewers.
+    ///
+    /// Each node is valid unless validation reported a failure for it, an ingredient whose
+    /// manifest is already on the path to the root is listed without its children.
+    pub fn provenance_tree(&self, input_stream: &mut dyn CAIRead) -> Result<ProvenanceNode> {
+        let (reader, _) = self.verify_and_extract(input_stream)?;
+        let label = reader.active_label().ok_or(Error::JumbfNotFound)?;
+        let title = reader.get_manifest(label).and_then(|m| m.title());
+        let valid = reader.validation_state() != ValidationState::Invalid;
+
+        Ok(provenance_node(
+            &reader,
+            Some(label),
+            title,
+            valid,
+            &mut Vec::new(),
+        ))
+    }
+
+    /// Verifies the document like [`HtmlIO::verify_and_extract`] and writes the manifest
+    /// store JSON of the [`Reader`] to `output`, the same JSON the `readManifest` browser
+    /// binding returns.
+    pub fn write_manifest_json(
+        &self,
+        input_stream: &mut dyn CAIRead,
+        output: &mut dyn Write,
+    ) -> Result<()> {
+        let (reader, _) = self.verify_and_extract(input_stream)?;
+        output.write_all(reader.json().as_bytes())?;
+        Ok(())
+    }
+
+    /// Returns `true` if replacing the bytes in `edit_range` of the document with
+    /// `new_bytes` would invalidate the hard binding of its manifest.
+    ///
+    /// Edits that touch a hashed region break the binding. An edit only keeps it when it
+    /// lies within the excluded manifest content and keeps its length, since the excluded
+    /// range is fixed when signing (the manifest itself must of course stay valid). The
+    /// ranges are byte offsets into the stream, as returned by
+    /// `get_object_locations_from_stream`. Returns [`Error::JumbfNotFound`] when the document
+    /// has no manifest.
+    pub fn would_break_binding(
+        &self,
+        input_stream: &mut dyn CAIRead,
+        edit_range: Range<usize>,
+        new_bytes: &[u8],
+    ) -> Result<bool> {
+        if DEBUG {
+            println!("would_break_binding");
+        }
+
+        if !self.has_manifest(input_stream)? {
+            return Err(Error::JumbfNotFound);
+        }
+        if edit_range.is_empty() && new_bytes.is_empty() {
+            return Ok(false);
+        }
+
+        let positions = self.get_object_locations_from_stream(input_stream)?;
+        let within_manifest = positions
+            .iter()
+            .filter(|o| o.htype == HashBlockObjectType::Cai)
+            .any(|o| edit_range.start >= o.offset && edit_range.end <= o.offset + o.length);
+
+        Ok(!(within_manifest && new_bytes.len() == edit_range.len()))
+    }
+
+    /// Checks the hard binding of every embedded manifest against the hashed regions its
+    /// own data hash declares, in document order.
+    ///
+    /// Unlike [`HtmlIO::verify_binding`], which only looks at the first manifest, a mismatch
+    /// is reported per manifest instead of as an error. So is a manifest that cannot be
+    /// checked at all, in [`ManifestBindingReport::error`]; only reading the document fails
+    /// the whole call.
+    pub fn validate_all(
+        &self,
+        input_stream: &mut dyn CAIRead,
+    ) -> Result<Vec<ManifestBindingReport>> {
+        if DEBUG {
+            println!("validate_all");
+        }
+
+        let (html, text_encoding) = read_html_document(input_stream, &self.config)?;
+
+        let mut reports = Vec::new();
+        for element in manifest_elements(&html, self.config.lookup()) {
+            let text = element.manifest_text(&html);
+            // the region of this manifest's encoded content in the stream
+            let region = text_encoding.byte_offset(&html, text.start)
+                ..text_encoding.byte_offset(&html, text.end);
+            let report = match self.manifest_binding_report(
+                &html[text],
+                element.encoding(),
+                region,
+                input_stream,
+            ) {
+                Ok(report) => report,
+                Err(e @ Error::IoError(_)) => return Err(e),
+                Err(e) => ManifestBindingReport {
+                    id: None,
+                    binding_ok: false,
+                    signer: None,
+                    first_diff: None,
+                    error: Some(e.to_string()),
+                },
+            };
+            reports.push(ManifestBindingReport {
+                id: element.attribute("id").map(str::to_owned),
+                ..report
+            });
+        }
+        Ok(reports)
+    }
+
+    /// the binding of one of the manifests of [`HtmlIO::validate_all`], whose encoded content
+    /// is at `region` of the stream
+    fn manifest_binding_report(
+        &self,
+        content: &str,
+        encoding: ManifestEncoding,
+        region: Range<usize>,
+        input_stream: &mut dyn CAIRead,
+    ) -> Result<ManifestBindingReport> {
+        let store_bytes =
+            decode_manifest_content(content, encoding, self.config.max_decoded_bytes)?
+                .ok_or_else(|| Error::InvalidAsset("manifest is empty".into()))?;
+        let store = Store::from_jumbf(&store_bytes, &mut StatusTracker::default())?;
+        let signer = store
+            .provenance_claim()
+            .and_then(|claim| claim.signature_info())
+            .and_then(|info| info.issuer_org);
+
+        let first_diff = match check_data_hash(&store, input_stream, |_| Ok(Some(region))) {
+            Ok(()) => None,
+            Err(Error::BindingMismatch {
+                first_diff_offset, ..
+            }) => Some(first_diff_offset),
+            Err(e) => return Err(e),
+        };
+
+        Ok(ManifestBindingReport {
+            id: None,
+            binding_ok: first_diff.is_none(),
+            signer,
+            first_diff: first_diff.flatten(),
+            error: None,
+        })
+    }
+
+    /// Returns the URL of the remote manifest referenced by a
+    /// `<link rel="c2pa-manifest" href="...">` element, if the document has one.
+    ///
+    /// A relative `href` is resolved against the document's `<base href>`, which itself is
+    /// resolved against `document_url`, the address the document was loaded from. Absolute
+    /// URLs are returned as they are.
+    pub fn remote_manifest_url(
+        &self,
+        input_stream: &mut dyn CAIRead,
+        document_url: Option<&str>,
+    ) -> Result<Option<String>> {
+        if DEBUG {
+            println!("remote_manifest_url");
+        }
+
+        let html = read_html(input_stream, &self.config)?;
+
+        let Some(href) = manifest_link_href(&html) else {
+            return Ok(None);
+        };
+
+        if let Ok(url) = url::Url::parse(href) {
+            return Ok(Some(url.to_string()));
+        }
+
+        // only the first <base> with an href counts
+        let base_href = open_tags(&html, BASE_OPEN)
+            .into_iter()
+            .find_map(|attributes| find_attribute(&attributes, "href").map(str::trim));
+
+        let document_url = document_url
+            .map(url::Url::parse)
+            .transpose()
+            .map_err(|e| Error::BadParam(format!("invalid document url: {e}")))?;
+        let base = match (base_href, document_url) {
+            (Some(base_href), document_url) => match url::Url::parse(base_href) {
+                Ok(base) => Some(base),
+                Err(_) => document_url.and_then(|d| d.join(base_href).ok()),
+            },
+            (None, document_url) => document_url,
+        };
+
+        let base = base.ok_or_else(|| {
+            Error::BadParam(format!(
+                "relative manifest url {href} cannot be resolved without a document url"
+            ))
+        })?;
+        let url = base
+            .join(href)
+            .map_err(|e| Error::BadParam(format!("invalid manifest url {href}: {e}")))?;
+        Ok(Some(url.to_string()))
+    }
+
+    /// Same as [`HtmlIO::verify_binding`], but reports a modified document in the returned
+    /// [`BindingReport`] instead of as an error.
+    pub fn binding_report(&self, input_stream: &mut dyn CAIRead) -> Result<BindingReport> {
+        match self.verify_binding(input_stream) {
+            Ok(()) => Ok(BindingReport {
+                binding_ok: true,
+                first_diff: None,
+            }),
+            Err(Error::BindingMismatch {
+                first_diff_offset, ..
+            }) => Ok(BindingReport {
+                binding_ok: false,
+                first_diff: first_diff_offset,
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// SHA-256 of the document without the manifest, the same bytes the hard binding covers,
+    /// so the fingerprint stays the same whichever manifest is embedded. A document without
+    /// a manifest is hashed as it would be signed.
+    pub fn content_fingerprint(&self, input_stream: &mut dyn CAIRead) -> Result<[u8; 32]> {
+        let mut buffer = std::io::Cursor::new(Vec::new());
+        add_required_segs_to_stream(self, input_stream, &mut buffer)?;
+
+        let exclusions = self
+            .get_object_locations_from_stream(&mut buffer)?
+            .into_iter()
+            .filter(|position| position.htype == HashBlockObjectType::Cai)
+            .map(|position| HashRange::new(position.offset, position.length))
+            .collect();
+        let hash = hash_stream_by_alg("sha256", &mut buffer, Some(exclusions), true)?;
+        hash.try_into()
+            .map_err(|_| Error::InvalidAsset("unexpected SHA-256 length".into()))
+    }
+
+    /// Returns the branch counts of all handlers of the process since it started.
+    #[cfg(feature = "html_stats")]
+    pub fn stats() -> Stats {
+        let get = |counter: &std::sync::atomic::AtomicU64| {
+            counter.load(std::sync::atomic::Ordering::Relaxed)
+        };
+        Stats {
+            replaced: get(&STATS.replaced),
+            head: get(&STATS.head),
+            body: get(&STATS.body),
+            appended: get(&STATS.appended),
+            detected_in_head: get(&STATS.detected_in_head),
+            detected_elsewhere: get(&STATS.detected_elsewhere),
+            not_detected: get(&STATS.not_detected),
+        }
+    }
+
+    /// Same as [`HtmlIO::verify_binding`], but answers documents whose bytes were already
+    /// verified from `cache`.
+    #[cfg(feature = "html_verification_cache")]
+    pub fn verify_binding_cached(
+        &self,
+        input_stream: &mut dyn CAIRead,
+        cache: &mut VerificationCache,
+    ) -> Result<()> {
+        if DEBUG {
+            println!("verify_binding_cached");
+        }
+
+        let key = hash_stream_by_alg("sha256", input_stream, None, true)?;
+        let outcome = match cache.get(&key) {
+            Some(outcome) => outcome,
+            None => {
+                let outcome = match self.verify_binding(input_stream) {
+                    Ok(()) => None,
+                    Err(Error::BindingMismatch {
+                        expected,
+                        actual,
+                        first_diff_offset,
+                    }) => Some((expected, actual, first_diff_offset)),
+                    Err(e) => return Err(e),
+                };
+                cache.insert(key, outcome.clone());
+                outcome
+            }
+        };
+
+        match outcome {
+            None => Ok(()),
+            Some((expected, actual, first_diff_offset)) => Err(Error::BindingMismatch {
+                expected,
+                actual,
+                first_diff_offset,
+            }),
+        }
+    }
+
+    /// `read_cai_store` for a memory mapped file, the bytes are scanned in place and only
+    /// the manifest content is copied
+    #[cfg(all(feature = "mmap", not(target_arch = "wasm32")))]
+    fn read_mapped_cai(&self, html: &[u8]) -> Result<Vec<u8>> {
+        let manifest = scan_manifest_block(
+            &mut std::io::Cursor::new(html),
+            self.config.active_manifest,
+            self.config.read_from_template,
+            self.config.manifest_comment.as_deref(),
+        )?
+        .ok_or(Error::JumbfNotFound)?;
+        self.decode_scanned_manifest(&html[manifest.content], manifest.encoding)
+    }
+
+    /// the store bytes in the raw content of a manifest script found by [`scan_manifest_block`]
+    fn decode_scanned_manifest(
+        &self,
+        content: &[u8],
+        encoding: ManifestEncoding,
+    ) -> Result<Vec<u8>> {
+        let content = std::str::from_utf8(content)
+            .map_err(|_| Error::InvalidAsset("HTML manifest script is not valid UTF-8".into()))?;
+
+        decode_manifest_content(
+            &content[manifest_text_in(content)],
+            encoding,
+            self.config.max_decoded_bytes,
+        )?
+        .filter(|data| !data.is_empty())
+        .ok_or(Error::JumbfNotFound)
+    }
+}
+
+impl CAIReader for HtmlIO {
+    /// read manifest data from HTML stream with embedded manifest
+    fn read_cai(&self, asset_reader: &mut dyn CAIRead) -> Result<Vec<u8>> {
+        if DEBUG {
+            println!("read_cai");
+        }
+
+        if self.config.lossy_utf8 {
+            let bytes = read_html_bytes(asset_reader, self.config.max_document_bytes)?;
+            let bytes = match TextEncoding::detect(&bytes) {
+                TextEncoding::Utf8 => String::from_utf8_lossy(&bytes).into_owned().into_bytes(),
+                _ => bytes,
+            };
+            let strict = HtmlIO {
+                config: HtmlIoConfig {
+                    lossy_utf8: false,
+                    ..self.config.clone()
+                },
+                ..self.clone()
+            };
+            return strict.read_cai(&mut std::io::Cursor::new(bytes));
+        }
+
+        if self.config.read_sse_framed {
+            let html = read_html(asset_reader, &self.config)?;
+            let html = strip_sse_framing(&html).unwrap_or(html);
+            let unframed = HtmlIO {
+                config: HtmlIoConfig {
+                    read_sse_framed: false,
+                    ..self.config.clone()
+                },
+                ..self.clone()
+            };
+            return unframed.read_cai(&mut std::io::Cursor::new(html));
+        }
+
+        if self.config.read_escaped_html {
+            let html = read_html(asset_reader, &self.config)?;
+            let unescaped = HtmlIO {
+                config: HtmlIoConfig {
+                    read_escaped_html: false,
+                    ..self.config.clone()
+                },
+                ..self.clone()
+            };
+            return unescaped.read_cai(&mut std::io::Cursor::new(decode_html_entities(&html)));
+        }
+
+        let (manifest_opt, _insertion_point, _encoding) =
+            detect_manifest_location(asset_reader, &self.config)?;
+
+        match manifest_opt {
+            Some(data) if !data.is_empty() => Ok(data),
+            _ if self.config.read_iframe_srcdoc || self.config.read_data_uris => self
+                .read_manifests(asset_reader)?
+                .into_iter()
+                .map(|m| m.store_bytes)
+                .find(|data| !data.is_empty())
+                .ok_or(Error::JumbfNotFound),
+            _ => Err(Error::JumbfNotFound),
+        }
+    }
+
+    /// read an XMP packet embedded in the HTML, see also [`HtmlIoConfig::synthesize_meta_xmp`]
+    fn read_xmp(&self, asset_reader: &mut dyn CAIRead) -> Option<String> {
+        if DEBUG {
+            println!("read_xmp");
+        }
+
+        let html = read_html(asset_reader, &self.config).ok()?;
+
+        let xmp = match html.find(XPACKET_BEGIN) {
+            Some(start) => {
+                let end = html[start..].find(XPACKET_END)? + start;
+                let end = html[end..].find("?>")? + end + 2;
+                Some(html[start..end].to_owned())
+            }
+            None if self.config.synthesize_meta_xmp => meta_xmp(&html),
+            None => None,
+        };
+
+        // an absolute manifest link is the remote manifest of the document, relative ones
+        // need the document url, see [`HtmlIO::remote_manifest_url`]
+        let Some(href) = manifest_link_href(&html).filter(|href| url::Url::parse(href).is_ok())
+        else {
+            return xmp;
+        };
+        match xmp {
+            Some(xmp) if extract_provenance(&xmp).is_some() => Some(xmp),
+            Some(xmp) => add_provenance(&xmp, href).ok().or(Some(xmp)),
+            None => Some(format!(
+                r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?><x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"><rdf:Description rdf:about="" xmlns:dcterms="http://purl.org/dc/terms/" dcterms:provenance="{}"/></rdf:RDF></x:xmpmeta><?xpacket end="r"?>"#,
+                quick_xml::escape::escape(href)
+            )),
+        }
+    }
+}
+
+impl CAIWriter for HtmlIO {
+    /// embed the base64 encoded manifest bytes inside a script tag into the HTML stream, the
+    /// input is read completely before the output is written, see
+    /// [`HtmlIO::write_cai_in_place`] for writing back into the input buffer
+    fn write_cai(
+        &self,
+        input_stream: &mut dyn CAIRead,
+        output_stream: &mut dyn CAIReadWrite,
+        store_bytes: &[u8],
+    ) -> Result<()> {
+        if DEBUG {
+            println!("write_cai");
+        }
+
+        let report = self.write_cai_with_report(input_stream, output_stream, store_bytes)?;
+        if report.replaced_undecodable_manifest {
+            log::warn!("replaced an HTML manifest that could not be decoded");
+        }
+        Ok(())
+    }
+
+    /// locate the position of the embedded manifest to exclude it from hashing
+    fn get_object_locations_from_stream(
+        &self,
+        input_stream: &mut dyn CAIRead,
+    ) -> Result<Vec<HashObjectPositions>> {
+        if DEBUG {
+            println!("get_object_locations_from_stream");
+        }
+
+        let mut buffer: Vec<u8> = Vec::new();
+        {
+            let mut output_stream = std::io::Cursor::new(&mut buffer);
+            // make sure the input stream is in the correct format (contains at least a dummy manifest)
+            add_required_segs_to_stream(self, input_stream, &mut output_stream)?;
+        }
+
+        let mut buffer_cursor = std::io::Cursor::new(&buffer);
+        let (manifest_opt, insertion_point, encoding) =
+            detect_manifest_location(&mut buffer_cursor, &self.config)?;
+
+        let manifest = manifest_opt.ok_or(Error::JumbfNotFound)?;
+        if !is_jumbf(&manifest) {
+            return Err(Error::InvalidAsset(
+                "HTML manifest is not a JUMBF manifest store".into(),
+            ));
+        }
+        let b64_len = encoding.encode(&manifest).len(); // length of only the manifest data (without script tag)
+
+        // the positions are byte offsets in the stream, which differ from the offsets in the
+        // decoded text for UTF-16 documents
+        let (start, b64_len) = if TextEncoding::detect(&buffer) == TextEncoding::Utf8 {
+            let b64_len = wrapped_text_len(&buffer, insertion_point, b64_len);
+            check_manifest_offset(&buffer, insertion_point, b64_len, &manifest, encoding)?;
+            (insertion_point, b64_len) // insertion point is the start of the base64 encoded manifest in the html stream
+        } else {
+            let (html, text_encoding) =
+                read_html_document(&mut buffer_cursor, &HtmlIoConfig::default())?;
+            let b64_len = wrapped_text_len(html.as_bytes(), insertion_point, b64_len);
+            check_manifest_offset(
+                html.as_bytes(),
+                insertion_point,
+                b64_len,
+                &manifest,
+                encoding,
+            )?;
+            let start = text_encoding.byte_offset(&html, insertion_point);
+            (
+                start,
+                text_encoding.byte_offset(&html, insertion_point + b64_len) - start,
+            )
+        };
+        let positions = manifest_regions(start, b64_len, buffer.len());
+        validate_regions(&positions, buffer.len())?;
+        Ok(positions)
+    }
+
+    /// remove the manifest from the html stream
+    fn remove_cai_store_from_stream(
+        &self,
+        input_stream: &mut dyn CAIRead,
+        output_stream: &mut dyn CAIReadWrite,
+    ) -> Result<()> {
+        if DEBUG {
+            println!("remove_cai_store_from_stream");
+        }
+
+        // the scan only understands `<script>` elements in ASCII compatible text, UTF-16
+        // documents and custom manifest elements are handled on the decoded document instead
+        let mut bom = [0u8; 2];
+        input_stream.rewind()?;
+        if self.config.manifest_element.is_some()
+            || input_stream.read_exact(&mut bom).is_ok()
+                && TextEncoding::from_bom(&bom) != TextEncoding::Utf8
+        {
+            let (mut html, text_encoding) = read_html_document(input_stream, &self.config)?;
+            if let Some(element) = find_manifest_element(&html, self.config.lookup()) {
+                let block = element.block_range(&html, self.config.manifest_comment.as_deref());
+                let bytes = html.as_bytes();
+                let block = match sentinel_extension(
+                    &bytes[..block.start],
+                    &bytes[block.clone()],
+                    &bytes[block.end..],
+                ) {
+                    Some((before, after)) => block.start - before..block.end + after,
+                    None => block,
+                };
+                html.replace_range(block, "");
+            }
+            rewind_output(output_stream)?;
+            output_stream.write_all(&text_encoding.encode(&html))?;
+            return Ok(());
+        }
+
+        // find the manifest block in a single forward scan, then copy everything around it
+        let block = match scan_manifest_block(
+            input_stream,
+            self.config.active_manifest,
+            self.config.read_from_template,
+            self.config.manifest_comment.as_deref(),
+        )? {
+            Some(manifest) => Some(with_sentinels(input_stream, manifest.block)?),
+            None => None,
+        };
+
+        input_stream.rewind()?;
+        rewind_output(output_stream)?;
+        match block {
+            Some(block) => {
+                std::io::copy(
+                    &mut (&mut *input_stream).take(block.start as u64),
+                    output_stream,
+                )?;
+                input_stream.seek(SeekFrom::Start(block.end as u64))?;
+                std::io::copy(input_stream, output_stream)?;
+            }
+            None => {
+                std::io::copy(input_stream, output_stream)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AssetIO for HtmlIO {
+    fn new(asset_type: &str) -> Self {
+        if DEBUG {
+            println!("new");
+        }
+
+        HtmlIO {
+            config: HtmlIoConfig::default(),
+            asset_type_mode: MarkupMode::from_asset_type(asset_type),
+        }
+    }
+
+    fn get_handler(&self, asset_type: &str) -> Box<dyn AssetIO> {
+        if DEBUG {
+            println!("get_handler");
+        }
+
+        Box::new(HtmlIO::new(asset_type))
+    }
+
+    fn get_reader(&self) -> &dyn CAIReader {
+        if DEBUG {
+            println!("get_reader");
+        }
+
+        self
+    }
+
+    fn get_writer(&self, asset_type: &str) -> Option<Box<dyn CAIWriter>> {
+        if DEBUG {
+            println!("get_writer");
+        }
+
+        let mut writer = self.clone();
+        writer.asset_type_mode = MarkupMode::from_asset_type(asset_type).or(self.asset_type_mode);
+        Some(Box::new(writer))
+    }
+
+    fn read_cai_store(&self, asset_path: &Path) -> Result<Vec<u8>> {
+        if DEBUG {
+            println!("read_cai_store: {}", asset_path.display());
+        }
+
+        let mut f = File::open(asset_path)?;
+
+        // read only the manifest out of the file unless an option needs the whole document
+        let mut bom = [0u8; 2];
+        let utf16 =
+            f.read_exact(&mut bom).is_ok() && TextEncoding::from_bom(&bom) != TextEncoding::Utf8;
+        if utf16
+            || self.config.read_iframe_srcdoc
+            || self.config.read_data_uris
+            || self.config.read_escaped_html
+            || self.config.read_sse_framed
+            || self.config.json_bootstrap.is_some()
+            || self.config.manifest_element.is_some()
+        {
+            return self.read_cai(&mut f); // simply call the function that operates on the stream...
+        }
+        if let Some(max) = self.config.max_document_bytes {
+            if f.metadata()?.len() > max as u64 {
+                return Err(Error::InvalidAsset("document too large".into()));
+            }
+        }
+
+        // scan the mapped file in place, files that cannot be mapped are scanned while reading
+        #[cfg(all(feature = "mmap", not(target_arch = "wasm32")))]
+        {
+            // SAFETY: the map is only read while the file is open, like any memory mapped
+            // reader it faults if another process truncates the file meanwhile
+            match unsafe { memmap2::Mmap::map(&f) } {
+                Ok(map) => return self.read_mapped_cai(&map),
+                Err(e) => log::debug!("reading {} without mapping it: {e}", asset_path.display()),
+            }
+        }
+
+        let manifest = scan_manifest_block(
+            &mut f,
+            self.config.active_manifest,
+            self.config.read_from_template,
+            self.config.manifest_comment.as_deref(),
+        )?
+        .ok_or(Error::JumbfNotFound)?;
+        let mut content = Vec::with_capacity(manifest.content.len());
+        f.seek(SeekFrom::Start(manifest.content.start as u64))?;
+        (&mut f)
+            .take(manifest.content.len() as u64)
+            .read_to_end(&mut content)?;
+        self.decode_scanned_manifest(&content, manifest.encoding)
+    }
+
+    fn save_cai_store(&self, asset_path: &Path, store_bytes: &[u8]) -> Result<()> {
+        if DEBUG {
+            println!("save_cai_store: {}", asset_path.display());
+        }
+
+        let mut input_stream = std::fs::OpenOptions::new()
+            .read(true)
+            .open(asset_path)
+            .map_err(|e| save_step_error(SaveStep::Read, asset_path, Error::IoError(e)))?;
+        let mut temp_file =
+            tempfile_builder("c2pa_temp") // create a temp file while writing
+                .map_err(|e| save_step_error(SaveStep::TempCreate, asset_path, e))?;
+        self.write_cai(&mut input_stream, &mut temp_file, store_bytes)
+            .map_err(|e| save_step_error(SaveStep::Write, asset_path, e))?;
+        drop(input_stream);
+        match self.config.rename_retry {
+            Some(policy) => replace_with_retry(temp_file, asset_path, policy, &mut |from, to| {
+                std::fs::rename(from, to)
+            }),
+            None => rename_or_move(temp_file, asset_path),
+        }
+        .map_err(|e| save_step_error(SaveStep::Rename, asset_path, e))
+    }
+
+    fn get_object_locations(&self, asset_path: &Path) -> Result<Vec<HashObjectPositions>> {
+        if DEBUG {
+            println!("get_object_locations: {}", asset_path.display());
+        }
+
+        let mut input_stream =
+            std::fs::File::open(asset_path).map_err(|_err| Error::EmbeddingError)?;
+        self.get_object_locations_from_stream(&mut input_stream)
+    }
+
+    fn remove_cai_store(&self, asset_path: &Path) -> Result<()> {
+        if DEBUG {
+            println!("remove_cai_store: {}", asset_path.display());
+        }
+
+        let mut input_file = File::open(asset_path)?;
+        let mut temp_file = tempfile_builder("c2pa_temp")?;
+        self.remove_cai_store_from_stream(&mut input_file, &mut temp_file)?;
+        rename_or_move(temp_file, asset_path)
+    }
+
+    fn supported_types(&self) -> &[&str] {
+        if DEBUG {
+            println!("supported_types");
+        }
+
+        &SUPPORTED_TYPES
+    }
+
+    fn composed_data_ref(&self) -> Option<&dyn ComposedManifestRef> {
+        Some(self)
+    }
+}
+
+impl ComposedManifestRef for HtmlIO {
+    // the manifest store bytes as they are passed to `write_cai`, which encodes them
+    fn compose_manifest(&self, manifest_data: &[u8], _format: &str) -> Result<Vec<u8>> {
+        Ok(manifest_data.to_vec())
+    }
+}
+
+/// steps of `save_cai_store` used to annotate the errors raised while saving
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SaveStep {
+    Read,
+    TempCreate,
+    Write,
+    Rename,
+}
+
+impl std::fmt::Display for SaveStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let step = match self {
+            SaveStep::Read => "read",
+            SaveStep::TempCreate => "temp-create",
+            SaveStep::Write => "write",
+            SaveStep::Rename => "rename",
+        };
+        write!(f, "{step}")
+    }
+}
+
+/// move the temporary file over the asset with `rename`, retrying failures as the policy
+/// says and copying the file instead when renaming is not possible
+fn replace_with_retry(
+    temp_file: tempfile::NamedTempFile,
+    asset_path: &Path,
+    policy: RenameRetry,
+    rename: &mut dyn FnMut(&Path, &Path) -> std::io::Result<()>,
+) -> Result<()> {
+    let (_, temp_path) = temp_file
+        .keep()
+        .map_err(|e| Error::OtherError(Box::new(e)))?;
+
+    let mut backoff = policy.backoff;
+    let mut attempt = 0;
+    let rename_err = loop {
+        match rename(&temp_path, asset_path) {
+            Ok(()) => return Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => break e,
+            Err(e) if attempt >= policy.retries => break e,
+            Err(e) => {
+                log::debug!("retrying the rename to {}: {e}", asset_path.display());
+                std::thread::sleep(backoff);
+                backoff = backoff.saturating_mul(2);
+                attempt += 1;
+            }
+        }
+    };
+
+    // copy over the asset, truncating it, when it cannot be renamed
+    let copy = || -> std::io::Result<()> {
+        let mut source = File::open(&temp_path)?;
+        let mut asset = std::fs::OpenOptions::new()
+            .write(true)
+            .truncate(true)
+            .open(asset_path)?;
+        std::io::copy(&mut source, &mut asset).map(|_| ())
+    };
+    // a copy failing partway may leave the asset truncated, the temp file then is the only
+    // complete document and is kept
+    copy().map_err(|copy_err| {
+        Error::IoError(std::io::Error::new(
+            rename_err.kind(),
+            format!(
+                "rename failed: {rename_err}, copy failed: {copy_err}, document kept at {}",
+                temp_path.display()
+            ),
+        ))
+    })?;
+    std::fs::remove_file(&temp_path).map_err(Error::IoError)
+}
+
+/// add the failing step and the asset path to IO errors so permission and cross-device
+/// problems can be diagnosed, other errors are already specific and are passed through
+fn save_step_error(step: SaveStep, asset_path: &Path, err: Error) -> Error {
+    let context = format!(
+        "save_cai_store {step} step failed for {}",
+        asset_path.display()
+    );
+    match err {
+        Error::IoError(e) => {
+            Error::IoError(std::io::Error::new(e.kind(), format!("{context}: {e}")))
+        }
+        Error::OtherError(e) => Error::IoError(std::io::Error::other(format!("{context}: {e}"))),
+        err => err,
+    }
+}
+
+/// Finds the active manifest of a UTF-8 document, with the [`HtmlIoConfig::manifest_element`]
+/// and [`HtmlIoConfig::active_manifest`] of `config`. `None` for documents without a
+/// manifest or that are not UTF-8.
+///
+/// This is the scanner behind [`HtmlIO`], it works on a byte slice without any I/O. The
+/// scanner itself only uses `core` and `alloc`.
+pub fn locate_manifest(html: &[u8], config: &HtmlIoConfig) -> Option<ManifestLocation> {
+    html_scan::locate_manifest(html, config.lookup())
+}
+
+/// Returns `true` when the extension of `path` is one of the HTML or XHTML types handled by
+/// [`HtmlIO`] (`.html`, `.htm`, `.xhtml` and `.xht`, in any case), for tools that route the
+/// files they are given by path.
+pub fn is_html_path(path: &Path) -> bool {
+    format_from_path(path).is_some_and(|format| SUPPORTED_TYPES.contains(&format.as_str()))
+}
+
+/// Signs an HTML document and returns the signed bytes.
+///
+/// The manifest is built from the `manifest_def` JSON (see [`Builder::from_json`]) and
+/// embedded with the reserve, hash, sign and fill passes of [`Builder::sign`]. XHTML
+/// documents are recognized with [`MarkupMode::detect`] and signed as
+/// `application/xhtml+xml`.
+pub fn sign_html(input: &[u8], signer: &dyn Signer, manifest_def: &str) -> Result<Vec<u8>> {
+    let (html, _) = decode_html(input.to_vec())?;
+    let format = match MarkupMode::detect(&html) {
+        MarkupMode::Html => "text/html",
+        MarkupMode::Xhtml => "application/xhtml+xml",
+    };
+
+    let mut builder = Builder::from_json(manifest_def)?;
+    let mut dest = std::io::Cursor::new(Vec::new());
+    builder.sign(signer, format, &mut std::io::Cursor::new(input), &mut dest)?;
+    Ok(dest.into_inner())
+}
+
+/// Reads the manifest store of a document served over HTTP(S) by fetching only the
+/// manifest text at `range`, for example the [`HashBlockObjectType::Cai`] region recorded
+/// in a sidecar index, with a range request.
+///
+/// The range is a byte range of the served document, which must be UTF-8 or another ASCII
+/// compatible encoding. The text is decoded as base64 and as hex when that does not yield a
+/// manifest store. A server that ignores the range and returns the whole document works
+/// as well, at the cost of the full download.
+///
+/// Available on all targets but `wasm32`, it uses the HTTP client the SDK already fetches
+/// time stamps and OCSP responses with.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_cai_range(url: &str, range: Range<usize>) -> Result<Vec<u8>> {
+    if range.is_empty() {
+        return Err(Error::BadParam("empty manifest range".into()));
+    }
+
+    let response = ureq::get(url)
+        .set("Range", &format!("bytes={}-{}", range.start, range.end - 1))
+        .call()
+        .map_err(|e| Error::RemoteManifestFetch(e.to_string()))?;
+    let skip = match response.status() {
+        206 => 0,
+        200 => range.start as u64,
+        status => {
+            return Err(Error::RemoteManifestFetch(format!(
+                "fetch failed: code: {status}, status: {}",
+                response.status_text()
+            )))
+        }
+    };
+
+    let mut reader = response.into_reader();
+    std::io::copy(&mut reader.by_ref().take(skip), &mut std::io::sink())?;
+    let mut text = Vec::with_capacity(range.len());
+    reader.take(range.len() as u64).read_to_end(&mut text)?;
+    if text.len() != range.len() {
+        return Err(Error::RemoteManifestFetch(format!(
+            "expected {} manifest bytes, received {}",
+            range.len(),
+            text.len()
+        )));
+    }
+
+    let text = std::str::from_utf8(&text)
+        .map_err(|_| Error::InvalidAsset("HTML manifest range is not text".into()))?;
+    [ManifestEncoding::Base64, ManifestEncoding::Hex]
+        .into_iter()
+        .find_map(|encoding| {
+            decode_manifest_content(text, encoding, None)
+                .ok()
+                .flatten()
+                .filter(|store| is_jumbf(store))
+        })
+        .ok_or(Error::JumbfNotFound)
+}
+
+/// the manifest region at `start` and the hashed regions before and after it, the manifest
+/// region comes first. Empty regions, e.g. after a manifest that runs to the end of a
+/// truncated document, are left out since some hashers reject zero length ranges.
+fn manifest_regions(start: usize, len: usize, html_len: usize) -> Vec<HashObjectPositions> {
+    [
+        HashObjectPositions {
+            offset: start,
+            length: len,
+            htype: HashBlockObjectType::Cai, // this will be excluded from hashing
+        },
+        HashObjectPositions {
+            offset: 0,
+            length: start,
+            htype: HashBlockObjectType::Other, // part before manifest
+        },
+        HashObjectPositions {
+            offset: start + len,
+            length: html_len.saturating_sub(start + len), // until the end of the stream
+            htype: HashBlockObjectType::Other,            // part after manifest
+        },
+    ]
+    .into_iter()
+    .filter(|region| region.length > 0)
+    .collect()
+}
+
+/// Checks the hashed regions of a document of `total_len` bytes: there is a non empty
+/// manifest region and together the regions cover the document without gaps or overlaps.
+pub fn validate_regions(regions: &[HashObjectPositions], total_len: usize) -> Result<()> {
+    if !regions
+        .iter()
+        .any(|r| r.htype == HashBlockObjectType::Cai && r.length > 0)
+    {
+        return Err(Error::InvalidAsset(
+            "HTML hashed regions have no manifest region".into(),
+        ));
+    }
+
+    let mut sorted: Vec<&HashObjectPositions> = regions.iter().collect();
+    sorted.sort_by_key(|r| (r.offset, r.length));
+
+    let mut end = 0;
+    for region in sorted {
+        if region.offset != end {
+            return Err(Error::InvalidAsset(format!(
+                "HTML hashed regions {} at offset {end}",
+                if region.offset < end {
+                    "overlap"
+                } else {
+                    "leave a gap"
+                }
+            )));
+        }
+        end = region.offset + region.length;
+    }
+    if end != total_len {
+        return Err(Error::InvalidAsset(format!(
+            "HTML hashed regions end at {end}, the document has {total_len} bytes"
+        )));
+    }
+    Ok(())
+}
+
+fn read_html(input_stream: &mut dyn CAIRead, config: &HtmlIoConfig) -> Result<String> {
+    Ok(read_html_document(input_stream, config)?.0)
+}
+
+/// text encoding of an html stream, UTF-16 documents are recognized by their byte order
+/// mark and written back in the same encoding (with the byte order mark)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TextEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// an ASCII compatible encoding other than UTF-8 named by the XML declaration
+    Declared(&'static Encoding),
+}
+
+impl TextEncoding {
+    fn from_bom(bytes: &[u8]) -> Self {
+        match Encoding::for_bom(bytes) {
+            Some((encoding, _)) if encoding == UTF_16LE => TextEncoding::Utf16Le,
+            Some((encoding, _)) if encoding == UTF_16BE => TextEncoding::Utf16Be,
+            _ => TextEncoding::Utf8,
+        }
+    }
+
+    /// the encoding of the bytes, a UTF-16 byte order mark comes first since a declaration
+    /// can only be read in an ASCII compatible encoding, which then overrides the UTF-8 default
+    fn detect(bytes: &[u8]) -> Self {
+        match TextEncoding::from_bom(bytes) {
+            TextEncoding::Utf8 => {
+                xml_declared_encoding(bytes).map_or(TextEncoding::Utf8, TextEncoding::Declared)
+            }
+            utf16 => utf16,
+        }
+    }
+
+    fn encode(self, html: &str) -> Cow<'_, [u8]> {
+        // encoding_rs only encodes into UTF-8 for UTF-16 labels, so the units are written here
+        match self {
+            TextEncoding::Declared(encoding) => encoding.encode(html).0,
+            TextEncoding::Utf8 => Cow::Borrowed(html.as_bytes()),
+            TextEncoding::Utf16Le => [0xff, 0xfe]
+                .into_iter()
+                .chain(html.encode_utf16().flat_map(u16::to_le_bytes))
+                .collect(),
+            TextEncoding::Utf16Be => [0xfe, 0xff]
+                .into_iter()
+                .chain(html.encode_utf16().flat_map(u16::to_be_bytes))
+                .collect(),
+        }
+    }
+
+    /// the offset in the encoded stream of the `offset` in the decoded html
+    fn byte_offset(self, html: &str, offset: usize) -> usize {
+        match self {
+            TextEncoding::Utf8 => offset,
+            TextEncoding::Utf16Le | TextEncoding::Utf16Be => {
+                2 + 2 * html[..offset].encode_utf16().count()
+            }
+            TextEncoding::Declared(encoding) => encoding.encode(&html[..offset]).0.len(),
+        }
+    }
+}
+
+/// read the whole html stream, decoding UTF-16 documents that start with a byte order mark
+/// and documents in the encoding of their XML declaration, see
+/// [`HtmlIoConfig::max_document_bytes`] and [`HtmlIoConfig::max_tokens`]
+fn read_html_document(
+    input_stream: &mut dyn CAIRead,
+    config: &HtmlIoConfig,
+) -> Result<(String, TextEncoding)> {
+    decode_html_document(
+        read_html_bytes(input_stream, config.max_document_bytes)?,
+        config,
+    )
+}
+
+/// same as [`read_html_document`] for the document a manifest is written into, refusing
+/// binary content so a misnamed file (e.g. a PNG saved as `.html`) is not turned into a
+/// corrupt "signed" copy
+fn read_html_to_write(
+    input_stream: &mut dyn CAIRead,
+    config: &HtmlIoConfig,
+) -> Result<(String, TextEncoding)> {
+    let buffer = read_html_bytes(input_stream, config.max_document_bytes)?;
+    if looks_binary(&buffer) {
+        return Err(Error::InvalidAsset("document is not HTML".into()));
+    }
+    decode_html_document(buffer, config)
+}
+
+fn decode_html_document(buffer: Vec<u8>, config: &HtmlIoConfig) -> Result<(String, TextEncoding)> {
+    let (html, text_encoding) = decode_html(buffer)?;
+    if let Some(max) = config.max_tokens {
+        check_token_count(&html, max)?;
+    }
+    Ok((html, text_encoding))
+}
+
+// bytes of the document checked for binary content, the resource header of the WHATWG
+// MIME sniffing algorithm
+const SNIFF_LEN: usize = 1445;
+
+/// `true` when the start of a document that is not UTF-16 contains a byte that does not
+/// appear in text, the "binary data byte" of the WHATWG MIME sniffing algorithm
+fn looks_binary(bytes: &[u8]) -> bool {
+    if matches!(
+        TextEncoding::detect(bytes),
+        TextEncoding::Utf16Le | TextEncoding::Utf16Be
+    ) {
+        return false;
+    }
+    bytes
+        .iter()
+        .take(SNIFF_LEN)
+        .any(|&b| matches!(b, 0x00..=0x08 | 0x0b | 0x0e..=0x1a | 0x1c..=0x1f))
+}
+
+/// `true` when the `<` at `offset` opens a tag, an end tag, a comment or a declaration
+fn is_token_start(bytes: &[u8], offset: usize) -> bool {
+    bytes
+        .get(offset + 1)
+        .is_some_and(|b| b.is_ascii_alphabetic() || matches!(b, b'/' | b'!' | b'?'))
+}
+
+/// fail when the document has more than `max` markup tokens, a token starts at each `<`
+/// that opens a tag, an end tag, a comment or a declaration
+fn check_token_count(html: &str, max: usize) -> Result<()> {
+    let bytes = html.as_bytes();
+    let tokens = memchr::memchr_iter(b'<', bytes)
+        .filter(|&i| is_token_start(bytes, i))
+        .take(max + 1)
+        .count();
+    if tokens > max {
+        return Err(Error::InvalidAsset("document too complex".into()));
+    }
+    Ok(())
+}
+
+/// read the raw bytes of the whole html stream from its start, a read returning no data
+/// before the length reported by the stream is retried so streams that deliver their data
+/// in bursts are not truncated
+fn read_html_bytes(
+    input_stream: &mut dyn CAIRead,
+    max_document_bytes: Option<usize>,
+) -> Result<Vec<u8>> {
+    let too_large = || Error::InvalidAsset("document too large".into());
+    let len = input_stream.seek(SeekFrom::End(0))?;
+    if max_document_bytes.is_some_and(|max| len > max as u64) {
+        return Err(too_large());
+    }
+    input_stream.rewind()?;
+
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let mut empty_reads = 0;
+    loop {
+        match input_stream.read(&mut chunk) {
+            Ok(0) if (buffer.len() as u64) < len && empty_reads < MAX_EMPTY_READS => {
+                empty_reads += 1;
+            }
+            Ok(0) => break,
+            Ok(read) => {
+                // the reported length is not trusted, the limit holds while reading
+                if max_document_bytes.is_some_and(|max| buffer.len() + read > max) {
+                    return Err(too_large());
+                }
+                buffer.extend_from_slice(&chunk[..read]);
+                empty_reads = 0;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(buffer)
+}
+
+fn decode_html(buffer: Vec<u8>) -> Result<(String, TextEncoding)> {
+    let text_encoding = TextEncoding::detect(&buffer);
+    let decoded = match text_encoding {
+        TextEncoding::Utf8 => None,
+        TextEncoding::Utf16Le => Some((UTF_16LE, &buffer[2..])),
+        TextEncoding::Utf16Be => Some((UTF_16BE, &buffer[2..])),
+        TextEncoding::Declared(encoding) => Some((encoding, &buffer[..])),
+    };
+    if let Some((encoding, bytes)) = decoded {
+        return match encoding.decode_without_bom_handling_and_without_replacement(bytes) {
+            Some(html) => Ok((html.into_owned(), text_encoding)),
+            None => Err(Error::IoError(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("HTML stream did not contain valid {}", encoding.name()),
+            ))),
+        };
+    }
+
+    let html = String::from_utf8(buffer).map_err(|_| {
+        Error::IoError(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "HTML stream did not contain valid UTF-8",
+        ))
+    })?;
+    Ok((html, text_encoding))
+}
+
+/// the encoding named by the XML declaration at the start of the bytes (after an optional
+/// UTF-8 byte order mark), only ASCII compatible encodings other than UTF-8 are returned
+fn xml_declared_encoding(bytes: &[u8]) -> Option<&'static Encoding> {
+    let bytes = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+    let rest = bytes.strip_prefix(XML_DECLARATION.as_bytes())?;
+    let end = memchr::memmem::find(rest, b"?>")?;
+    let declaration = format!("{}>", std::str::from_utf8(&rest[..end]).ok()?);
+
+    let (attributes, _) = parse_tag_attributes(&declaration, 0)?;
+    let label = find_attribute(&attributes, "encoding")?;
+    Encoding::for_label(label.trim().as_bytes())
+        .filter(|encoding| encoding.is_ascii_compatible() && *encoding != UTF_8)
+}
+
+/// rewind the output before writing, a sink that cannot seek back (append-only or
+/// write-only streams) is accepted as long as nothing was written to it yet
+fn rewind_output(output_stream: &mut dyn CAIReadWrite) -> Result<()> {
+    match output_stream.rewind() {
+        Ok(()) => Ok(()),
+        Err(_) if matches!(output_stream.stream_position(), Ok(0)) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// prepare the html stream by including a dummy manifest if no manifest is present
+fn add_required_segs_to_stream(
+    html_io: &HtmlIO,
+    input_stream: &mut dyn CAIRead,
+    output_stream: &mut dyn CAIReadWrite,
+) -> Result<()> {
+    if DEBUG {
+        println!("add_required_segs_to_stream");
+    }
+
+    let (encoded_manifest_opt, _insertion_point, _encoding) =
+        detect_manifest_location(input_stream, &html_io.config)?;
+
+    // an empty (or whitespace only) manifest element is filled by write_cai rather than
+    // getting a second one next to it
+    let need_manifest = if let Some(encoded_manifest) = encoded_manifest_opt {
+        encoded_manifest.is_empty() // if there is already a manifest and it is not empty we don't need one
+    } else {
+        true
+    };
+
+    if need_manifest {
+        // Placeholder manifest to be inserted into HTML
+        let data = placeholder_store()?;
+
+        // use the same handler so the placeholder is laid out like the final manifest
+        html_io.write_cai(input_stream, output_stream, &data)?;
+    } else {
+        // Just clone the input to the output
+        let (html, text_encoding) = read_html_document(input_stream, &html_io.config)?;
+        rewind_output(output_stream)?;
+        output_stream.write_all(&text_encoding.encode(&html))?;
+    }
+
+    Ok(())
+}
+
+/// an empty but valid JUMBF manifest store used as placeholder when sizing the manifest location
+fn placeholder_store() -> Result<Vec<u8>> {
+    let mut store_bytes = Vec::new();
+    Cai::new().write_box(&mut store_bytes)?;
+    Ok(store_bytes)
+}
+
+/// sanity check that the data starts with a JUMBF superbox header
+fn is_jumbf(data: &[u8]) -> bool {
+    let Some(header) = data.get(..8) else {
+        return false;
+    };
+    let box_len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+
+    // a length of 1 means the 64 bit extended length follows the box type
+    &header[4..8] == b"jumb" && (box_len == 1 || (8..=data.len()).contains(&box_len))
+}
+
+/// find the location of the manifest inside the html stream
+/// returns the manifest_opt, the location of the manifest content (not the location of the script tag)
+/// and the encoding of the manifest content
+fn detect_manifest_location(
+    input_stream: &mut dyn CAIRead,
+    config: &HtmlIoConfig,
+) -> Result<(Option<Vec<u8>>, usize, ManifestEncoding)> {
+    if DEBUG {
+        println!("detect_manifest_location");
+    }
+
+    let html = read_html(input_stream, config)?;
+    let max_decoded_bytes = config.max_decoded_bytes;
+
+    if let Some(bootstrap) = &config.json_bootstrap {
+        if let Some(JsonManifest {
+            value: Some(value), ..
+        }) = find_json_manifest(&html, bootstrap)?
+        {
+            let encoding = ManifestEncoding::Base64;
+            if let Some(output) =
+                decode_manifest_content(&html[value.clone()], encoding, max_decoded_bytes)?
+            {
+                return Ok((Some(output), value.start, encoding));
+            }
+        }
+    }
+
+    let mut output: Option<Vec<u8>> = None;
+    let mut encoding = ManifestEncoding::default();
+    let mut offset = None;
+
+    // Try to capture existing manifest content
+    if let Some(location) = html_scan::locate_manifest(html.as_bytes(), config.lookup()) {
+        encoding = location.encoding;
+        offset = Some(location.text.start);
+        output = decode_manifest_content(&html[location.text], encoding, max_decoded_bytes)?;
+    }
+
+    #[cfg(feature = "html_stats")]
+    count(match offset {
+        Some(offset) if head_range(&html).is_some_and(|head| head.contains(&offset)) => {
+            &STATS.detected_in_head
+        }
+        Some(_) => &STATS.detected_elsewhere,
+        None => &STATS.not_detected,
+    });
+
+    // if no manifest is found this is where write_cai would insert one -> this should NOT happen
+    if output.is_none() && DEBUG {
+        println!("no manifest found");
+    }
+
+    Ok((
+        output,
+        match offset {
+            Some(offset) => offset,
+            None => plan_manifest_placement(&html, config.lookup())?.offset(),
+        },
+        encoding,
+    ))
+}
+
+/// the length of the manifest text at `offset` with `encoded_len` characters and the line
+/// breaks of a wrapped manifest between them, see [`HtmlIoConfig::wrap_base64`]
+fn wrapped_text_len(html: &[u8], offset: usize, encoded_len: usize) -> usize {
+    let mut remaining = encoded_len;
+    html.get(offset..)
+        .unwrap_or_default()
+        .iter()
+        .take_while(|b| {
+            if remaining == 0 {
+                return false;
+            }
+            if !b.is_ascii_whitespace() {
+                remaining -= 1;
+            }
+            true
+        })
+        .count()
+}
+
+/// make sure the `len` bytes of `html` at `offset` are the encoded manifest, the regions
+/// built from the offset would otherwise hash part of the manifest or exclude document text
+fn check_manifest_offset(
+    html: &[u8],
+    offset: usize,
+    len: usize,
+    manifest: &[u8],
+    encoding: ManifestEncoding,
+) -> Result<()> {
+    let found = html
+        .get(offset..offset.saturating_add(len))
+        .and_then(|text| std::str::from_utf8(text).ok())
+        .and_then(|text| encoding.decode(text).ok());
+    if found.as_deref() != Some(manifest) {
+        return Err(Error::InvalidAsset("manifest offset mismatch".into()));
+    }
+    Ok(())
+}
+
+/// the JSON bootstrap script of [`HtmlIoConfig::json_bootstrap`]
+struct JsonManifest {
+    /// the JSON text of the script
+    content: Range<usize>,
+    /// the text of the manifest field's string value, `None` when the field is missing
+    value: Option<Range<usize>>,
+}
+
+fn find_json_manifest(html: &str, bootstrap: &JsonBootstrap) -> Result<Option<JsonManifest>> {
+    let Some(element) = script_elements(html).into_iter().find(|element| {
+        element
+            .attribute("type")
+            .is_some_and(|t| t.trim().eq_ignore_ascii_case(JSON_SCRIPT_TYPE))
+            && element.attribute("id") == Some(bootstrap.script_id.as_str())
+    }) else {
+        return Ok(None);
+    };
+    let content = element.content_start..element.content_end;
+    let text = &html[content.clone()];
+
+    let json: serde_json::Value = serde_json::from_str(text)?;
+    let Some(manifest_b64) = json.get(&bootstrap.field).and_then(|v| v.as_str()) else {
+        return Ok(Some(JsonManifest {
+            content,
+            value: None,
+        }));
+    };
+
+    // the hashed regions need the position of the value in the document, so it is located
+    // in the text after the quoted key
+    let key = serde_json::to_string(&bootstrap.field)?;
+    let value = memchr::memmem::find_iter(text.as_bytes(), key.as_bytes())
+        .find_map(|pos| {
+            let rest = text[pos + key.len()..]
+                .trim_start()
+                .strip_prefix(':')?
+                .trim_start()
+                .strip_prefix('"')?;
+            let start = content.end - rest.len();
+            (rest.starts_with(manifest_b64) && rest[manifest_b64.len()..].starts_with('"'))
+                .then_some(start..start + manifest_b64.len())
+        })
+        .ok_or_else(|| {
+            Error::InvalidAsset(
+                "HTML JSON bootstrap manifest field must be a string without escapes".into(),
+            )
+        })?;
+
+    Ok(Some(JsonManifest {
+        content,
+        value: Some(value),
+    }))
+}
+
+/// decode the base64 (or hex) content of a manifest script, `None` if the script is empty
+fn decode_manifest_content(
+    encoded: &str,
+    encoding: ManifestEncoding,
+    max_decoded_bytes: Option<usize>,
+) -> Result<Option<Vec<u8>>> {
+    let trimmed = encoded.trim();
+    if trimmed.is_empty() {
+        return Ok(None);
+    }
+
+    let decoded = encoding.decode(trimmed)?;
+    match max_decoded_bytes {
+        Some(limit) if decoded.len() > limit => Err(Error::ManifestTooLarge {
+            size: decoded.len(),
+            limit,
+        }),
+        _ => Ok(Some(decoded)),
+    }
+}
+
+// elements whose content is text, a script inserted into them is not a script
+const TEXT_ONLY_ELEMENTS: [&str; 8] = [
+    "title",
+    "textarea",
+    "style",
+    "xmp",
+    "iframe",
+    "noembed",
+    "noframes",
+    "plaintext",
+];
+
+/// warning for a manifest inserted where a parser would not create a `<script>` element,
+/// inside a comment or the text content of an element like `<textarea>` that a malformed
+/// document left open. Tables, lists and `<select>` may contain scripts.
+fn insertion_context_warning(html: &str, placement: ManifestPlacement) -> Option<String> {
+    let offset = match placement {
+        ManifestPlacement::Existing { .. } => return None,
+        ManifestPlacement::Head { offset }
+        | ManifestPlacement::Body { offset }
+        | ManifestPlacement::Append { offset } => offset,
+    };
+    let before = &html.as_bytes()[..offset];
+
+    let context = if let Some(comment) = memchr::memmem::rfind(before, b"<!--")
+        .filter(|&start| memchr::memmem::find(&before[start + 4..], b"-->").is_none())
+    {
+        Some(("a comment", comment))
+    } else {
+        TEXT_ONLY_ELEMENTS.iter().find_map(|name| {
+            let open = format!("<{name}");
+            let close = format!("</{name}");
+            let mut last_open = None;
+            let mut pos = 0;
+            while let Some(start) = find_ascii_ci(before, open.as_bytes(), pos) {
+                if is_tag_name_end(before.get(start + open.len())) {
+                    last_open = Some(start);
+                }
+                pos = start + open.len();
+            }
+            let start = last_open?;
+            find_ascii_ci(before, close.as_bytes(), start)
+                .is_none()
+                .then_some((*name, start))
+        })
+    };
+    context.map(|(context, start)| {
+        format!(
+            "HTML manifest inserted at byte {offset} is inside {context} opened at byte {start}, it will not be parsed as a script"
+        )
+    })
+}
+
+/// warnings for the non manifest scripts of the document, all of them are part of the
+/// hashed region, see [`HtmlIoConfig::warn_hashed_scripts`]
+fn hashed_script_warnings(html: &str) -> Vec<String> {
+    script_elements(html)
+        .into_iter()
+        .filter(|e| !e.is_manifest())
+        .map(|e| {
+            let source = e
+                .attribute("src")
+                .map(|src| format!(" (src=\"{src}\")"))
+                .unwrap_or_default();
+            format!(
+                "HTML script at byte {}{source} is covered by the hard binding, editing it after signing breaks validation",
+                e.start
+            )
+        })
+        .collect()
+}
+
+/// a minimal XMP packet with the Dublin Core properties found in the `<meta>` tags
+fn meta_xmp(html: &str) -> Option<String> {
+    let mut properties: Vec<(&str, String)> = Vec::new();
+    for attributes in open_tags(html, META_OPEN) {
+        let Some(name) =
+            find_attribute(&attributes, "property").or_else(|| find_attribute(&attributes, "name"))
+        else {
+            continue;
+        };
+        let Some(content) = find_attribute(&attributes, "content").map(str::trim) else {
+            continue;
+        };
+        let Some((_, dc)) = META_XMP_PROPERTIES
+            .iter()
+            .find(|(meta, _)| meta.eq_ignore_ascii_case(name))
+        else {
+            continue;
+        };
+        // the first tag for a property wins
+        if !content.is_empty() && !properties.iter().any(|(p, _)| p == dc) {
+            let value = quick_xml::escape::escape(decode_html_entities(content)).into_owned();
+            properties.push((dc, value));
+        }
+    }
+
+    if properties.is_empty() {
+        return None;
+    }
+
+    let body: String = properties
+        .iter()
+        .map(|(dc, value)| format!("<{dc}>{value}</{dc}>"))
+        .collect();
+    Some(format!(
+        r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?><x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"><rdf:Description rdf:about="" xmlns:dc="http://purl.org/dc/elements/1.1/">{body}</rdf:Description></rdf:RDF></x:xmpmeta><?xpacket end="r"?>"#
+    ))
+}
+
+/// the text of the first `<title>` element outside of scripts, entity decoded with the white
+/// space collapsed
+fn page_title(html: &str) -> Option<String> {
+    let scripts: Vec<Range<usize>> = script_elements(html)
+        .iter()
+        .map(|e| e.start..e.end)
+        .collect();
+    let bytes = html.as_bytes();
+    let mut pos = 0;
+
+    while let Some(start) = find_ascii_ci(bytes, TITLE_OPEN, pos) {
+        let name_end = start + TITLE_OPEN.len();
+        pos = name_end;
+        if !is_tag_name_end(bytes.get(name_end)) || scripts.iter().any(|s| s.contains(&start)) {
+            continue;
+        }
+
+        // the title is text only, it ends at the first end tag
+        let (_, content_start) = parse_tag_attributes(html, name_end)?;
+        let content_end = find_ascii_ci(bytes, TITLE_CLOSE, content_start).unwrap_or(html.len());
+        let title = decode_html_entities(&html[content_start..content_end])
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        return (!title.is_empty()).then_some(title);
+    }
+    None
+}
+
+/// decode all manifest scripts of the html in document order
+fn embedded_manifests(
+    html: &str,
+    max_decoded_bytes: Option<usize>,
+    lookup: ManifestLookup<'_>,
+) -> Result<Vec<EmbeddedManifest>> {
+    let mut manifests = Vec::new();
+    for element in manifest_elements(html, lookup) {
+        if let Some(store_bytes) = decode_manifest_content(
+            &html[element.manifest_text(html)],
+            element.encoding(),
+            max_decoded_bytes,
+        )? {
+            manifests.push(EmbeddedManifest {
+                id: element.attribute("id").map(str::to_owned),
+                store_bytes,
+            });
+        }
+    }
+    Ok(manifests)
+}
+
+/// decode the `data:application/c2pa` URIs of `<object>`, `<embed>` and `<img>` elements,
+/// see [`HtmlIoConfig::read_data_uris`]
+fn data_uri_manifests(
+    html: &str,
+    max_decoded_bytes: Option<usize>,
+) -> Result<Vec<EmbeddedManifest>> {
+    let mut manifests = Vec::new();
+    for (tag_open, attribute) in [
+        (OBJECT_OPEN, "data"),
+        (EMBED_OPEN, "src"),
+        (IMG_OPEN, "src"),
+    ] {
+        for attributes in open_tags(html, tag_open) {
+            let Some(encoded) = find_attribute(&attributes, attribute)
+                .map(decode_html_entities)
+                .as_deref()
+                .and_then(c2pa_data_uri_content)
+            else {
+                continue;
+            };
+            if let Some(store_bytes) =
+                decode_manifest_content(&encoded, ManifestEncoding::Base64, max_decoded_bytes)?
+            {
+                manifests.push(EmbeddedManifest {
+                    id: find_attribute(&attributes, "id").map(str::to_owned),
+                    store_bytes,
+                });
+            }
+        }
+    }
+    Ok(manifests)
+}
+
+/// base64 content of a `data:application/c2pa;base64,` URI without white space, `None` for
+/// any other URI
+fn c2pa_data_uri_content(uri: &str) -> Option<String> {
+    let uri = uri.trim();
+    let rest = uri
+        .get(..5)
+        .filter(|scheme| scheme.eq_ignore_ascii_case("data:"))
+        .map(|_| &uri[5..])?;
+    let (header, data) = rest.split_once(',')?;
+
+    let mut params = header.split(';').map(str::trim);
+    let media_type = params.next()?;
+    if !media_type.eq_ignore_ascii_case(C2PA_DATA_URI_TYPE)
+        || !params
+            .next_back()
+            .is_some_and(|p| p.eq_ignore_ascii_case("base64"))
+    {
+        return None;
+    }
+    Some(data.chars().filter(|c| !c.is_ascii_whitespace()).collect())
+}
+
+/// check a signature time against the clock and the certificate validity (in unix seconds)
+fn timestamp_report(
+    time: Option<DateTime<Utc>>,
+    validity: Option<std::ops::RangeInclusive<i64>>,
+    now: DateTime<Utc>,
+    tolerance: Option<std::time::Duration>,
+) -> TimestampReport {
+    let Some(time) = time else {
+        return TimestampReport::default();
+    };
+    let in_future = tolerance.is_some_and(|tolerance| {
+        chrono::Duration::from_std(tolerance).is_ok_and(|tolerance| time > now + tolerance)
+    });
+    let outside_validity = validity.is_some_and(|validity| !validity.contains(&time.timestamp()));
+
+    TimestampReport {
+        time: Some(time),
+        in_future,
+        outside_validity,
+    }
+}
+
+/// the node of the manifest `label` and, recursively, of its ingredients, `path` holds the
+/// labels of the manifests above it
+fn provenance_node(
+    reader: &Reader,
+    label: Option<&str>,
+    title: Option<&str>,
+    valid: bool,
+    path: &mut Vec<String>,
+) -> ProvenanceNode {
+    let mut children = Vec::new();
+    if let Some((label, manifest)) = label.and_then(|l| Some((l, reader.get_manifest(l)?))) {
+        if !path.iter().any(|l| l == label) {
+            path.push(label.to_owned());
+            for ingredient in manifest.ingredients() {
+                let valid = match ingredient.validation_results() {
+                    Some(results) => results.validation_state() != ValidationState::Invalid,
+                    None => ingredient
+                        .validation_status()
+                        .is_none_or(|status| status.iter().all(|s| s.passed())),
+                };
+                children.push(provenance_node(
+                    reader,
+                    ingredient.active_manifest(),
+                    ingredient.title(),
+                    valid,
+                    path,
+                ));
+            }
+            path.pop();
+        }
+    }
+
+    ProvenanceNode {
+        label: label.map(str::to_owned),
+        title: title.map(str::to_owned),
+        valid,
+        children,
+    }
+}
+
+/// compare the data hash of the provenance claim in `store` with the stream, `current_region`
+/// is only called on a mismatch and returns where the manifest content is now
+fn check_data_hash(
+    store: &Store,
+    input_stream: &mut dyn CAIRead,
+    current_region: impl FnOnce(&mut dyn CAIRead) -> Result<Option<Range<usize>>>,
+) -> Result<()> {
+    let claim = store.provenance_claim().ok_or(Error::ProvenanceMissing)?;
+
+    let hash_assertion = claim
+        .hash_assertions()
+        .into_iter()
+        .find(|a| a.label_raw() == DataHash::LABEL)
+        .ok_or(Error::HashMismatch(
+            "no data hash assertion found".to_owned(),
+        ))?;
+    let mut data_hash = DataHash::from_assertion(hash_assertion.assertion())?;
+    if data_hash.alg.is_none() {
+        data_hash.alg = Some(claim.alg().to_owned());
+    }
+
+    let actual = data_hash.hash_from_stream(input_stream)?;
+    if actual == data_hash.hash {
+        return Ok(());
+    }
+
+    // the manifest moving or changing size is the only place we can pinpoint
+    let first_diff_offset = match (
+        data_hash.exclusions.as_ref().and_then(|e| e.first()),
+        current_region(input_stream)?,
+    ) {
+        (Some(excluded), Some(current)) if excluded.start() != current.start => {
+            Some(excluded.start().min(current.start))
+        }
+        (Some(excluded), Some(current)) if excluded.length() != current.len() => {
+            Some(current.start + excluded.length().min(current.len()))
+        }
+        _ => None,
+    };
+
+    Err(Error::BindingMismatch {
+        expected: data_hash.hash,
+        actual,
+        first_diff_offset,
+    })
+}
+
+/// the entity decoded `srcdoc` documents of the `<iframe>` elements in the html
+fn srcdoc_documents(html: &str) -> Vec<String> {
+    open_tags(html, IFRAME_OPEN)
+        .iter()
+        .filter_map(|attributes| find_attribute(attributes, "srcdoc"))
+        .map(decode_html_entities)
+        .collect()
+}
+
+/// the document carried by the `data` fields of a Server-Sent-Events stream, `None` when the
+/// text does not start with an event field
+fn strip_sse_framing(text: &str) -> Option<String> {
+    let first = text.lines().find(|line| !line.is_empty())?;
+    let is_field = first.starts_with(':')
+        || ["data", "event", "id", "retry"].iter().any(|field| {
+            first
+                .strip_prefix(field)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(':'))
+        });
+    if !is_field {
+        return None;
+    }
+
+    let mut html = String::with_capacity(text.len());
+    let mut in_event = false;
+    for line in text.lines() {
+        if line.is_empty() {
+            // events are chunks of the document, only the lines of one event are separated
+            in_event = false;
+            continue;
+        }
+        let data = match line.strip_prefix("data") {
+            Some("") => "",
+            Some(rest) => match rest.strip_prefix(':') {
+                Some(value) => value.strip_prefix(' ').unwrap_or(value),
+                None => continue,
+            },
+            // comments and the other fields carry no document text
+            None => continue,
+        };
+        if in_event {
+            html.push('\n');
+        }
+        html.push_str(data);
+        in_event = true;
+    }
+    Some(html)
+}
+
+/// decode the character references of an attribute value, only the named references that
+/// are needed to escape markup are known, anything unknown is kept as it is
+fn decode_html_entities(value: &str) -> String {
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let reference = rest[1..].find(';').map(|end| &rest[1..end + 1]);
+        let ch = match reference {
+            Some("amp") => Some('&'),
+            Some("lt") => Some('<'),
+            Some("gt") => Some('>'),
+            Some("quot") => Some('"'),
+            Some("apos") => Some('\''),
+            Some(r) if r.starts_with("#x") || r.starts_with("#X") => {
+                u32::from_str_radix(&r[2..], 16)
+                    .ok()
+                    .and_then(char::from_u32)
+            }
+            Some(r) if r.starts_with('#') => r[1..].parse().ok().and_then(char::from_u32),
+            _ => None,
+        };
+
+        match (ch, reference) {
+            (Some(ch), Some(reference)) => {
+                decoded.push(ch);
+                rest = &rest[reference.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// the `id` for a manifest written into the html with [`HtmlIoConfig::manifest_id_prefix`],
+/// the manifest being replaced keeps its id otherwise the next free number is used
+fn next_manifest_id(html: &str, prefix: &str, lookup: ManifestLookup<'_>) -> String {
+    let sequence = |element: &ScriptElement| {
+        element
+            .attribute("id")?
+            .strip_prefix(prefix)?
+            .strip_prefix('-')?
+            .parse::<usize>()
+            .ok()
+    };
+
+    if let Some(number) = find_manifest_element(html, lookup)
+        .as_ref()
+        .and_then(sequence)
+    {
+        return format!("{prefix}-{number}");
+    }
+
+    let next = manifest_elements(html, lookup)
+        .iter()
+        .filter_map(sequence)
+        .max()
+        .unwrap_or(0)
+        + 1;
+    format!("{prefix}-{next}")
+}
+
+/// where the manifest is found or would be written in the html, see [`ManifestPlacement`]
+fn plan_manifest_placement(html: &str, lookup: ManifestLookup<'_>) -> Result<ManifestPlacement> {
+    if let Some(element) = find_manifest_element(html, lookup) {
+        // Position of base64 encoded manifest bytes (not the position of the tag)
+        return Ok(ManifestPlacement::Existing {
+            offset: element.manifest_text(html).start,
+        });
+    }
+
+    if let Some(placement) = target_placement(html, PlacementTarget::Body)? {
+        return Ok(placement);
+    }
+    if let Some(placement) = target_placement(html, PlacementTarget::Head)? {
+        return Ok(placement);
+    }
+    check_not_truncated(html)?;
+    Ok(ManifestPlacement::Append {
+        offset: html.trim_end().len(),
+    })
+}
+
+/// appending to a document cut off inside a tag, or inside a script whose end tag would
+/// then close the appended manifest, would only make it more broken
+fn check_not_truncated(html: &str) -> Result<()> {
+    let bytes = html.as_bytes();
+    let mut last_script = None;
+    let mut pos = 0;
+    while let Some(start) = find_ascii_ci(bytes, SCRIPT_OPEN, pos) {
+        pos = start + SCRIPT_OPEN.len();
+        if is_tag_name_end(bytes.get(pos)) {
+            last_script = Some(pos);
+        }
+    }
+    if memchr::memrchr(b'<', bytes)
+        .is_some_and(|last| is_token_start(bytes, last) && !bytes[last..].contains(&b'>'))
+        || last_script.is_some_and(|start| find_ascii_ci(bytes, SCRIPT_CLOSE, start).is_none())
+    {
+        return Err(Error::InvalidAsset("truncated HTML".into()));
+    }
+    Ok(())
+}
+
+/// where the body of a document whose `<body>` has no end tag ends, in front of `</html>`
+/// or the whitespace at the end of the document
+fn implied_body_end(html: &str) -> Result<Option<usize>> {
+    let Some((body, _)) = open_tag_ranges(html, BODY_OPEN).into_iter().next() else {
+        return Ok(None);
+    };
+    let end = match find_outside_scripts(html, HTML_CLOSE, body.end) {
+        Some(end) => end,
+        None => {
+            check_not_truncated(html)?;
+            html.len()
+        }
+    };
+    Ok(Some(html[..end].trim_end().len().max(body.end)))
+}
+
+/// where a manifest would be inserted for the target, `None` if the html lacks the tag
+fn target_placement(html: &str, target: PlacementTarget) -> Result<Option<ManifestPlacement>> {
+    Ok(match target {
+        PlacementTarget::Head => head_open_end(html).map(|offset| ManifestPlacement::Head {
+            offset, // Right after the <head> tag
+        }),
+        // in front of the whitespace before </body>
+        PlacementTarget::Body => match find_outside_scripts(html, BODY_CLOSE, 0) {
+            Some(end) => Some(ManifestPlacement::Body {
+                offset: html[..end].trim_end().len(),
+            }),
+            // HTML5 allows omitting the end tag of the body
+            None => implied_body_end(html)?.map(|offset| ManifestPlacement::Body { offset }),
+        },
+        PlacementTarget::Append => Some(ManifestPlacement::Append {
+            offset: html.trim_end().len(),
+        }),
+    })
+}
+
+/// the offset after the first `<head>` open tag, found with byte searches so a document
+/// that is a single long line is scanned once, a `<head>` inside script text is skipped
+fn head_open_end(html: &str) -> Option<usize> {
+    let bytes = html.as_bytes();
+    let mut scripts = None;
+    let mut pos = 0;
+    while let Some(start) = find_ascii_ci(bytes, HEAD_OPEN, pos) {
+        let name_end = start + HEAD_OPEN.len();
+        if in_script_text(html, &mut scripts, start) {
+            pos = name_end;
+            continue;
+        }
+        match bytes.get(name_end) {
+            Some(b'>') => return Some(name_end + 1),
+            // attribute values may contain `>`, the tag ends at the first one outside quotes
+            next if is_tag_name_end(next) => {
+                return parse_tag_attributes(html, name_end).map(|(_, end)| end)
+            }
+            _ => pos = name_end,
+        }
+    }
+    None
+}
+
+/// the first `needle` at or after `from` that is markup, occurrences inside the raw text
+/// of a script (e.g. `"</body>"` in a string) are skipped
+fn find_outside_scripts(html: &str, needle: &[u8], from: usize) -> Option<usize> {
+    let bytes = html.as_bytes();
+    let mut scripts = None;
+    let mut pos = from;
+    while let Some(start) = find_ascii_ci(bytes, needle, pos) {
+        if !in_script_text(html, &mut scripts, start) {
+            return Some(start);
+        }
+        pos = start + needle.len();
+    }
+    None
+}
+
+/// `true` when `offset` is inside the content of a script, the scripts are only tokenized
+/// once a candidate is found
+fn in_script_text<'a>(
+    html: &'a str,
+    scripts: &mut Option<Vec<ScriptElement<'a>>>,
+    offset: usize,
+) -> bool {
+    scripts
+        .get_or_insert_with(|| script_elements(html))
+        .iter()
+        .any(|s| (s.content_start..s.content_end).contains(&offset))
+}
+
+/// the content of the `<head>` element, up to `</head>` or, when the end tag is omitted, the
+/// `<body>` open tag
+fn head_range(html: &str) -> Option<Range<usize>> {
+    let start = head_open_end(html)?;
+    let bytes = html.as_bytes();
+    let end =
+        find_ascii_ci(bytes, b"</head", start).or_else(|| find_ascii_ci(bytes, b"<body", start))?;
+    Some(start..end)
+}
+
+/// the html without its comments, see [`HtmlIoConfig::strip_comments`], an unterminated
+/// comment is kept
+fn strip_html_comments(html: &str) -> String {
+    let bytes = html.as_bytes();
+    let raw_text: Vec<Range<usize>> = script_elements(html)
+        .into_iter()
+        .chain(elements_named(html, b"<style", b"</style"))
+        .map(|e| e.content_start..e.content_end)
+        .collect();
+
+    let mut stripped = String::with_capacity(html.len());
+    let mut copied = 0;
+    let mut pos = 0;
+    while let Some(start) = memchr::memmem::find(&bytes[pos..], b"<!--").map(|i| pos + i) {
+        pos = start + 4;
+        if raw_text.iter().any(|r| r.contains(&start)) {
+            continue;
+        }
+        let Some(end) = memchr::memmem::find(&bytes[pos..], b"-->").map(|i| pos + i + 3) else {
+            break;
+        };
+        stripped.push_str(&html[copied..start]);
+        copied = end;
+        pos = end;
+    }
+    stripped.push_str(&html[copied..]);
+    stripped
+}
+
+/// the leading white space of the line that contains `offset`
+fn line_indent(html: &str, offset: usize) -> &str {
+    let line_start = html[..offset].rfind('\n').map_or(0, |pos| pos + 1);
+    let line = &html[line_start..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// one level of indentation of the html, a tab when most indented lines start with one,
+/// otherwise the largest space width all indented lines are a multiple of
+fn indent_unit(html: &str) -> String {
+    let mut tab_lines = 0;
+    let mut space_lines = 0;
+    let mut width = 0;
+    for line in html.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.starts_with('\t') {
+            tab_lines += 1;
+        } else if line.starts_with(' ') {
+            space_lines += 1;
+            let spaces = line.len() - line.trim_start_matches(' ').len();
+            width = gcd(width, spaces);
+        }
+    }
+
+    if tab_lines > space_lines {
+        "\t".to_owned()
+    } else {
+        " ".repeat(width)
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// write the manifest block into the html at the placement
+fn splice_manifest<'a>(
+    html: &'a str,
+    placement: ManifestPlacement,
+    manifest_block: &str,
+    lookup: ManifestLookup<'_>,
+) -> Result<Cow<'a, str>> {
+    let (before, after) = match placement {
+        ManifestPlacement::Existing { .. } => {
+            // replace any existing c2pa script tag and manifest
+            let block = find_manifest_element(html, lookup)
+                .map(|element| element.block_range(html, lookup.comment))
+                .ok_or(Error::JumbfNotFound)?;
+            if html[block.clone()] == *manifest_block {
+                return Ok(Cow::Borrowed(html));
+            }
+            (&html[..block.start], &html[block.end..])
+        }
+        // inserted blocks start with their own whitespace and keep the bytes around them, so
+        // removing the block (see [`ScriptElement::block_range`]) restores the document
+        ManifestPlacement::Body { offset }
+        | ManifestPlacement::Head { offset }
+        // fallback: if HTML is broken insert manifest at the end
+        | ManifestPlacement::Append { offset } => (&html[..offset], &html[offset..]),
+    };
+    Ok(Cow::Owned(format!("{before}{manifest_block}{after}")))
+}
+
+/// attributes of the open tags starting with `tag_open` (e.g. `<link`) in document order,
+/// tags inside script raw text are skipped
+fn open_tags<'a>(html: &'a str, tag_open: &[u8]) -> Vec<Vec<(&'a str, &'a str)>> {
+    open_tag_ranges(html, tag_open)
+        .into_iter()
+        .map(|(_, attributes)| attributes)
+        .collect()
+}
+
+/// an open tag found by [`open_tag_ranges`], its range in the html and its attributes
+type OpenTag<'a> = (Range<usize>, Vec<(&'a str, &'a str)>);
+
+/// the open tags of [`open_tags`] with their range in the html
+fn open_tag_ranges<'a>(html: &'a str, tag_open: &[u8]) -> Vec<OpenTag<'a>> {
+    let bytes = html.as_bytes();
+    let scripts = script_elements(html);
+    let mut tags = Vec::new();
+    let mut pos = 0;
+
+    while let Some(start) = find_ascii_ci(bytes, tag_open, pos) {
+        let name_end = start + tag_open.len();
+        pos = name_end;
+        if !is_tag_name_end(bytes.get(name_end))
+            || scripts
+                .iter()
+                .any(|s| (s.content_start..s.content_end).contains(&start))
+        {
+            continue;
+        }
+
+        let Some((attributes, end)) = parse_tag_attributes(html, name_end) else {
+            break;
+        };
+        tags.push((start..end, attributes));
+        pos = end;
+    }
+
+    tags
+}
+
+/// `true` for the attributes of a `<link rel="c2pa-manifest">` element
+fn is_manifest_link(attributes: &[(&str, &str)]) -> bool {
+    find_attribute(attributes, "rel").is_some_and(|rel| {
+        rel.split_ascii_whitespace()
+            .any(|r| r.eq_ignore_ascii_case(C2PA_LINK_REL))
+    })
+}
+
+/// the `href` of the first manifest link of the html, as written in the document
+fn manifest_link_href(html: &str) -> Option<&str> {
+    open_tags(html, LINK_OPEN)
+        .into_iter()
+        .find(|attributes| is_manifest_link(attributes))
+        .and_then(|attributes| find_attribute(&attributes, "href").map(str::trim))
+}
+
+/// the html with a manifest link to `href`, see [`HtmlIoConfig::manifest_link`]. A link
+/// that already points there is kept as it is, so writing the same document again does not
+/// change its bytes.
+fn with_manifest_link<'a>(html: Cow<'a, str>, href: &str, q: char) -> Result<Cow<'a, str>> {
+    if href.contains(['"', '\'', '<', '>']) {
+        return Err(Error::BadParam(
+            "HTML manifest link cannot contain quotes or angle brackets".into(),
+        ));
+    }
+    let link = format!("<link rel={q}{C2PA_LINK_REL}{q} href={q}{href}{q}>");
+
+    let existing = open_tag_ranges(&html, LINK_OPEN)
+        .into_iter()
+        .find(|(_, attributes)| is_manifest_link(attributes));
+    let range = match existing {
+        Some((_, attributes))
+            if find_attribute(&attributes, "href").map(str::trim) == Some(href) =>
+        {
+            return Ok(html)
+        }
+        Some((range, _)) => range,
+        None => {
+            let offset = head_open_end(&html).ok_or_else(|| {
+                Error::BadParam("HTML manifest link needs a <head> element".into())
+            })?;
+            offset..offset
+        }
+    };
+    let mut html = html.into_owned();
+    html.replace_range(range, &link);
+    Ok(Cow::Owned(html))
+}
+
+// consecutive empty reads retried before the end of a stream that claims to be longer
+const MAX_EMPTY_READS: usize = 8;
+
+// open tags longer than this are treated as unterminated by the streaming scanner
+const MAX_OPEN_TAG_LEN: usize = 64 * 1024;
+
+/// byte reader that tracks the offset of the next byte
+struct ByteScanner<R: Read> {
+    reader: BufReader<R>,
+    offset: usize,
+}
+
+impl<R: Read> ByteScanner<R> {
+    fn new(reader: R) -> Self {
+        ByteScanner {
+            reader: BufReader::new(reader),
+            offset: 0,
+        }
+    }
+
+    fn next(&mut self) -> Result<Option<u8>> {
+        let Some(byte) = self.peek()? else {
+            return Ok(None);
+        };
+        self.reader.consume(1);
+        self.offset += 1;
+        Ok(Some(byte))
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>> {
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    /// advance until the ascii marker (matched case insensitively) has been read
+    fn skip_past(&mut self, marker: &[u8]) -> Result<bool> {
+        let mut matched = 0;
+        while let Some(byte) = self.next()? {
+            matched = if byte.eq_ignore_ascii_case(&marker[matched]) {
+                matched + 1
+            } else if byte == marker[0] {
+                1
+            } else {
+                0
+            };
+            if matched == marker.len() {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// the number of bytes before and after a manifest block that belong to a matched
+/// `<!--c2pa-begin-->` ... `<!--c2pa-end-->` pair around it, with the whitespace in front of
+/// each comment, `None` when the block is not bracketed by both. The begin comment is part of
+/// the block when it directly precedes the manifest script, see
+/// [`ScriptElement::comment_start`].
+fn sentinel_extension(before: &[u8], block: &[u8], after: &[u8]) -> Option<(usize, usize)> {
+    let is_sentinel = |comment: &[u8], text: &[u8]| {
+        comment
+            .strip_prefix(b"<!--")
+            .and_then(|c| c.strip_suffix(b"-->"))
+            .is_some_and(|c| c.trim_ascii() == text)
+    };
+
+    let before_end = before.trim_ascii_end().len();
+    let begin_outside = memchr::memmem::rfind(&before[..before_end], b"<!--")
+        .filter(|&start| is_sentinel(&before[start..before_end], SENTINEL_BEGIN));
+    let block = block.trim_ascii_start();
+    let begin_inside = memchr::memmem::find(block, b"-->")
+        .is_some_and(|end| is_sentinel(&block[..end + 3], SENTINEL_BEGIN));
+    if begin_outside.is_none() && !begin_inside {
+        return None;
+    }
+
+    let end_start = after.len() - after.trim_ascii_start().len();
+    let end_len = memchr::memmem::find(&after[end_start..], b"-->")
+        .map(|end| end + 3)
+        .filter(|&len| is_sentinel(&after[end_start..end_start + len], SENTINEL_END))?;
+    let extra_before = begin_outside.map_or(0, |start| {
+        before.len() - before[..start].trim_ascii_end().len()
+    });
+    Some((extra_before, end_start + end_len))
+}
+
+/// the manifest block found by the streaming scan, widened by the sentinel comments around
+/// it, see [`sentinel_extension`]
+fn with_sentinels(input_stream: &mut dyn CAIRead, block: Range<usize>) -> Result<Range<usize>> {
+    let mut read_window = |range: Range<usize>| -> Result<Vec<u8>> {
+        let mut window = Vec::with_capacity(range.len());
+        input_stream.seek(SeekFrom::Start(range.start as u64))?;
+        (&mut *input_stream)
+            .take(range.len() as u64)
+            .read_to_end(&mut window)?;
+        Ok(window)
+    };
+    let before = read_window(block.start.saturating_sub(SENTINEL_WINDOW)..block.start)?;
+    let head = read_window(block.start..block.end.min(block.start + SENTINEL_WINDOW))?;
+    let after = read_window(block.end..block.end + SENTINEL_WINDOW)?;
+
+    Ok(match sentinel_extension(&before, &head, &after) {
+        Some((before, after)) => block.start - before..block.end + after,
+        None => block,
+    })
+}
+
+/// the first manifest script of a stream as found by [`scan_manifest_block`]
+#[derive(Debug)]
+struct ScannedManifest {
+    /// see [`ScriptElement::block_range`]
+    block: Range<usize>,
+    /// the raw text between the open and the end tag
+    content: Range<usize>,
+    encoding: ManifestEncoding,
+}
+
+/// streaming counterpart of [`find_manifest_element`] and [`ScriptElement::block_range`],
+/// finds the active manifest block with a single forward scan of the stream while holding
+/// no more than one open tag in memory, manifests in `<template>` elements are skipped
+/// unless `templates` is set and only a preceding `comment` or sentinel is part of the block
+fn scan_manifest_block(
+    input_stream: &mut dyn CAIRead,
+    active: ActiveManifest,
+    templates: bool,
+    comment: Option<&str>,
+) -> Result<Option<ScannedManifest>> {
+    input_stream.rewind()?;
+    let mut scanner = ByteScanner::new(input_stream);
+
+    let mut script_open = 0; // bytes of `<script` matched
+    let mut template_open = 0; // bytes of `<template` matched
+    let mut template_close = 0; // bytes of `</template` matched
+    let mut template_depth = 0usize; // templates open at the current offset
+    let mut comment_open = 0; // bytes of `<!--` matched
+    let mut dashes = 0; // consecutive `-` seen, for `-->`
+    let mut text_end = 0; // offset after the last non whitespace byte
+    let mut text_end_before_lt = 0; // `text_end` before the last `<`
+    let mut comment_text_end = 0; // `text_end` before the last `<!--`
+    let mut comment_text: Option<Vec<u8>> = None; // the open comment, `MAX_OPEN_TAG_LEN` at most
+    let mut block_comment = None; // `comment_text_end` and the end of the last block comment
+    let mut found = None; // the last manifest block so far
+
+    loop {
+        let offset = scanner.offset;
+        let Some(byte) = scanner.next()? else {
+            return Ok(found);
+        };
+
+        if byte == b'<' {
+            text_end_before_lt = text_end;
+        }
+        if !byte.is_ascii_whitespace() {
+            text_end = offset + 1;
+        }
+
+        if let Some(text) = &mut comment_text {
+            if text.len() <= MAX_OPEN_TAG_LEN {
+                text.push(byte);
+            }
+        }
+        comment_open = match (comment_open, byte) {
+            (_, b'<') => 1,
+            (1, b'!') | (2, b'-') => comment_open + 1,
+            (3, b'-') => {
+                comment_text_end = text_end_before_lt;
+                comment_text = Some(Vec::new());
+                0
+            }
+            _ => 0,
+        };
+        if byte == b'>' && dashes >= 2 {
+            let is_block = comment_text.take().is_some_and(|text| {
+                text.strip_suffix(b"-->")
+                    .and_then(|text| std::str::from_utf8(text).ok())
+                    .is_some_and(|text| is_block_comment(text, comment))
+            });
+            if is_block {
+                block_comment = Some((comment_text_end, offset + 1));
+            }
+        }
+        dashes = if byte == b'-' { dashes + 1 } else { 0 };
+
+        if !templates {
+            template_open = advance_tag_match(TEMPLATE_OPEN, template_open, byte);
+            template_close = advance_tag_match(TEMPLATE_CLOSE, template_close, byte);
+            if template_open == TEMPLATE_OPEN.len() {
+                template_open = 0;
+                if is_tag_name_end(scanner.peek()?.as_ref()) {
+                    template_depth += 1;
+                }
+            }
+            if template_close == TEMPLATE_CLOSE.len() {
+                template_close = 0;
+                if is_tag_name_end(scanner.peek()?.as_ref()) {
+                    template_depth = template_depth.saturating_sub(1);
+                }
+            }
+        }
+
+        script_open = advance_tag_match(SCRIPT_OPEN, script_open, byte);
+        if script_open < SCRIPT_OPEN.len() {
+            continue;
+        }
+        script_open = 0;
+
+        let start = offset + 1 - SCRIPT_OPEN.len();
+        if !is_tag_name_end(scanner.peek()?.as_ref()) {
+            continue;
+        }
+
+        // collect the rest of the open tag, a `>` inside a quoted value does not end it
+        let mut open_tag = Vec::new();
+        let attributes = loop {
+            let Some(byte) = scanner.next()? else {
+                return Ok(found);
+            };
+            open_tag.push(byte);
+            if byte == b'>' {
+                let tag = String::from_utf8_lossy(&open_tag);
+                if let Some((attributes, _)) = parse_tag_attributes(&tag, 0) {
+                    break attributes
+                        .into_iter()
+                        .map(|(n, v)| (n.to_owned(), v.to_owned()))
+                        .collect::<Vec<_>>();
+                }
+            }
+            if open_tag.len() > MAX_OPEN_TAG_LEN {
+                return Ok(found);
+            }
+        };
+        let content_start = scanner.offset;
+
+        let script_type = attributes
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case("type"))
+            .map(|(_, v)| v.as_str());
+        let is_manifest = is_manifest_type(script_type);
+        let self_closed = is_manifest && open_tag.ends_with(b"/>");
+
+        let content_end = if self_closed {
+            content_start
+        } else {
+            // skip the raw text up to the end tag
+            loop {
+                if !scanner.skip_past(SCRIPT_CLOSE)? {
+                    return Ok(found);
+                }
+                if is_tag_name_end(scanner.peek()?.as_ref()) {
+                    break;
+                }
+            }
+            let content_end = scanner.offset - SCRIPT_CLOSE.len();
+            if !scanner.skip_past(b">")? {
+                return Ok(found);
+            }
+            content_end
+        };
+        text_end = scanner.offset;
+        comment_open = 0;
+        comment_text = None;
+        dashes = 0;
+        template_open = 0;
+        template_close = 0;
+
+        if !is_manifest || template_depth > 0 {
+            continue;
+        }
+        let encoding = manifest_type_encoding(script_type).unwrap_or_default();
+
+        let block_start = match block_comment {
+            Some((comment_text_end, comment_end)) if comment_end == start => comment_text_end,
+            _ => text_end_before_lt,
+        };
+        let manifest = ScannedManifest {
+            block: block_start..scanner.offset,
+            content: content_start..content_end,
+            encoding,
+        };
+        let marked = attributes
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(C2PA_ACTIVE_ATTRIBUTE))
+            .is_some_and(|(_, v)| is_active_marker(Some(v)));
+        match active {
+            ActiveManifest::First => return Ok(Some(manifest)),
+            ActiveManifest::MarkedOrLast if marked => return Ok(Some(manifest)),
+            _ => found = Some(manifest),
+        }
+    }
+}
+
+/// the bytes of the tag marker `tag` (starting with `<`) matched after `byte`, when `matched`
+/// bytes of it were matched before
+fn advance_tag_match(tag: &[u8], matched: usize, byte: u8) -> usize {
+    if byte.eq_ignore_ascii_case(&tag[matched]) {
+        matched + 1
+    } else if byte == b'<' {
+        1
+    } else {
+        0
+    }
+}
+
+/// HTML custom element names start with a lowercase ascii letter and contain a `-`, only
+/// the ascii subset of the allowed characters is accepted
+fn is_custom_element_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name.contains('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '.' | '_'))
+}
+
+/// async counterparts of `read_cai` and `write_cai` for `tokio` streams, the document is
+/// buffered in memory and handled by the synchronous code
+#[cfg(feature = "tokio")]
+impl HtmlIO {
+    /// Same as `read_cai`, reading the document from `reader` from its current position
+    /// to the end.
+    pub async fn read_cai_async<R>(&self, reader: &mut R) -> Result<Vec<u8>>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+    {
+        let html = self.read_async(reader).await?;
+        self.read_cai(&mut std::io::Cursor::new(html))
+    }
+
+    /// Same as `write_cai`, reading the document from `reader` and writing the document
+    /// with the manifest to `writer`, which is flushed.
+    pub async fn write_cai_async<R, W>(
+        &self,
+        reader: &mut R,
+        writer: &mut W,
+        store_bytes: &[u8],
+    ) -> Result<()>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+        W: tokio::io::AsyncWrite + Unpin,
+    {
+        use tokio::io::AsyncWriteExt;
+
+        let html = self.read_async(reader).await?;
+        let mut output = std::io::Cursor::new(Vec::new());
+        self.write_cai(&mut std::io::Cursor::new(html), &mut output, store_bytes)?;
+
+        writer.write_all(output.get_ref()).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+
+    /// read the whole document, see [`HtmlIoConfig::max_document_bytes`]
+    async fn read_async<R>(&self, reader: &mut R) -> Result<Vec<u8>>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+    {
+        use tokio::io::AsyncReadExt;
+
+        let mut html = Vec::new();
+        match self.config.max_document_bytes {
+            Some(max) => {
+                // one byte more than allowed tells a document at the limit from a larger one
+                reader.take(max as u64 + 1).read_to_end(&mut html).await?;
+                if html.len() > max {
+                    return Err(Error::InvalidAsset("document too large".into()));
+                }
+            }
+            None => {
+                reader.read_to_end(&mut html).await?;
+            }
+        }
+        Ok(html)
+    }
+}
+
+/// bindings for browsers, the document is passed as its bytes
+#[cfg(all(target_arch = "wasm32", not(target_os = "wasi")))]
+mod wasm {
+    use std::io::Cursor;
+
+    use serde::Serialize;
+    use wasm_bindgen::prelude::*;
+
+    use super::HtmlIO;
+    use crate::{
+        asset_io::{CAIReader, CAIWriter, HashBlockObjectType, HashObjectPositions},
+        Error, Reader,
+    };
+
+    /// an error passed to JavaScript as `{ code, message }`
+    #[derive(Serialize)]
+    struct JsError {
+        code: &'static str,
+        message: String,
+    }
+
+    /// convert an error into a `{ code, message }` object, `code` is `no_manifest`,
+    /// `invalid_html` or `validation_error`, errors without a code are passed as their
+    /// message string
+    fn js_error(e: Error) -> JsValue {
+        let code = match &e {
+            Error::JumbfNotFound | Error::ProvenanceMissing | Error::ClaimMissing { .. } => {
+                "no_manifest"
+            }
+            Error::InvalidAsset(_)
+            | Error::IoError(_)
+            | Error::Utf8Error(_)
+            | Error::ManifestTooLarge { .. }
+            | Error::UnsupportedType => "invalid_html",
+            Error::HashMismatch(_)
+            | Error::BindingMismatch { .. }
+            | Error::ClaimVerification(_)
+            | Error::InvalidClaim(_)
+            | Error::JumbfParseError(_)
+            | Error::ClaimMissingHardBinding
+            | Error::CoseSignature
+            | Error::CoseInvalidCert => "validation_error",
+            _ => return JsValue::from_str(&e.to_string()),
+        };
+        let error = JsError {
+            code,
+            message: e.to_string(),
+        };
+        serde_wasm_bindgen::to_value(&error).unwrap_or_else(|_| JsValue::from_str(&error.message))
+    }
+
+    /// the document with the manifest embedded and its binding status, see [`embed_and_verify`]
+    #[derive(Serialize)]
+    struct EmbedResult {
+        #[serde(with = "serde_bytes")]
+        html: Vec<u8>,
+        binding_ok: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        first_diff: Option<usize>,
+    }
+
+    /// a hashed byte range of the original document
+    #[derive(Serialize)]
+    struct HashedRegion {
+        offset: usize,
+        length: usize,
+        htype: String,
+    }
+
+    /// Checks the hard binding of the manifest embedded in `html` and returns a
+    /// `{ binding_ok, first_diff? }` object, see [`HtmlIO::binding_report`].
+    #[wasm_bindgen(js_name = verifyHtmlBinding)]
+    pub fn verify_html_binding(html: &[u8]) -> Result<JsValue, JsValue> {
+        let report = HtmlIO::default()
+            .binding_report(&mut Cursor::new(html))
+            .map_err(js_error)?;
+        serde_wasm_bindgen::to_value(&report).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Embeds the manifest store into `html` and returns the updated document, replacing an
+    /// existing manifest.
+    #[wasm_bindgen(js_name = embedManifest)]
+    pub fn embed_manifest(html: &[u8], store_bytes: &[u8]) -> Result<Vec<u8>, JsValue> {
+        let mut output = Cursor::new(Vec::new());
+        HtmlIO::default()
+            .write_cai(&mut Cursor::new(html), &mut output, store_bytes)
+            .map_err(js_error)?;
+        Ok(output.into_inner())
+    }
+
+    /// Embeds the manifest store into `html` like [`embed_manifest`] and checks the hard
+    /// binding of the result like [`verify_html_binding`], returning a
+    /// `{ html, binding_ok, first_diff? }` object with the updated document as a
+    /// `Uint8Array`.
+    #[wasm_bindgen(js_name = embedAndVerify)]
+    pub fn embed_and_verify(html: &[u8], store_bytes: &[u8]) -> Result<JsValue, JsValue> {
+        let html = embed_manifest(html, store_bytes)?;
+        let report = HtmlIO::default()
+            .binding_report(&mut Cursor::new(&html))
+            .map_err(js_error)?;
+        let result = EmbedResult {
+            html,
+            binding_ok: report.binding_ok,
+            first_diff: report.first_diff,
+        };
+        serde_wasm_bindgen::to_value(&result).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Returns the byte regions of `html` covered by the hard binding as an array of
+    /// `{ offset, length, htype }` objects, so the document can be re-hashed in the
+    /// browser. The offsets are byte offsets into `html`, which must already contain a
+    /// manifest.
+    #[wasm_bindgen(js_name = hashedRegions)]
+    pub fn hashed_regions(html: &[u8]) -> Result<JsValue, JsValue> {
+        let html_io = HtmlIO::default();
+        // without a manifest the positions would refer to a document with a placeholder
+        html_io.read_cai(&mut Cursor::new(html)).map_err(js_error)?;
+
+        let regions: Vec<HashedRegion> = html_io
+            .get_object_locations_from_stream(&mut Cursor::new(html))
+            .map_err(js_error)?
+            .into_iter()
+            .filter(|region| region.htype != HashBlockObjectType::Cai)
+            .map(
+                |HashObjectPositions {
+                     offset,
+                     length,
+                     htype,
+                 }| HashedRegion {
+                    offset,
+                    length,
+                    htype: htype.to_string(),
+                },
+            )
+            .collect();
+        serde_wasm_bindgen::to_value(&regions).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Reads and validates the manifest store embedded in `html` and returns it as JSON.
+    /// Failures are `{ code, message }` objects with the code `no_manifest`, `invalid_html` or
+    /// `validation_error`, other errors are passed as their message string.
+    #[wasm_bindgen(js_name = readManifest)]
+    pub fn read_manifest(html: &[u8]) -> Result<String, JsValue> {
+        let reader = Reader::from_stream("text/html", Cursor::new(html)).map_err(js_error)?;
+        Ok(reader.json())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        #![allow(clippy::unwrap_used)]
+
+        use wasm_bindgen_test::wasm_bindgen_test;
+
+        use super::{super::placeholder_store, *};
+        use crate::{utils::test_signer::test_signer, Builder, SigningAlg};
+
+        #[wasm_bindgen_test]
+        fn test_hashed_regions() {
+            let html = "<html><head></head><body><p>Hello</p></body></html>";
+            let mut output = Cursor::new(Vec::new());
+            HtmlIO::default()
+                .write_cai(
+                    &mut Cursor::new(html),
+                    &mut output,
+                    &placeholder_store().unwrap(),
+                )
+                .unwrap();
+            let written = output.into_inner();
+
+            let regions: Vec<serde_json::Value> =
+                serde_wasm_bindgen::from_value(hashed_regions(&written).unwrap()).unwrap();
+            assert_eq!(regions.len(), 2);
+            assert!(regions.iter().all(|region| region["htype"] == "Other"));
+            assert_eq!(regions[0]["offset"], 0);
+            let end =
+                regions[1]["offset"].as_u64().unwrap() + regions[1]["length"].as_u64().unwrap();
+            assert_eq!(end as usize, written.len());
+
+            assert!(hashed_regions(html.as_bytes()).is_err());
+        }
+
+        #[wasm_bindgen_test]
+        fn test_embed_and_verify() {
+            let html = "<html><head></head><body><p>Hello</p></body></html>";
+            let mut builder = Builder::from_json(
+                r#"{"claim_generator_info": [{"name": "c2pa_test", "version": "1.0.0"}]}"#,
+            )
+            .unwrap();
+            let mut signed = Cursor::new(Vec::new());
+            builder
+                .sign(
+                    test_signer(SigningAlg::Ps256).as_ref(),
+                    "html",
+                    &mut Cursor::new(html),
+                    &mut signed,
+                )
+                .unwrap();
+            let signed = signed.into_inner();
+            let store = HtmlIO::default()
+                .read_cai(&mut Cursor::new(&signed))
+                .unwrap();
+
+            #[derive(serde::Deserialize)]
+            struct Embedded {
+                #[serde(with = "serde_bytes")]
+                html: Vec<u8>,
+                binding_ok: bool,
+            }
+            let embed = |html: &str| -> Embedded {
+                serde_wasm_bindgen::from_value(embed_and_verify(html.as_bytes(), &store).unwrap())
+                    .unwrap()
+            };
+
+            // embedding the store into the page it was signed for gives the signed document
+            let result = embed(html);
+            assert!(result.binding_ok);
+            assert_eq!(result.html, signed);
+
+            // into another page it does not validate
+            assert!(!embed(&html.replace("Hello", "Goodbye")).binding_ok);
+
+            assert!(embed_and_verify(html.as_bytes(), &placeholder_store().unwrap()).is_err());
+        }
+
+        fn error_code(error: JsValue) -> String {
+            let error: serde_json::Value = serde_wasm_bindgen::from_value(error).unwrap();
+            error["code"].as_str().unwrap().to_owned()
+        }
+
+        #[wasm_bindgen_test]
+        fn test_read_manifest_errors() {
+            let html = b"<html><head></head><body><p>Hello</p></body></html>";
+            assert_eq!(error_code(read_manifest(html).unwrap_err()), "no_manifest");
+
+            // neither UTF-8 nor UTF-16
+            let malformed = b"<html><body>\xff\xfe\xfd</body></html>";
+            assert_eq!(
+                error_code(read_manifest(malformed).unwrap_err()),
+                "invalid_html"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    #![allow(clippy::expect_used)]
+    #![allow(clippy::panic)]
+    #![allow(clippy::unwrap_used)]
+
+    use std::io::Cursor;
+
+    use regex::Regex;
+
+    use super::*;
+    use crate::{
+        crypto::raw_signature::SigningAlg,
+        utils::{
+            io_utils::tempdirectory,
+            test::{create_test_claim, temp_dir_path},
+            test_signer::test_signer,
+        },
+        Builder,
+    };
+
+    const SAMPLE_HTML: &str = "<!DOCTYPE html>\n<html>\n<head>\n<title>Sample</title>\n</head>\n<body>\n<p>Hello</p>\n</body>\n</html>\n";
+
+    /// sign the html with the test signer and return the signed document
+    fn sign_sample(html: &str) -> Vec<u8> {
+        sign_as(html, "text/html")
+    }
+
+    /// sign the html as the given format
+    fn sign_as(html: &str, format: &str) -> Vec<u8> {
+        let mut builder = Builder::from_json(
+            r#"{"claim_generator_info": [{"name": "c2pa_test", "version": "1.0.0"}]}"#,
+        )
+        .unwrap();
+        let signer = test_signer(SigningAlg::Ps256);
+        let mut source = Cursor::new(html.as_bytes().to_vec());
+        let mut dest = Cursor::new(Vec::new());
+        builder
+            .sign(signer.as_ref(), format, &mut source, &mut dest)
+            .unwrap();
+        dest.into_inner()
+    }
+
+    #[test]
+    fn test_save_cai_store_missing_file_reports_read_step() {
+        let temp_dir = tempdirectory().unwrap();
+        let output = temp_dir_path(&temp_dir, "missing.html");
+
+        let html_io = HtmlIO::new("html");
+        match html_io.save_cai_store(&output, b"some test data") {
+            Err(Error::IoError(e)) => {
+                assert_eq!(e.kind(), std::io::ErrorKind::NotFound);
+                let msg = e.to_string();
+                assert!(msg.contains("read step"));
+                assert!(msg.contains("missing.html"));
+            }
+            _ => panic!("expected an IoError"),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_save_cai_store_read_only_dir_reports_rename_step() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempdirectory().unwrap();
+        let read_only_dir = temp_dir.path().join("read_only");
+        std::fs::create_dir(&read_only_dir).unwrap();
+        let output = read_only_dir.join("sample.html");
+        std::fs::write(&output, SAMPLE_HTML).unwrap();
+
+        std::fs::set_permissions(&output, std::fs::Permissions::from_mode(0o444)).unwrap();
+        std::fs::set_permissions(&read_only_dir, std::fs::Permissions::from_mode(0o555)).unwrap();
+
+        // privileged users ignore the permissions so there is nothing to simulate
+        let privileged = std::fs::File::create(read_only_dir.join("probe")).is_ok();
+
+        let html_io = HtmlIO::new("html");
+        let result = html_io.save_cai_store(&output, b"some test data");
+
+        std::fs::set_permissions(&read_only_dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        if privileged {
+            return;
+        }
+        match result {
+            Err(Error::IoError(e)) => {
+                let msg = e.to_string();
+                assert!(msg.contains("rename step"));
+                assert!(msg.contains("sample.html"));
+            }
+            _ => panic!("expected an IoError"),
+        }
+    }
+
+    #[test]
+    fn test_verify_binding_signed() {
+        let signed = sign_sample(SAMPLE_HTML);
+
+        let html_io = HtmlIO::new("html");
+        html_io.verify_binding(&mut Cursor::new(signed)).unwrap();
+    }
+
+    #[test]
+    fn test_verify_binding_tampered_content() {
+        let signed = String::from_utf8(sign_sample(SAMPLE_HTML)).unwrap();
+        let tampered = signed.replace("<p>Hello</p>", "<p>Hellp</p>");
+
+        let html_io = HtmlIO::new("html");
+        match html_io.verify_binding(&mut Cursor::new(tampered.into_bytes())) {
+            Err(Error::BindingMismatch {
+                expected,
+                actual,
+                first_diff_offset,
+            }) => {
+                assert_ne!(expected, actual);
+                assert_eq!(first_diff_offset, None);
+            }
+            _ => panic!("expected a binding mismatch"),
+        }
+    }
+
+    #[test]
+    fn test_verify_binding_tampered_before_manifest() {
+        let signed = String::from_utf8(sign_sample(SAMPLE_HTML)).unwrap();
+        let tampered = signed.replace("<title>Sample</title>", "<title>Sample page</title>");
+
+        let html_io = HtmlIO::new("html");
+        match html_io.verify_binding(&mut Cursor::new(tampered.into_bytes())) {
+            Err(Error::BindingMismatch {
+                first_diff_offset, ..
+            }) => {
+                // the manifest moved so the difference is reported at the signed manifest start
+                let tag = format!(r#"type="{C2PA_SCRIPT_TYPE}">"#);
+                let manifest_start = signed.find(&tag).unwrap() + tag.len();
+                assert_eq!(first_diff_offset, Some(manifest_start));
+            }
+            _ => panic!("expected a binding mismatch"),
+        }
+    }
+
+    #[test]
+    fn test_read_manifest_followed_by_script_comment() {
+        let manifest = b"manifest bytes";
+        let b64 = STANDARD.encode(manifest);
+        let html = format!(
+            r#"<html><head><script type="application/c2pa-manifest">{b64}</script><!-- not the end </script> --></head><body></body></html>"#
+        );
+
+        let html_io = HtmlIO::new("html");
+        let data = html_io.read_cai(&mut Cursor::new(html.as_bytes())).unwrap();
+        assert_eq!(data, manifest);
+
+        let element = find_manifest_element(&html, ManifestLookup::default()).unwrap();
+        assert_eq!(&html[element.content_start..element.content_end], b64);
+        assert_eq!(&html[element.end..element.end + 4], "<!--");
+    }
+
+    #[test]
+    fn test_read_manifest_with_spaced_end_tag() {
+        let manifest = b"manifest bytes";
+        let b64 = STANDARD.encode(manifest);
+        let html = format!(
+            r#"<html><body><script type="application/c2pa-manifest">{b64}</SCRIPT ></body></html>"#
+        );
+
+        let html_io = HtmlIO::new("html");
+        let data = html_io.read_cai(&mut Cursor::new(html.as_bytes())).unwrap();
+        assert_eq!(data, manifest);
+    }
+
+    #[test]
+    fn test_manifest_markup_inside_other_script_is_ignored() {
+        let manifest = b"manifest bytes";
+        let b64 = STANDARD.encode(manifest);
+        let html = format!(
+            r#"<html><head><script>var tag = '<script type="application/c2pa-manifest">';</script></head><body><script type="application/c2pa-manifest">{b64}</script></body></html>"#
+        );
+
+        let html_io = HtmlIO::new("html");
+        let data = html_io.read_cai(&mut Cursor::new(html.as_bytes())).unwrap();
+        assert_eq!(data, manifest);
+    }
+
+    fn commented_html_io() -> HtmlIO {
+        HtmlIO::with_config(HtmlIoConfig {
+            manifest_comment: Some("C2PA manifest: verify at https://example.com/verify".into()),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_write_manifest_comment_round_trip() {
+        let html_io = commented_html_io();
+
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(
+                &mut Cursor::new(SAMPLE_HTML),
+                &mut output,
+                b"first manifest",
+            )
+            .unwrap();
+        let written = String::from_utf8(output.get_ref().clone()).unwrap();
+        assert!(written.contains(
+            r#"<!-- C2PA manifest: verify at https://example.com/verify --><script type="application/c2pa-manifest">"#
+        ));
+        assert_eq!(html_io.read_cai(&mut output).unwrap(), b"first manifest");
+
+        // replacing the manifest must not duplicate the comment
+        let mut replaced = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut output, &mut replaced, b"second manifest")
+            .unwrap();
+        let replaced_html = String::from_utf8(replaced.get_ref().clone()).unwrap();
+        assert_eq!(replaced_html.matches("<!-- C2PA manifest").count(), 1);
+        assert_eq!(html_io.read_cai(&mut replaced).unwrap(), b"second manifest");
+
+        // removing the manifest also removes the comment
+        let mut removed = Cursor::new(Vec::new());
+        html_io
+            .remove_cai_store_from_stream(&mut replaced, &mut removed)
+            .unwrap();
+        let removed_html = String::from_utf8(removed.into_inner()).unwrap();
+        assert!(!removed_html.contains("<!--"));
+        assert!(!removed_html.contains(C2PA_SCRIPT_TYPE));
+
+        // without the config the comment is not known to belong to the manifest
+        let mut removed = Cursor::new(Vec::new());
+        HtmlIO::new("html")
+            .remove_cai_store_from_stream(&mut replaced, &mut removed)
+            .unwrap();
+        let removed_html = String::from_utf8(removed.into_inner()).unwrap();
+        assert_eq!(removed_html.matches("<!-- C2PA manifest").count(), 1);
+        assert!(!removed_html.contains(C2PA_SCRIPT_TYPE));
+    }
+
+    #[test]
+    fn test_other_comments_kept() {
+        let store = placeholder_store().unwrap();
+        let script = format!(
+            "<script type=\"{C2PA_SCRIPT_TYPE}\">{}</script>",
+            STANDARD.encode(&store)
+        );
+        let comment = "<!-- analytics: keep me -->";
+        let html = SAMPLE_HTML.replace("</head>", &format!("{comment}{script}\n</head>"));
+        let expected = SAMPLE_HTML.replace("</head>", &format!("{comment}\n</head>"));
+
+        for html_io in [HtmlIO::default(), commented_html_io()] {
+            let element = find_manifest_element(&html, html_io.config.lookup()).unwrap();
+            let configured = html_io.config.manifest_comment.as_deref();
+            assert_eq!(element.comment_start(&html, configured), None);
+
+            // both the in-memory and the streaming removal leave the comment alone
+            let mut removed = Cursor::new(Vec::new());
+            html_io
+                .remove_cai_store_from_stream(&mut Cursor::new(&html), &mut removed)
+                .unwrap();
+            assert_eq!(String::from_utf8(removed.into_inner()).unwrap(), expected);
+            let scanned = scan_manifest_block(
+                &mut Cursor::new(&html),
+                ActiveManifest::default(),
+                false,
+                configured,
+            )
+            .unwrap()
+            .unwrap();
+            assert_eq!(scanned.block, element.block_range(&html, configured));
+            assert_eq!(&html[scanned.block], script);
+
+            // replacing keeps it in front of the new manifest
+            let mut replaced = Cursor::new(Vec::new());
+            html_io
+                .write_cai(&mut Cursor::new(&html), &mut replaced, b"new manifest")
+                .unwrap();
+            let replaced = String::from_utf8(replaced.into_inner()).unwrap();
+            assert_eq!(replaced.matches(comment).count(), 1);
+            assert_eq!(
+                html_io.read_cai(&mut Cursor::new(&replaced)).unwrap(),
+                b"new manifest"
+            );
+        }
+
+        // the sentinel belongs to the block without any configured comment
+        let bracketed = html.replace(comment, "<!-- c2pa-begin -->");
+        let element = find_manifest_element(&bracketed, ManifestLookup::default()).unwrap();
+        assert!(element.comment_start(&bracketed, None).is_some());
+    }
+
+    #[test]
+    fn test_manifest_comment_in_hashed_region() {
+        let html_io = commented_html_io();
+
+        let locations = html_io
+            .get_object_locations_from_stream(&mut Cursor::new(SAMPLE_HTML))
+            .unwrap();
+
+        // the regions computed before writing must match the written document
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(
+                &mut Cursor::new(SAMPLE_HTML),
+                &mut output,
+                &placeholder_store().unwrap(),
+            )
+            .unwrap();
+        let written = String::from_utf8(output.get_ref().clone()).unwrap();
+        let written_locations = html_io
+            .get_object_locations_from_stream(&mut output)
+            .unwrap();
+        assert_eq!(locations, written_locations);
+
+        // the comment is hashed with the content in front of the manifest
+        let cai = &locations[0];
+        let before = &locations[1];
+        assert_eq!(before.htype, HashBlockObjectType::Other);
+        assert!(written[before.offset..before.offset + before.length]
+            .contains("<!-- C2PA manifest: verify at https://example.com/verify -->"));
+        assert!(!written[cai.offset..cai.offset + cai.length].contains("<!--"));
+    }
+
+    #[test]
+    fn test_manifest_comment_rejects_comment_end() {
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            manifest_comment: Some("not --> allowed".into()),
+            ..Default::default()
+        });
+
+        let result = html_io.write_cai(
+            &mut Cursor::new(SAMPLE_HTML),
+            &mut Cursor::new(Vec::new()),
+            b"data",
+        );
+        assert!(matches!(result, Err(Error::BadParam(_))));
+    }
+
+    #[test]
+    fn test_manifest_placement_existing() {
+        let html_io = HtmlIO::new("html");
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut output, b"manifest")
+            .unwrap();
+
+        let written = String::from_utf8(output.get_ref().clone()).unwrap();
+        let offset = written.find(&STANDARD.encode(b"manifest")).unwrap();
+        assert_eq!(
+            html_io.manifest_placement(&mut output).unwrap(),
+            ManifestPlacement::Existing { offset }
+        );
+    }
+
+    #[test]
+    fn test_manifest_placement_head() {
+        let html = "<html><head><title>t</title></head><header>x</header></html>";
+
+        let html_io = HtmlIO::new("html");
+        let placement = html_io.manifest_placement(&mut Cursor::new(html)).unwrap();
+        assert_eq!(placement, ManifestPlacement::Head { offset: 12 });
+
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(html), &mut output, b"manifest")
+            .unwrap();
+        let written = String::from_utf8(output.into_inner()).unwrap();
+        assert!(written.starts_with(r#"<html><head><script type="application/c2pa-manifest">"#));
+    }
+
+    #[test]
+    fn test_manifest_placement_body() {
+        let html = "<body><header>x</header><p>text</p>\n</body>";
+
+        let html_io = HtmlIO::new("html");
+        let placement = html_io.manifest_placement(&mut Cursor::new(html)).unwrap();
+        assert_eq!(placement, ManifestPlacement::Body { offset: 35 });
+
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(html), &mut output, b"manifest")
+            .unwrap();
+        let written = String::from_utf8(output.into_inner()).unwrap();
+        assert!(written.starts_with(r#"<body><header>x</header><p>text</p><script"#));
+        assert!(written.ends_with("</script>\n</body>"));
+    }
+
+    #[test]
+    fn test_manifest_placement_skips_tags_in_script_strings() {
+        let html = r#"<body><script>var s="</body>";</script><p>text</p></body>"#;
+
+        let html_io = HtmlIO::new("html");
+        let placement = html_io.manifest_placement(&mut Cursor::new(html)).unwrap();
+        assert_eq!(placement, ManifestPlacement::Body { offset: 50 });
+
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(html), &mut output, b"manifest")
+            .unwrap();
+        let written = String::from_utf8(output.into_inner()).unwrap();
+        assert!(written.starts_with(&html[..50]));
+        assert!(written[50..].starts_with("<script"));
+        assert_eq!(
+            html_io
+                .read_cai(&mut Cursor::new(written.as_bytes()))
+                .unwrap(),
+            b"manifest"
+        );
+
+        let html = r#"<script>document.write("<head>")</script><head><title>t</title></head>"#;
+        let placement = html_io.manifest_placement(&mut Cursor::new(html)).unwrap();
+        assert_eq!(placement, ManifestPlacement::Head { offset: 47 });
+    }
+
+    #[test]
+    fn test_manifest_placement_append() {
+        let html = "<div>fragment</div>\n\n";
+
+        let html_io = HtmlIO::new("html");
+        let placement = html_io.manifest_placement(&mut Cursor::new(html)).unwrap();
+        assert_eq!(placement, ManifestPlacement::Append { offset: 19 });
+    }
+
+    fn id_html_io() -> HtmlIO {
+        HtmlIO::with_config(HtmlIoConfig {
+            manifest_id_prefix: Some("c2pa-manifest".into()),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_manifest_id_round_trip() {
+        let html_io = id_html_io();
+
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(
+                &mut Cursor::new(SAMPLE_HTML),
+                &mut output,
+                b"first manifest",
+            )
+            .unwrap();
+        let written = String::from_utf8(output.get_ref().clone()).unwrap();
+        assert!(
+            written.contains(r#"<script type="application/c2pa-manifest" id="c2pa-manifest-1">"#)
+        );
+
+        // the replaced manifest keeps its id
+        let mut replaced = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut output, &mut replaced, b"second manifest")
+            .unwrap();
+        let manifests = html_io.read_manifests(&mut replaced).unwrap();
+        assert_eq!(
+            manifests,
+            vec![EmbeddedManifest {
+                id: Some("c2pa-manifest-1".into()),
+                store_bytes: b"second manifest".to_vec(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_manifest_id_sequential() {
+        let html = format!(
+            r#"<html><body><script type="application/c2pa-manifest" data-c2pa-active="true">{}</script><script type="application/c2pa-manifest" id="c2pa-manifest-2">{}</script></body></html>"#,
+            STANDARD.encode(b"unnamed"),
+            STANDARD.encode(b"second")
+        );
+
+        // the active first manifest is replaced and gets the next free id
+        let html_io = id_html_io();
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(html), &mut output, b"third")
+            .unwrap();
+
+        let manifests = html_io.read_manifests(&mut output).unwrap();
+        assert_eq!(manifests.len(), 2);
+        assert_eq!(manifests[0].id.as_deref(), Some("c2pa-manifest-3"));
+        assert_eq!(
+            html_io
+                .read_manifest_by_id(&mut output, "c2pa-manifest-2")
+                .unwrap(),
+            b"second"
+        );
+        assert_eq!(
+            html_io
+                .read_manifest_by_id(&mut output, "c2pa-manifest-3")
+                .unwrap(),
+            b"third"
+        );
+        assert!(matches!(
+            html_io.read_manifest_by_id(&mut output, "c2pa-manifest-1"),
+            Err(Error::JumbfNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_placeholder_store_is_jumbf() {
+        let placeholder = placeholder_store().unwrap();
+        assert!(is_jumbf(&placeholder));
+        assert!(!is_jumbf(b"placeholder manifest"));
+        assert!(!is_jumbf(&placeholder[..placeholder.len() - 1]));
+    }
+
+    #[test]
+    fn test_object_locations_sizing_with_jumbf_check() {
+        let html_io = HtmlIO::new("html");
+        let locations = html_io
+            .get_object_locations_from_stream(&mut Cursor::new(SAMPLE_HTML))
+            .unwrap();
+
+        let cai = locations
+            .iter()
+            .find(|o| o.htype == HashBlockObjectType::Cai)
+            .unwrap();
+        assert_eq!(
+            cai.length,
+            STANDARD.encode(placeholder_store().unwrap()).len()
+        );
+
+        // a manifest that is not JUMBF cannot be sized
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut output, b"not jumbf")
+            .unwrap();
+        assert!(matches!(
+            html_io.get_object_locations_from_stream(&mut output),
+            Err(Error::InvalidAsset(_))
+        ));
+    }
+
+    const SAMPLE_XHTML: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<html xmlns=\"http://www.w3.org/1999/xhtml\">\n<head>\n<title>Sample</title>\n</head>\n<body>\n<p>Hello</p>\n</body>\n</html>\n";
+
+    #[test]
+    fn test_xhtml_types_dispatch_to_html_handler() {
+        for asset_type in ["xht", "xhtml", "application/xhtml+xml"] {
+            let handler = crate::jumbf_io::get_assetio_handler(asset_type).unwrap();
+            assert!(handler.supported_types().contains(&"text/html"));
+            assert!(crate::jumbf_io::get_caiwriter_handler(asset_type).is_some());
+        }
+    }
+
+    #[test]
+    fn test_xht_file_round_trip() {
+        let temp_dir = tempdirectory().unwrap();
+        let output = temp_dir_path(&temp_dir, "sample.xht");
+        std::fs::write(&output, SAMPLE_XHTML).unwrap();
+
+        let handler = crate::jumbf_io::get_assetio_handler("xht").unwrap();
+        let store = placeholder_store().unwrap();
+        handler.save_cai_store(&output, &store).unwrap();
+        assert_eq!(handler.read_cai_store(&output).unwrap(), store);
+
+        // the written document must still be well-formed XML
+        let written = std::fs::read_to_string(&output).unwrap();
+        let mut reader = quick_xml::Reader::from_str(&written);
+        loop {
+            match reader.read_event().unwrap() {
+                quick_xml::events::Event::Eof => break,
+                _ => continue,
+            }
+        }
+    }
+
+    #[cfg(feature = "html_verification_cache")]
+    #[test]
+    fn test_verification_cache_hit_skips_validation() {
+        let html_io = HtmlIO::new("html");
+        let signed = sign_sample(SAMPLE_HTML);
+        let mut cache = VerificationCache::new(1);
+
+        html_io
+            .verify_binding_cached(&mut Cursor::new(&signed), &mut cache)
+            .unwrap();
+        html_io
+            .verify_binding_cached(&mut Cursor::new(&signed), &mut cache)
+            .unwrap();
+        assert_eq!((cache.misses(), cache.hits()), (1, 1));
+
+        // a tampered document is a different key and evicts the signed one
+        let tampered = String::from_utf8(signed.clone())
+            .unwrap()
+            .replace("Hello", "Howdy");
+        for _ in 0..2 {
+            assert!(matches!(
+                html_io.verify_binding_cached(&mut Cursor::new(tampered.as_bytes()), &mut cache),
+                Err(Error::BindingMismatch { .. })
+            ));
+        }
+        assert_eq!((cache.misses(), cache.hits(), cache.len()), (2, 2, 1));
+
+        cache.clear();
+        assert!(cache.is_empty());
+        html_io
+            .verify_binding_cached(&mut Cursor::new(&signed), &mut cache)
+            .unwrap();
+        assert_eq!(cache.misses(), 3);
+    }
+
+    #[test]
+    fn test_remote_manifest_url_honors_base_href() {
+        let html_io = HtmlIO::new("html");
+        let html = |head: &str| SAMPLE_HTML.replace("<head>\n", &format!("<head>\n{head}\n"));
+
+        let relative = html(
+            r#"<base href="https://cdn.example.com/assets/"><link rel="c2pa-manifest" href="manifests/page.c2pa">"#,
+        );
+        assert_eq!(
+            html_io
+                .remote_manifest_url(
+                    &mut Cursor::new(relative),
+                    Some("https://example.com/news/page.html")
+                )
+                .unwrap()
+                .as_deref(),
+            Some("https://cdn.example.com/assets/manifests/page.c2pa")
+        );
+
+        // a relative base is resolved against the document url
+        let relative_base =
+            html(r#"<base href="/static/"><link href="page.c2pa" rel="alternate c2pa-manifest">"#);
+        assert_eq!(
+            html_io
+                .remote_manifest_url(
+                    &mut Cursor::new(relative_base),
+                    Some("https://example.com/news/page.html")
+                )
+                .unwrap()
+                .as_deref(),
+            Some("https://example.com/static/page.c2pa")
+        );
+
+        // absolute urls ignore the base
+        let absolute = html(
+            r#"<base href="https://cdn.example.com/"><link rel="c2pa-manifest" href="https://manifests.example.org/page.c2pa">"#,
+        );
+        assert_eq!(
+            html_io
+                .remote_manifest_url(&mut Cursor::new(absolute), None)
+                .unwrap()
+                .as_deref(),
+            Some("https://manifests.example.org/page.c2pa")
+        );
+
+        // without any base the href cannot be resolved
+        let unresolved = html(r#"<link rel="c2pa-manifest" href="page.c2pa">"#);
+        assert!(matches!(
+            html_io.remote_manifest_url(&mut Cursor::new(unresolved), None),
+            Err(Error::BadParam(_))
+        ));
+
+        assert_eq!(
+            html_io
+                .remote_manifest_url(&mut Cursor::new(SAMPLE_HTML), None)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_hashed_script_warnings() {
+        let html = SAMPLE_HTML.replace(
+            "<p>Hello</p>\n",
+            "<p>Hello</p>\n<script src=\"ads.js\"></script>\n<script>track();</script>\n",
+        );
+        let warnings = hashed_script_warnings(&html);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains(r#"src="ads.js""#));
+
+        // writing with the option still embeds the manifest, the manifest itself is not reported
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            warn_hashed_scripts: true,
+            ..Default::default()
+        });
+        let store = placeholder_store().unwrap();
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(&html), &mut output, &store)
+            .unwrap();
+        assert_eq!(html_io.read_cai(&mut output).unwrap(), store);
+        assert_eq!(
+            hashed_script_warnings(&String::from_utf8(output.into_inner()).unwrap()).len(),
+            2
+        );
+        assert!(hashed_script_warnings(SAMPLE_HTML).is_empty());
+    }
+
+    #[test]
+    fn test_read_manifest_from_iframe_srcdoc() {
+        let store = placeholder_store().unwrap();
+        let mut inner = Cursor::new(Vec::new());
+        HtmlIO::new("html")
+            .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut inner, &store)
+            .unwrap();
+        let srcdoc = String::from_utf8(inner.into_inner())
+            .unwrap()
+            .replace('&', "&amp;")
+            .replace('"', "&quot;")
+            .replace('<', "&lt;");
+        let html = SAMPLE_HTML.replace(
+            "<p>Hello</p>",
+            &format!(r#"<iframe title="embed" srcdoc="{srcdoc}"></iframe>"#),
+        );
+
+        // the document itself has no manifest
+        assert!(matches!(
+            HtmlIO::new("html").read_cai(&mut Cursor::new(&html)),
+            Err(Error::JumbfNotFound)
+        ));
+
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            read_iframe_srcdoc: true,
+            ..Default::default()
+        });
+        assert_eq!(html_io.read_cai(&mut Cursor::new(&html)).unwrap(), store);
+        assert_eq!(
+            html_io
+                .read_manifests(&mut Cursor::new(&html))
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_decode_html_entities() {
+        assert_eq!(
+            decode_html_entities("&lt;p class=&quot;a&quot;&gt;&#72;&#x69; &amp; &copy; &bogus"),
+            "<p class=\"a\">Hi & &copy; &bogus"
+        );
+    }
+
+    #[test]
+    fn test_default_matches_asset_io_new() {
+        let html_io = HtmlIO::default();
+        assert_eq!(
+            html_io.supported_types(),
+            HtmlIO::new("html").supported_types()
+        );
+        assert!(html_io.config().manifest_comment.is_none());
+
+        let store = placeholder_store().unwrap();
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut output, &store)
+            .unwrap();
+        assert_eq!(html_io.read_cai(&mut output).unwrap(), store);
+    }
+
+    const SAMPLE_FRAGMENT: &str = "<div class=\"card\">\n<p>Hello</p>\n</div>\n";
+
+    #[test]
+    fn test_fragment_round_trip() {
+        let html_io = HtmlIO::default();
+        let store = placeholder_store().unwrap();
+        assert_eq!(
+            html_io
+                .manifest_placement(&mut Cursor::new(SAMPLE_FRAGMENT))
+                .unwrap(),
+            ManifestPlacement::Append {
+                offset: SAMPLE_FRAGMENT.trim_end().len()
+            }
+        );
+
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(SAMPLE_FRAGMENT), &mut output, &store)
+            .unwrap();
+        assert_eq!(html_io.read_cai(&mut output).unwrap(), store);
+
+        let mut removed = Cursor::new(Vec::new());
+        html_io
+            .remove_cai_store_from_stream(&mut output, &mut removed)
+            .unwrap();
+        assert_eq!(removed.into_inner(), SAMPLE_FRAGMENT.as_bytes());
+    }
+
+    #[test]
+    fn test_fragment_object_locations() {
+        let html_io = HtmlIO::default();
+        let store = placeholder_store().unwrap();
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(SAMPLE_FRAGMENT), &mut output, &store)
+            .unwrap();
+        let written = output.get_ref().clone();
+
+        let locations = html_io
+            .get_object_locations_from_stream(&mut output)
+            .unwrap();
+        let cai = locations
+            .iter()
+            .find(|o| o.htype == HashBlockObjectType::Cai)
+            .unwrap();
+        let script_open = format!(r#"<script type="{C2PA_SCRIPT_TYPE}">"#);
+        assert_eq!(
+            cai.offset,
+            SAMPLE_FRAGMENT.trim_end().len() + script_open.len()
+        );
+        assert_eq!(
+            &written[cai.offset..cai.offset + cai.length],
+            STANDARD.encode(&store).as_bytes()
+        );
+
+        // the regions cover the whole fragment without overlapping
+        let mut regions: Vec<_> = locations.iter().map(|o| (o.offset, o.length)).collect();
+        regions.sort();
+        assert_eq!(regions[0].0, 0);
+        for pair in regions.windows(2) {
+            assert_eq!(pair[0].0 + pair[0].1, pair[1].0);
+        }
+        let (last_offset, last_length) = regions[regions.len() - 1];
+        assert_eq!(last_offset + last_length, written.len());
+
+        // a signed fragment validates
+        let signed = sign_sample(SAMPLE_FRAGMENT);
+        html_io.verify_binding(&mut Cursor::new(signed)).unwrap();
+    }
+
+    #[test]
+    fn test_read_manifest_base64() {
+        let html_io = HtmlIO::default();
+        assert_eq!(
+            html_io
+                .read_manifest_base64(&mut Cursor::new(SAMPLE_HTML))
+                .unwrap(),
+            None
+        );
+
+        // the text is returned as stored, even when it would not decode
+        let html = SAMPLE_HTML.replace(
+            "</body>",
+            &format!("<script type=\"{C2PA_SCRIPT_TYPE}\">\n  not*base64  \n</script>\n</body>"),
+        );
+        assert_eq!(
+            html_io
+                .read_manifest_base64(&mut Cursor::new(&html))
+                .unwrap()
+                .as_deref(),
+            Some("not*base64")
+        );
+
+        let store = placeholder_store().unwrap();
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut output, &store)
+            .unwrap();
+        let b64 = html_io.read_manifest_base64(&mut output).unwrap().unwrap();
+        assert_eq!(b64, STANDARD.encode(&store));
+        assert!(String::from_utf8(output.into_inner())
+            .unwrap()
+            .contains(&b64));
+    }
+
+    #[test]
+    fn test_read_xmp_from_meta_tags() {
+        let html = SAMPLE_HTML.replace(
+            "<title>Sample</title>\n",
+            "<title>Sample</title>\n<meta property=\"og:title\" content=\"Breaking &amp; News\">\n<meta name=\"author\" content=\"Jane Doe\">\n<meta name=\"viewport\" content=\"width=device-width\">\n",
+        );
+
+        // opt-in only
+        assert_eq!(HtmlIO::default().read_xmp(&mut Cursor::new(&html)), None);
+
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            synthesize_meta_xmp: true,
+            ..Default::default()
+        });
+        let xmp = html_io.read_xmp(&mut Cursor::new(&html)).unwrap();
+        assert!(xmp.contains("<dc:title>Breaking &amp; News</dc:title>"));
+        assert!(xmp.contains("<dc:creator>Jane Doe</dc:creator>"));
+        assert!(!xmp.contains("viewport"));
+        assert_eq!(html_io.read_xmp(&mut Cursor::new(SAMPLE_HTML)), None);
+
+        // a real packet takes precedence
+        let packet = crate::utils::xmp_inmemory_utils::MIN_XMP;
+        let with_packet = html.replace("</body>", &format!("<!--{packet}-->\n</body>"));
+        assert_eq!(
+            html_io.read_xmp(&mut Cursor::new(&with_packet)).as_deref(),
+            Some(packet)
+        );
+    }
+
+    /// a write sink that can report its position but cannot seek back
+    struct AppendOnly(Cursor<Vec<u8>>);
+
+    impl std::io::Read for AppendOnly {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl std::io::Write for AppendOnly {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl std::io::Seek for AppendOnly {
+        fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+            match pos {
+                std::io::SeekFrom::Current(0) => Ok(self.0.position()),
+                _ => Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "append only",
+                )),
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_to_append_only_sink() {
+        let html_io = HtmlIO::default();
+        let store = placeholder_store().unwrap();
+
+        let mut output = AppendOnly(Cursor::new(Vec::new()));
+        html_io
+            .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut output, &store)
+            .unwrap();
+        let written = output.0.into_inner();
+        assert_eq!(html_io.read_cai(&mut Cursor::new(&written)).unwrap(), store);
+
+        let mut removed = AppendOnly(Cursor::new(Vec::new()));
+        html_io
+            .remove_cai_store_from_stream(&mut Cursor::new(&written), &mut removed)
+            .unwrap();
+        assert!(html_io
+            .read_cai(&mut Cursor::new(removed.0.into_inner()))
+            .is_err());
+
+        // once written to, the sink cannot be rewound
+        let mut used = AppendOnly(Cursor::new(b"used".to_vec()));
+        used.0.set_position(4);
+        assert!(matches!(
+            html_io.write_cai(&mut Cursor::new(SAMPLE_HTML), &mut used, &store),
+            Err(Error::IoError(_))
+        ));
+    }
+
+    #[test]
+    fn test_scan_manifest_block_matches_tokenizer() {
+        let manifest =
+            format!("<script type=\"{C2PA_SCRIPT_TYPE}\" data-note=\"a > b\">QUJD</script>");
+        let documents = [
+            SAMPLE_HTML.to_owned(),
+            SAMPLE_HTML.replace("</body>", &format!("{manifest}\n</body>")),
+            SAMPLE_HTML.replace("<head>", &format!("<head>\n  <!-- c2pa -->{manifest}  ")),
+            SAMPLE_HTML.replace("<head>", &format!("<head><!-- other --> {manifest}")),
+            format!(
+                "<SCRIPT>var s = '<script type=\"{C2PA_SCRIPT_TYPE}\">';</SCRIPT >\n<scripts></scripts>{manifest}"
+            ),
+            format!("<div>{manifest}</div><script type=\"{C2PA_SCRIPT_TYPE}\">REVG</script>"),
+            format!("<p>unterminated</p><script type=\"{C2PA_SCRIPT_TYPE}\">QUJD"),
+        ];
+        for html in documents {
+            let expected = find_manifest_element(&html, ManifestLookup::default())
+                .map(|e| e.block_range(&html, None));
+            assert_eq!(
+                scan_manifest_block(
+                    &mut Cursor::new(&html),
+                    ActiveManifest::default(),
+                    false,
+                    None
+                )
+                .unwrap()
+                .map(|manifest| manifest.block),
+                expected,
+                "{html}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_remove_large_file_is_byte_identical() {
+        let paragraphs: String = (0..50_000)
+            .map(|i| format!("<p>paragraph {i}</p>\n"))
+            .collect();
+        let before = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<title>Large</title>\n</head>\n<body>\n{paragraphs}"
+        );
+        let after = format!("{paragraphs}</body>\n</html>\n");
+        let html = format!(
+            "{before}<script type=\"{C2PA_SCRIPT_TYPE}\">{}</script>\n{after}",
+            STANDARD.encode(placeholder_store().unwrap())
+        );
+
+        let html_io = HtmlIO::default();
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .remove_cai_store_from_stream(&mut Cursor::new(&html), &mut output)
+            .unwrap();
+        let removed = output.into_inner();
+        assert_eq!(removed, format!("{before}{after}").into_bytes());
+        assert!(matches!(
+            html_io.read_cai(&mut Cursor::new(removed)),
+            Err(Error::JumbfNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_markup_mode_from_doctype() {
+        assert_eq!(MarkupMode::detect(SAMPLE_HTML), MarkupMode::Html);
+        assert_eq!(
+            MarkupMode::detect("<!doctype HTML><p>x</p>"),
+            MarkupMode::Html
+        );
+        assert_eq!(
+            MarkupMode::detect(
+                r#"<!DOCTYPE HTML PUBLIC "-//W3C//DTD HTML 4.01//EN" "http://www.w3.org/TR/html4/strict.dtd"><html></html>"#
+            ),
+            MarkupMode::Html
+        );
+        assert_eq!(MarkupMode::detect(SAMPLE_XHTML), MarkupMode::Xhtml);
+        assert_eq!(MarkupMode::detect(SAMPLE_FRAGMENT), MarkupMode::Html);
+    }
+
+    #[test]
+    fn test_html5_doctype_writes_plain_manifest() {
+        let html_io = HtmlIO::default();
+        let store = placeholder_store().unwrap();
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut output, &store)
+            .unwrap();
+        let written = String::from_utf8(output.into_inner()).unwrap();
+        assert!(!written.contains(CDATA_OPEN));
+        assert!(written.contains(&format!(">{}</script>", STANDARD.encode(&store))));
+    }
+
+    #[test]
+    fn test_xhtml_strict_doctype_writes_cdata_manifest() {
+        let xhtml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html PUBLIC \"-//W3C//DTD XHTML 1.0 Strict//EN\" \"http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd\">\n<html xmlns=\"http://www.w3.org/1999/xhtml\">\n<head>\n<title>Sample</title>\n</head>\n<body>\n<p>Hello</p>\n</body>\n</html>\n";
+        let without_declaration = xhtml.split_once('\n').unwrap().1;
+        assert_eq!(MarkupMode::detect(without_declaration), MarkupMode::Xhtml);
+
+        let html_io = HtmlIO::default();
+        let store = placeholder_store().unwrap();
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(without_declaration), &mut output, &store)
+            .unwrap();
+        let written = String::from_utf8(output.get_ref().clone()).unwrap();
+        let b64 = STANDARD.encode(&store);
+        assert!(written.contains(&format!("{CDATA_OPEN}{b64}{CDATA_CLOSE}")));
+        assert_eq!(html_io.read_cai(&mut output).unwrap(), store);
+        assert_eq!(
+            html_io.read_manifest_base64(&mut output).unwrap().unwrap(),
+            b64
+        );
+
+        // the excluded region is the base64 text inside the CDATA section
+        let cai = html_io
+            .get_object_locations_from_stream(&mut output)
+            .unwrap()
+            .into_iter()
+            .find(|o| o.htype == HashBlockObjectType::Cai)
+            .unwrap();
+        assert_eq!(&written[cai.offset..cai.offset + cai.length], b64);
+
+        // a configured mode overrides the detection
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            markup_mode: Some(MarkupMode::Html),
+            ..Default::default()
+        });
+        assert_eq!(
+            html_io.markup_mode(&mut Cursor::new(xhtml)).unwrap(),
+            MarkupMode::Html
+        );
+
+        let signed = sign_sample(xhtml);
+        assert!(String::from_utf8_lossy(&signed).contains(CDATA_OPEN));
+        HtmlIO::default()
+            .verify_binding(&mut Cursor::new(signed))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_binding_report() {
+        let html_io = HtmlIO::default();
+        let signed = sign_sample(SAMPLE_HTML);
+        let report = html_io.binding_report(&mut Cursor::new(&signed)).unwrap();
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({"binding_ok": true})
+        );
+
+        // content inserted in front of the manifest moves it, so it can be located
+        let signed = String::from_utf8(signed).unwrap();
+        let tampered = signed.replacen("<p>", "<p><b>", 1);
+        let report = html_io
+            .binding_report(&mut Cursor::new(tampered.as_bytes()))
+            .unwrap();
+        assert!(!report.binding_ok);
+        assert!(report.first_diff.is_some());
+
+        assert!(html_io
+            .binding_report(&mut Cursor::new(SAMPLE_HTML))
+            .is_err());
+    }
+
+    /// a reader that spuriously returns `Ok(0)` before every chunk of data
+    struct StutteringReader {
+        inner: Cursor<Vec<u8>>,
+        stutter: bool,
+    }
+
+    impl std::io::Read for StutteringReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.stutter = !self.stutter;
+            if self.stutter {
+                return Ok(0);
+            }
+            let len = buf.len().min(16);
+            self.inner.read(&mut buf[..len])
+        }
+    }
+
+    impl std::io::Seek for StutteringReader {
+        fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_read_from_stuttering_stream() {
+        let html_io = HtmlIO::default();
+        let store = placeholder_store().unwrap();
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut output, &store)
+            .unwrap();
+        let written = output.into_inner();
+
+        let mut reader = StutteringReader {
+            inner: Cursor::new(written.clone()),
+            stutter: false,
+        };
+        assert_eq!(html_io.read_cai(&mut reader).unwrap(), store);
+        assert_eq!(
+            read_html(&mut reader, &HtmlIoConfig::default())
+                .unwrap()
+                .as_bytes(),
+            written
+        );
+        let locations = html_io
+            .get_object_locations_from_stream(&mut reader)
+            .unwrap();
+        assert_eq!(
+            locations.iter().map(|o| o.length).sum::<usize>(),
+            written.len()
+        );
+    }
+
+    #[test]
+    fn test_relocate_manifest_head_to_body() {
+        let store = placeholder_store().unwrap();
+        let manifest_block = format!(
+            "<!-- c2pa --><script type=\"{C2PA_SCRIPT_TYPE}\" id=\"m-1\">\n{}\n</script>",
+            STANDARD.encode(&store)
+        );
+        let html = SAMPLE_HTML.replace("<head>", &format!("<head>{manifest_block}"));
+
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            manifest_comment: Some("c2pa".into()),
+            ..Default::default()
+        });
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .relocate_manifest(&mut Cursor::new(&html), &mut output, PlacementTarget::Body)
+            .unwrap();
+        let relocated = String::from_utf8(output.get_ref().clone()).unwrap();
+
+        // the old block is cut out like when removing a manifest, the bytes around it stay
+        assert_eq!(
+            relocated,
+            SAMPLE_HTML.replace("\n</body>", &format!("{manifest_block}\n</body>"))
+        );
+        let element = find_manifest_element(&relocated, ManifestLookup::default()).unwrap();
+        assert_eq!(
+            &relocated[element.comment_start(&relocated, Some("c2pa")).unwrap()..element.end],
+            manifest_block
+        );
+        assert_eq!(html_io.read_cai(&mut output).unwrap(), store);
+
+        // a target the document lacks
+        let fragment = format!("<div>x</div>{manifest_block}");
+        assert!(matches!(
+            html_io.relocate_manifest(
+                &mut Cursor::new(fragment),
+                &mut Cursor::new(Vec::new()),
+                PlacementTarget::Head
+            ),
+            Err(Error::BadParam(_))
+        ));
+        assert!(matches!(
+            html_io.relocate_manifest(
+                &mut Cursor::new(SAMPLE_HTML),
+                &mut Cursor::new(Vec::new()),
+                PlacementTarget::Head
+            ),
+            Err(Error::JumbfNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_max_decoded_bytes() {
+        let store = placeholder_store().unwrap();
+        let mut output = Cursor::new(Vec::new());
+        HtmlIO::default()
+            .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut output, &store)
+            .unwrap();
+
+        let capped = |limit| {
+            HtmlIO::with_config(HtmlIoConfig {
+                max_decoded_bytes: Some(limit),
+                ..Default::default()
+            })
+        };
+        assert_eq!(capped(store.len()).read_cai(&mut output).unwrap(), store);
+        match capped(store.len() - 1).read_cai(&mut output) {
+            Err(Error::ManifestTooLarge { size, limit }) => {
+                assert_eq!((size, limit), (store.len(), store.len() - 1))
+            }
+            other => panic!("unexpected result {other:?}"),
+        }
+        assert!(matches!(
+            capped(1).read_manifests(&mut output),
+            Err(Error::ManifestTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn test_xhtml_content_type_selects_xhtml_mode() {
+        // the document itself does not look like XHTML
+        assert_eq!(MarkupMode::detect(SAMPLE_HTML), MarkupMode::Html);
+
+        let signed = sign_as(SAMPLE_HTML, "application/xhtml+xml");
+        assert!(String::from_utf8_lossy(&signed).contains(CDATA_OPEN));
+        let signed_html = sign_as(SAMPLE_HTML, "text/html");
+        assert!(!String::from_utf8_lossy(&signed_html).contains(CDATA_OPEN));
+
+        let reader =
+            crate::Reader::from_stream("application/xhtml+xml", Cursor::new(signed)).unwrap();
+        assert!(reader.active_manifest().is_some());
+        assert_eq!(reader.validation_status(), None);
+
+        assert_eq!(
+            HtmlIO::new("xht")
+                .markup_mode(&mut Cursor::new(SAMPLE_HTML))
+                .unwrap(),
+            MarkupMode::Xhtml
+        );
+        assert_eq!(
+            HtmlIO::new("text/html")
+                .markup_mode(&mut Cursor::new(SAMPLE_XHTML))
+                .unwrap(),
+            MarkupMode::Xhtml
+        );
+    }
+
+    /// a source that panics if anything tries to write to it
+    struct NoWrite(Cursor<Vec<u8>>);
+
+    impl std::io::Read for NoWrite {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl std::io::Seek for NoWrite {
+        fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+            self.0.seek(pos)
+        }
+    }
+
+    impl std::io::Write for NoWrite {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            panic!("write attempted on a read only source");
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            panic!("flush attempted on a read only source");
+        }
+    }
+
+    #[test]
+    fn test_read_paths_never_write_to_the_source() {
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            synthesize_meta_xmp: true,
+            ..Default::default()
+        });
+        let store = placeholder_store().unwrap();
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut output, &store)
+            .unwrap();
+        let written = output.into_inner();
+
+        let mut source = NoWrite(Cursor::new(written.clone()));
+        assert_eq!(html_io.read_cai(&mut source).unwrap(), store);
+        assert_eq!(html_io.read_xmp(&mut source), None);
+        assert!(html_io.has_manifest(&mut source).unwrap());
+        html_io
+            .get_object_locations_from_stream(&mut source)
+            .unwrap();
+        assert_eq!(source.0.into_inner(), written);
+
+        let mut empty = NoWrite(Cursor::new(SAMPLE_HTML.as_bytes().to_vec()));
+        assert!(!html_io.has_manifest(&mut empty).unwrap());
+        assert!(html_io.read_cai(&mut empty).is_err());
+    }
+
+    #[test]
+    fn test_extract_to_sidecar_then_remove() {
+        let html_io = HtmlIO::default();
+        let signed = sign_sample(SAMPLE_HTML);
+
+        let sidecar = html_io
+            .extract_to_sidecar(&mut Cursor::new(&signed))
+            .unwrap()
+            .unwrap();
+        assert!(is_jumbf(&sidecar));
+        Store::from_jumbf(&sidecar, &mut StatusTracker::default()).unwrap();
+
+        let mut removed = Cursor::new(Vec::new());
+        html_io
+            .remove_cai_store_from_stream(&mut Cursor::new(&signed), &mut removed)
+            .unwrap();
+        assert_eq!(html_io.extract_to_sidecar(&mut removed).unwrap(), None);
+        assert_eq!(removed.into_inner(), SAMPLE_HTML.as_bytes());
+    }
+
+    #[test]
+    fn test_manifest_placement_duplicate_heads() {
+        let html = "<html>\n<head>\n<title>First</title>\n</head>\n<head>\n<title>Second</title>\n</head>\n<p>Hello</p>\n</html>\n";
+        let first_head_end = html.find("<head>").unwrap() + "<head>".len();
+
+        let html_io = HtmlIO::default();
+        assert_eq!(
+            html_io.manifest_placement(&mut Cursor::new(html)).unwrap(),
+            ManifestPlacement::Head {
+                offset: first_head_end
+            }
+        );
+
+        let store = placeholder_store().unwrap();
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(html), &mut output, &store)
+            .unwrap();
+        let written = String::from_utf8(output.into_inner()).unwrap();
+        let element = find_manifest_element(&written, ManifestLookup::default()).unwrap();
+        assert_eq!(element.start, first_head_end);
+        assert!(written[element.end..].starts_with("\n<title>First</title>"));
+    }
+
+    fn hex_html_io() -> HtmlIO {
+        HtmlIO::with_config(HtmlIoConfig {
+            manifest_encoding: ManifestEncoding::Hex,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_hex_manifest_round_trip() {
+        let html_io = hex_html_io();
+        let store = placeholder_store().unwrap();
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut output, &store)
+            .unwrap();
+        let written = String::from_utf8(output.get_ref().clone()).unwrap();
+        assert!(written.contains(&format!(
+            "<script type=\"{C2PA_HEX_SCRIPT_TYPE}\">{}</script>",
+            hex::encode(&store)
+        )));
+
+        // any handler reads it
+        assert_eq!(HtmlIO::default().read_cai(&mut output).unwrap(), store);
+        let cai = html_io
+            .get_object_locations_from_stream(&mut output)
+            .unwrap()
+            .into_iter()
+            .find(|o| o.htype == HashBlockObjectType::Cai)
+            .unwrap();
+        assert_eq!(
+            &written[cai.offset..cai.offset + cai.length],
+            hex::encode(&store)
+        );
+
+        let mut removed = Cursor::new(Vec::new());
+        html_io
+            .remove_cai_store_from_stream(&mut output, &mut removed)
+            .unwrap();
+        assert!(!html_io.has_manifest(&mut removed).unwrap());
+
+        let signed = sign_sample(SAMPLE_HTML);
+        // a signed store can be re-embedded as hex
+        let store = HtmlIO::default()
+            .read_cai(&mut Cursor::new(&signed))
+            .unwrap();
+        let mut rewritten = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(&signed), &mut rewritten, &store)
+            .unwrap();
+        assert_eq!(html_io.read_cai(&mut rewritten).unwrap(), store);
+    }
+
+    #[test]
+    fn test_base64_mode_unaffected_by_hex_support() {
+        let store = placeholder_store().unwrap();
+        let mut output = Cursor::new(Vec::new());
+        HtmlIO::default()
+            .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut output, &store)
+            .unwrap();
+        let written = String::from_utf8(output.into_inner()).unwrap();
+        assert!(written.contains(&format!(
+            "<script type=\"{C2PA_SCRIPT_TYPE}\">{}</script>",
+            STANDARD.encode(&store)
+        )));
+        assert!(!written.contains(C2PA_HEX_SCRIPT_TYPE));
+
+        // a hex manifest script with bad hex is reported as such
+        let html = SAMPLE_HTML.replace(
+            "</body>",
+            &format!("<script type=\"{C2PA_HEX_SCRIPT_TYPE}\">zz</script></body>"),
+        );
+        assert!(matches!(
+            HtmlIO::default().read_cai(&mut Cursor::new(html)),
+            Err(Error::InvalidAsset(_))
+        ));
+    }
+
+    #[test]
+    fn test_pre_write_transform_is_signed() {
+        let upper_tags = |html: &str| {
+            Regex::new(r"</?[a-zA-Z]+")
+                .unwrap()
+                .replace_all(html, |c: &regex::Captures| c[0].to_uppercase())
+                .into_owned()
+        };
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            pre_write_transform: Some(PreWriteTransform::new(upper_tags)),
+            ..Default::default()
+        });
+
+        // the transformed document is what gets hashed
+        let transformed = upper_tags(SAMPLE_HTML);
+        let length: usize = html_io
+            .get_object_locations_from_stream(&mut Cursor::new(SAMPLE_HTML))
+            .unwrap()
+            .iter()
+            .map(|o| o.length)
+            .sum();
+        let mut placeholder = Cursor::new(Vec::new());
+        HtmlIO::default()
+            .write_cai(
+                &mut Cursor::new(&transformed),
+                &mut placeholder,
+                &placeholder_store().unwrap(),
+            )
+            .unwrap();
+        assert_eq!(length, placeholder.get_ref().len());
+
+        // writing a manifest signed over the transformed document into the original one
+        // reproduces the signed document
+        let signed = sign_sample(&transformed);
+        let store = html_io.read_cai(&mut Cursor::new(&signed)).unwrap();
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut output, &store)
+            .unwrap();
+        assert_eq!(output.get_ref(), &signed);
+        assert!(String::from_utf8_lossy(&signed).contains("<BODY>"));
+        html_io.verify_binding(&mut output).unwrap();
+    }
+
+    #[test]
+    fn test_object_locations_tile_append_case() {
+        let html = "<p>no head or body</p>\n\n";
+        let html_io = HtmlIO::default();
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(
+                &mut Cursor::new(html),
+                &mut output,
+                &placeholder_store().unwrap(),
+            )
+            .unwrap();
+        let written = output.get_ref().clone();
+        let html_len = written.len();
+
+        let locations = html_io
+            .get_object_locations_from_stream(&mut output)
+            .unwrap();
+        let region = |htype: HashBlockObjectType, offset: usize| {
+            locations
+                .iter()
+                .find(|o| o.htype == htype && o.offset == offset)
+                .map(|o| o.offset..o.offset + o.length)
+                .unwrap()
+        };
+
+        let cai = locations
+            .iter()
+            .find(|o| o.htype == HashBlockObjectType::Cai)
+            .map(|o| o.offset..o.offset + o.length)
+            .unwrap();
+        let before = region(HashBlockObjectType::Other, 0);
+        let after = region(HashBlockObjectType::Other, cai.end);
+        assert_eq!(locations.len(), 3);
+        assert_eq!(before, 0..cai.start);
+        assert_eq!(after, cai.end..html_len);
+
+        // the trailing region is the closing tag of the appended manifest and the trailing
+        // whitespace of the document
+        assert_eq!(
+            &written[after],
+            format!("</script>{}", &html[html.trim_end().len()..]).as_bytes()
+        );
+        assert_eq!(
+            &written[before],
+            format!("{}<script type=\"{C2PA_SCRIPT_TYPE}\">", html.trim_end()).as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_single_quoted_manifest_input() {
+        let store = placeholder_store().unwrap();
+        let html = SAMPLE_HTML.replace(
+            "</body>",
+            &format!(
+                "<script type='{C2PA_SCRIPT_TYPE}' id='m-1'>{}</script>\n</body>",
+                STANDARD.encode(&store)
+            ),
+        );
+
+        let html_io = HtmlIO::default();
+        assert_eq!(html_io.read_cai(&mut Cursor::new(&html)).unwrap(), store);
+        assert_eq!(
+            html_io
+                .read_manifest_by_id(&mut Cursor::new(&html), "m-1")
+                .unwrap(),
+            store
+        );
+
+        // replacing it keeps a single manifest
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(&html), &mut output, &store)
+            .unwrap();
+        assert_eq!(html_io.read_manifests(&mut output).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_quote_style_round_trip() {
+        let store = placeholder_store().unwrap();
+        for (quote_style, quote) in [(QuoteStyle::Double, '"'), (QuoteStyle::Single, '\'')] {
+            let html_io = HtmlIO::with_config(HtmlIoConfig {
+                quote_style,
+                manifest_id_prefix: Some("c2pa-manifest".to_owned()),
+                ..Default::default()
+            });
+            let mut output = Cursor::new(Vec::new());
+            html_io
+                .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut output, &store)
+                .unwrap();
+            let written = String::from_utf8(output.get_ref().clone()).unwrap();
+            assert!(written.contains(&format!(
+                "<script type={quote}{C2PA_SCRIPT_TYPE}{quote} id={quote}c2pa-manifest-1{quote}>"
+            )));
+            assert_eq!(
+                html_io
+                    .read_manifest_by_id(&mut output, "c2pa-manifest-1")
+                    .unwrap(),
+                store
+            );
+            assert_eq!(
+                html_io
+                    .get_object_locations_from_stream(&mut output)
+                    .unwrap()
+                    .iter()
+                    .map(|o| o.length)
+                    .sum::<usize>(),
+                written.len()
+            );
+        }
+    }
+
+    #[test]
+    fn test_predict_added_size() {
+        let store = placeholder_store().unwrap();
+        let existing = {
+            let mut output = Cursor::new(Vec::new());
+            HtmlIO::default()
+                .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut output, b"old manifest")
+                .unwrap();
+            String::from_utf8(output.into_inner()).unwrap()
+        };
+        let documents = [
+            SAMPLE_HTML.to_owned(),
+            SAMPLE_HTML
+                .replace("</body>", "</BODY>")
+                .replace("<body>", ""),
+            "<html><head><title>x</title></head></html>".to_owned(),
+            "<p>fragment</p>\n\n".to_owned(),
+            existing,
+        ];
+        let handlers = [
+            HtmlIO::default(),
+            commented_html_io(),
+            id_html_io(),
+            hex_html_io(),
+        ];
+
+        for html in &documents {
+            for html_io in &handlers {
+                let predicted = html_io
+                    .predict_added_size(&store, &mut Cursor::new(html))
+                    .unwrap();
+                let mut output = Cursor::new(Vec::new());
+                html_io
+                    .write_cai(&mut Cursor::new(html), &mut output, &store)
+                    .unwrap();
+                let actual = output.into_inner().len() as isize - html.len() as isize;
+                assert_eq!(predicted, actual, "{html}");
+            }
+        }
+    }
+
+    fn utf16le(html: &str) -> Vec<u8> {
+        TextEncoding::Utf16Le.encode(html).into_owned()
+    }
+
+    #[test]
+    fn test_utf16le_round_trip() {
+        let html_io = HtmlIO::default();
+        let source = utf16le(SAMPLE_HTML);
+        assert_eq!(&source[..2], &[0xff, 0xfe]);
+        assert_eq!(
+            read_html(&mut Cursor::new(&source), &HtmlIoConfig::default()).unwrap(),
+            SAMPLE_HTML
+        );
+
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(&source), &mut output, b"utf-16 manifest")
+            .unwrap();
+        let written = output.into_inner();
+        assert_eq!(&written[..2], &[0xff, 0xfe]);
+        let (html, text_encoding) =
+            read_html_document(&mut Cursor::new(&written), &HtmlIoConfig::default()).unwrap();
+        assert_eq!(text_encoding, TextEncoding::Utf16Le);
+        assert!(html.contains(C2PA_SCRIPT_TYPE));
+        assert_eq!(
+            html_io.read_cai(&mut Cursor::new(&written)).unwrap(),
+            b"utf-16 manifest"
+        );
+
+        let mut removed = Cursor::new(Vec::new());
+        html_io
+            .remove_cai_store_from_stream(&mut Cursor::new(&written), &mut removed)
+            .unwrap();
+        let (html, text_encoding) =
+            read_html_document(&mut removed, &HtmlIoConfig::default()).unwrap();
+        assert_eq!(text_encoding, TextEncoding::Utf16Le);
+        assert!(!html.contains(C2PA_SCRIPT_TYPE));
+    }
+
+    #[test]
+    fn test_utf16le_signed_binding() {
+        let source = String::from_utf8(utf16le(SAMPLE_HTML))
+            .unwrap_err()
+            .into_bytes();
+        let mut builder = Builder::from_json(
+            r#"{"claim_generator_info": [{"name": "c2pa_test", "version": "1.0.0"}]}"#,
+        )
+        .unwrap();
+        let signer = test_signer(SigningAlg::Ps256);
+        let mut dest = Cursor::new(Vec::new());
+        builder
+            .sign(
+                signer.as_ref(),
+                "text/html",
+                &mut Cursor::new(source),
+                &mut dest,
+            )
+            .unwrap();
+        let signed = dest.into_inner();
+
+        assert_eq!(&signed[..2], &[0xff, 0xfe]);
+        HtmlIO::default()
+            .verify_binding(&mut Cursor::new(&signed))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_invalid_utf16_rejected() {
+        // a lone high surrogate after the byte order mark
+        let source = [0xff, 0xfe, 0x00, 0xd8];
+        assert!(matches!(
+            read_html(&mut Cursor::new(&source), &HtmlIoConfig::default()),
+            Err(Error::IoError(e)) if e.kind() == std::io::ErrorKind::InvalidData
+        ));
+    }
+
+    #[test]
+    fn test_validate_all_reports_each_manifest() {
+        // a manifest signed for another document stands in for a tampered one
+        let stale = HtmlIO::default()
+            .read_cai(&mut Cursor::new(sign_sample(SAMPLE_HTML)))
+            .unwrap();
+        let placeholder = STANDARD.encode(placeholder_store().unwrap());
+        let html = SAMPLE_HTML
+            .replace(
+                "<head>\n",
+                &format!(
+                    "<head>\n<script type=\"{C2PA_SCRIPT_TYPE}\" data-c2pa-active=\"true\">{placeholder}</script>\n"
+                ),
+            )
+            .replace(
+                "</body>",
+                &format!(
+                    "<script type=\"{C2PA_SCRIPT_TYPE}\" id=\"stale\">{}</script>\n</body>",
+                    STANDARD.encode(&stale)
+                ),
+            );
+
+        // signing replaces the active first manifest, its binding covers the stale one
+        let signed = sign_sample(&html);
+        let reports = HtmlIO::default()
+            .validate_all(&mut Cursor::new(&signed))
+            .unwrap();
+
+        assert_eq!(reports.len(), 2);
+        assert!(reports[0].binding_ok);
+        assert_eq!(reports[0].id, None);
+        assert_eq!(reports[0].first_diff, None);
+        assert!(!reports[1].binding_ok);
+        assert_eq!(reports[1].id.as_deref(), Some("stale"));
+        assert!(reports[1].first_diff.is_some());
+        assert_eq!(reports[0].signer, reports[1].signer);
+        assert!(reports.iter().all(|report| report.error.is_none()));
+    }
+
+    #[test]
+    fn test_validate_all_reports_broken_manifests() {
+        let placeholder = STANDARD.encode(placeholder_store().unwrap());
+        let html = SAMPLE_HTML
+            .replace(
+                "<head>\n",
+                &format!(
+                    "<head>\n<script type=\"{C2PA_SCRIPT_TYPE}\" data-c2pa-active=\"true\">{placeholder}</script>\n"
+                ),
+            )
+            .replace(
+                "</body>",
+                &format!(
+                    "<script type=\"{C2PA_SCRIPT_TYPE}\" id=\"broken\">{}</script>\n<script type=\"{C2PA_SCRIPT_TYPE}\" id=\"empty\"></script>\n</body>",
+                    STANDARD.encode(b"not a jumbf box")
+                ),
+            );
+
+        // one broken manifest does not hide the others from the report
+        let signed = sign_sample(&html);
+        let reports = HtmlIO::default()
+            .validate_all(&mut Cursor::new(&signed))
+            .unwrap();
+
+        assert_eq!(reports.len(), 3);
+        assert!(reports[0].binding_ok);
+        assert_eq!(reports[0].error, None);
+        assert!(!reports[1].binding_ok);
+        assert_eq!(reports[1].id.as_deref(), Some("broken"));
+        assert!(reports[1].error.is_some());
+        assert!(!reports[2].binding_ok);
+        assert_eq!(reports[2].id.as_deref(), Some("empty"));
+        assert!(reports[2].error.is_some());
+    }
+
+    #[test]
+    fn test_fill_reserved_manifest_in_place() {
+        // the reserve pass keeps its layout, including the comment and id
+        let reserved = {
+            let html_io = HtmlIO::with_config(HtmlIoConfig {
+                manifest_comment: Some("C2PA manifest".into()),
+                manifest_id_prefix: Some("c2pa".into()),
+                ..Default::default()
+            });
+            let mut output = Cursor::new(Vec::new());
+            html_io
+                .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut output, &[0u8; 200])
+                .unwrap();
+            output.into_inner()
+        };
+        let start = HtmlIO::default()
+            .manifest_placement(&mut Cursor::new(&reserved))
+            .unwrap()
+            .offset();
+        let end = start + STANDARD.encode([0u8; 200]).len();
+
+        let html_io = HtmlIO::default();
+        let filled_store = [0xabu8; 200];
+        assert_eq!(
+            html_io
+                .predict_added_size(&filled_store, &mut Cursor::new(&reserved))
+                .unwrap(),
+            0
+        );
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(&reserved), &mut output, &filled_store)
+            .unwrap();
+        let filled = output.into_inner();
+
+        assert_eq!(filled.len(), reserved.len());
+        assert_eq!(filled[..start], reserved[..start]);
+        assert_eq!(filled[end..], reserved[end..]);
+        assert_eq!(
+            html_io.read_cai(&mut Cursor::new(&filled)).unwrap(),
+            filled_store
+        );
+
+        // a manifest of another size replaces the whole block, with the comment it was
+        // configured with
+        let mut output = Cursor::new(Vec::new());
+        HtmlIO::with_config(HtmlIoConfig {
+            manifest_comment: Some("C2PA manifest".into()),
+            ..Default::default()
+        })
+        .write_cai(&mut Cursor::new(&reserved), &mut output, &[0xab; 100])
+        .unwrap();
+        let replaced = String::from_utf8(output.into_inner()).unwrap();
+        assert_eq!(replaced.matches("C2PA manifest").count(), 1);
+        assert!(!replaced.contains("id=\"c2pa-"));
+    }
+
+    fn json_html_io() -> HtmlIO {
+        HtmlIO::with_config(HtmlIoConfig {
+            json_bootstrap: Some(JsonBootstrap::new("__C2PA__", "manifest")),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_json_bootstrap_round_trip() {
+        let html = SAMPLE_HTML.replace(
+            "</head>",
+            "<script type=\"application/json\" id=\"__C2PA__\">{\n  \"user\": \"a<\\/b\",\n  \"manifest\": \"\"\n}</script>\n</head>",
+        );
+        let html_io = json_html_io();
+
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(&html), &mut output, b"json manifest")
+            .unwrap();
+        let written = String::from_utf8(output.into_inner()).unwrap();
+
+        // only the field value changed
+        let manifest_b64 = STANDARD.encode(b"json manifest");
+        assert_eq!(
+            written,
+            html.replace(
+                "\"manifest\": \"\"",
+                &format!("\"manifest\": \"{manifest_b64}\"")
+            )
+        );
+        assert_eq!(
+            html_io.read_cai(&mut Cursor::new(&written)).unwrap(),
+            b"json manifest"
+        );
+        assert!(HtmlIO::default()
+            .read_cai(&mut Cursor::new(&written))
+            .is_err());
+
+        // the hashed regions exclude the field value
+        let store = placeholder_store().unwrap();
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(&written), &mut output, &store)
+            .unwrap();
+        let written = output.into_inner();
+        let cai = html_io
+            .get_object_locations_from_stream(&mut Cursor::new(&written))
+            .unwrap()
+            .into_iter()
+            .find(|o| o.htype == HashBlockObjectType::Cai)
+            .unwrap();
+        assert_eq!(
+            &written[cai.offset..cai.offset + cai.length],
+            STANDARD.encode(&store).as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_json_bootstrap_added_when_missing() {
+        let html_io = json_html_io();
+
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut output, b"new")
+            .unwrap();
+        let written = String::from_utf8(output.into_inner()).unwrap();
+        assert!(written.contains(&format!(
+            "<script type=\"application/json\" id=\"__C2PA__\">{{\"manifest\":\"{}\"}}</script>",
+            STANDARD.encode(b"new")
+        )));
+
+        // a script without the field gets it added
+        let html = SAMPLE_HTML.replace(
+            "</head>",
+            "<script type=\"application/json\" id=\"__C2PA__\">{\"state\":\"</p>\"}</script></head>",
+        );
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(&html), &mut output, b"added")
+            .unwrap();
+        let written = String::from_utf8(output.into_inner()).unwrap();
+        assert!(written.contains(&format!(
+            "{{\"state\":\"<\\/p>\",\"manifest\":\"{}\"}}",
+            STANDARD.encode(b"added")
+        )));
+        assert_eq!(
+            html_io.read_cai(&mut Cursor::new(&written)).unwrap(),
+            b"added"
+        );
+    }
+
+    /// a stream that counts the bytes read from it
+    struct CountingReader<R> {
+        inner: R,
+        read: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl<R: Read> Read for CountingReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let read = self.inner.read(buf)?;
+            self.read
+                .fetch_add(read, std::sync::atomic::Ordering::Relaxed);
+            Ok(read)
+        }
+    }
+
+    impl<R: std::io::Seek> std::io::Seek for CountingReader<R> {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            // report an empty stream so only the limit while reading can stop it
+            match pos {
+                SeekFrom::End(_) => Ok(0),
+                _ => self.inner.seek(pos),
+            }
+        }
+    }
+
+    #[test]
+    fn test_max_document_bytes() {
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            max_document_bytes: Some(16 * 1024),
+            ..Default::default()
+        });
+        let oversized = format!("<html><body>{}</body></html>", "x".repeat(1024 * 1024));
+
+        // the reported length is rejected without reading
+        let result = html_io.read_cai(&mut Cursor::new(&oversized));
+        assert!(matches!(result, Err(Error::InvalidAsset(msg)) if msg == "document too large"));
+
+        // a stream hiding its length is stopped while reading
+        let read = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut stream = CountingReader {
+            inner: Cursor::new(oversized.into_bytes()),
+            read: read.clone(),
+        };
+        assert!(matches!(
+            html_io.has_manifest(&mut stream),
+            Err(Error::InvalidAsset(msg)) if msg == "document too large"
+        ));
+        assert!(read.load(std::sync::atomic::Ordering::Relaxed) <= 16 * 1024 + 8192);
+
+        // documents within the limit are read as usual
+        assert!(!html_io.has_manifest(&mut Cursor::new(SAMPLE_HTML)).unwrap());
+    }
+
+    #[test]
+    fn test_would_break_binding() {
+        let signed = sign_sample(SAMPLE_HTML);
+        let html_io = HtmlIO::default();
+        let start = html_io
+            .manifest_placement(&mut Cursor::new(&signed))
+            .unwrap()
+            .offset();
+        let check = |range: Range<usize>, new_bytes: &[u8]| {
+            html_io
+                .would_break_binding(&mut Cursor::new(&signed), range, new_bytes)
+                .unwrap()
+        };
+
+        // inside the manifest content with the same length
+        assert!(!check(start + 4..start + 8, b"AAAA"));
+        assert!(!check(10..10, b""));
+        // inside the manifest content but changing its length
+        assert!(check(start + 4..start + 8, b"AA"));
+        assert!(check(start..start, b"A"));
+        // hashed bytes before and after the manifest, or across its boundary
+        assert!(check(0..1, b"<"));
+        assert!(check(signed.len() - 1..signed.len(), b"\n"));
+        assert!(check(start - 1..start + 1, b"xy"));
+
+        // an edit of the hashed bytes is caught by the actual binding check
+        let mut edited = signed.clone();
+        edited[1] = b'?';
+        assert!(matches!(
+            html_io.verify_binding(&mut Cursor::new(&edited)),
+            Err(Error::BindingMismatch { .. })
+        ));
+
+        assert!(matches!(
+            html_io.would_break_binding(&mut Cursor::new(SAMPLE_HTML), 0..1, b"x"),
+            Err(Error::JumbfNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_read_cai_store_scans_large_file() {
+        let temp_dir = tempdirectory().unwrap();
+        let path = temp_dir_path(&temp_dir, "large.html");
+        let filler = "<p>filler paragraph</p>\n".repeat(200_000);
+        let html = SAMPLE_HTML.replace("<body>\n", &format!("<body>\n{filler}"));
+        std::fs::write(&path, &html).unwrap();
+
+        let store = placeholder_store().unwrap();
+        let html_io = HtmlIO::default();
+        html_io.save_cai_store(&path, &store).unwrap();
+        assert_eq!(html_io.read_cai_store(&path).unwrap(), store);
+
+        // the result agrees with reading the buffered document
+        let buffered = html_io.read_cai(&mut File::open(&path).unwrap()).unwrap();
+        assert_eq!(buffered, store);
+
+        // documents the scan does not handle are read buffered
+        std::fs::write(&path, utf16le(&std::fs::read_to_string(&path).unwrap())).unwrap();
+        assert_eq!(html_io.read_cai_store(&path).unwrap(), store);
+
+        std::fs::write(&path, SAMPLE_HTML).unwrap();
+        assert!(matches!(
+            html_io.read_cai_store(&path),
+            Err(Error::JumbfNotFound)
+        ));
+    }
+
+    #[cfg(all(feature = "mmap", not(target_arch = "wasm32")))]
+    #[test]
+    fn test_read_cai_store_memory_mapped() {
+        let temp_dir = tempdirectory().unwrap();
+        let path = temp_dir_path(&temp_dir, "large.html");
+        let filler = "<p>filler paragraph</p>\n".repeat(200_000);
+        let html = SAMPLE_HTML.replace("<body>\n", &format!("<body>\n{filler}"));
+        std::fs::write(&path, &html).unwrap();
+
+        let store = placeholder_store().unwrap();
+        let html_io = HtmlIO::default();
+        html_io.save_cai_store(&path, &store).unwrap();
+
+        // the mapped file is scanned in place and agrees with the buffered read
+        let map = unsafe { memmap2::Mmap::map(&File::open(&path).unwrap()).unwrap() };
+        assert_eq!(html_io.read_mapped_cai(&map).unwrap(), store);
+        assert_eq!(html_io.read_cai_store(&path).unwrap(), store);
+        let buffered = html_io.read_cai(&mut File::open(&path).unwrap()).unwrap();
+        assert_eq!(buffered, store);
+    }
+
+    #[test]
+    fn test_verify_and_extract_matches_separate_calls() {
+        let signed = sign_sample(SAMPLE_HTML);
+        let html_io = HtmlIO::default();
+
+        let (reader, store_bytes) = html_io
+            .verify_and_extract(&mut Cursor::new(&signed))
+            .unwrap();
+
+        let expected_reader = Reader::from_stream("text/html", Cursor::new(&signed)).unwrap();
+        let expected_store = html_io.read_cai(&mut Cursor::new(&signed)).unwrap();
+        assert_eq!(store_bytes, expected_store);
+        assert_eq!(
+            reader.validation_state(),
+            expected_reader.validation_state()
+        );
+        assert_eq!(
+            reader.validation_results(),
+            expected_reader.validation_results()
+        );
+        assert_eq!(reader.json(), expected_reader.json());
+
+        assert!(matches!(
+            html_io.verify_and_extract(&mut Cursor::new(SAMPLE_HTML)),
+            Err(Error::JumbfNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_manifest_type_attribute_in_any_position() {
+        let manifest_b64 = STANDARD.encode(b"attribute order");
+        let open_tags = [
+            format!("<script type=\"{C2PA_SCRIPT_TYPE}\" defer>"),
+            format!("<script defer type=\"{C2PA_SCRIPT_TYPE}\" data-x=\"1\">"),
+            format!("<script defer data-x=\"1\" type=\"{C2PA_SCRIPT_TYPE}\">"),
+            // no space after the quoted value, as left by hand edits
+            format!("<script type=\"{C2PA_SCRIPT_TYPE}\"defer>"),
+            format!("<script data-x='a>b'type='{C2PA_SCRIPT_TYPE}'>"),
+            format!("<script\ndefer\n  TYPE = \"{C2PA_SCRIPT_TYPE}\"\n>"),
+            format!("<script defer type={C2PA_SCRIPT_TYPE}>"),
+        ];
+
+        let html_io = HtmlIO::default();
+        for open_tag in open_tags {
+            let html = SAMPLE_HTML.replace(
+                "</body>",
+                &format!("{open_tag}{manifest_b64}</script>\n</body>"),
+            );
+            assert_eq!(
+                html_io.read_cai(&mut Cursor::new(&html)).unwrap(),
+                b"attribute order",
+                "{open_tag}"
+            );
+            let scanned = scan_manifest_block(
+                &mut Cursor::new(&html),
+                ActiveManifest::default(),
+                false,
+                None,
+            )
+            .unwrap();
+            assert_eq!(
+                scanned.map(|manifest| manifest.block),
+                find_manifest_element(&html, ManifestLookup::default())
+                    .map(|e| e.block_range(&html, None)),
+                "{open_tag}"
+            );
+
+            // writing replaces the manifest instead of adding a second one
+            let mut output = Cursor::new(Vec::new());
+            html_io
+                .write_cai(&mut Cursor::new(&html), &mut output, b"replaced")
+                .unwrap();
+            let written = String::from_utf8(output.into_inner()).unwrap();
+            assert!(!written.contains(&manifest_b64), "{open_tag}");
+            assert_eq!(
+                html_io
+                    .read_manifests(&mut Cursor::new(&written))
+                    .unwrap()
+                    .len(),
+                1
+            );
+        }
+    }
+
+    #[test]
+    fn test_extract_page_metadata() {
+        let html = r#"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <script>document.write("<title>not the title</title>");</script>
+  <TITLE>
+    Provenance &amp; Trust
+      Explained
+  </TITLE>
+  <meta property="og:description" content="Open Graph description">
+  <meta name="Description" content=" How &quot;content credentials&quot; work ">
+  <link rel="stylesheet" href="/style.css">
+  <link rel="canonical" href="https://example.com/articles/provenance?a=1&amp;b=2">
+</head>
+<body><svg><title>icon</title></svg></body>
+</html>"#;
+
+        let meta = HtmlIO::default()
+            .extract_page_metadata(&mut Cursor::new(html))
+            .unwrap();
+        assert_eq!(
+            meta,
+            PageMeta {
+                title: Some("Provenance & Trust Explained".into()),
+                description: Some("How \"content credentials\" work".into()),
+                canonical_url: Some("https://example.com/articles/provenance?a=1&b=2".into()),
+            }
+        );
+
+        // og:description is the fallback, missing elements are None
+        let html = r#"<head><meta property="og:description" content="only og"></head>"#;
+        let meta = HtmlIO::default()
+            .extract_page_metadata(&mut Cursor::new(html))
+            .unwrap();
+        assert_eq!(
+            meta,
+            PageMeta {
+                description: Some("only og".into()),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_manifest_inside_template() {
+        let template = format!(
+            "<template id=\"c2pa\"><script type=\"{C2PA_SCRIPT_TYPE}\">{}</script></template>",
+            STANDARD.encode(b"reserved")
+        );
+        let html = SAMPLE_HTML.replace("</body>", &format!("{template}\n</body>"));
+
+        // by default the inert copy is not the manifest of the document
+        let html_io = HtmlIO::default();
+        assert!(matches!(
+            html_io.read_cai(&mut Cursor::new(&html)),
+            Err(Error::JumbfNotFound)
+        ));
+        assert!(scan_manifest_block(
+            &mut Cursor::new(&html),
+            ActiveManifest::default(),
+            false,
+            None
+        )
+        .unwrap()
+        .is_none());
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(&html), &mut output, b"document manifest")
+            .unwrap();
+        let written = String::from_utf8(output.into_inner()).unwrap();
+        assert!(written.contains(&template));
+        assert_eq!(
+            html_io.read_cai(&mut Cursor::new(&written)).unwrap(),
+            b"document manifest"
+        );
+        let mut removed = Cursor::new(Vec::new());
+        html_io
+            .remove_cai_store_from_stream(&mut Cursor::new(&written), &mut removed)
+            .unwrap();
+        assert_eq!(String::from_utf8(removed.into_inner()).unwrap(), html);
+
+        // opted in, the manifest is read from and replaced within the template
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            read_from_template: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            html_io.read_cai(&mut Cursor::new(&html)).unwrap(),
+            b"reserved"
+        );
+        assert!(scan_manifest_block(
+            &mut Cursor::new(&html),
+            ActiveManifest::default(),
+            true,
+            None
+        )
+        .unwrap()
+        .is_some());
+
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(&html), &mut output, b"template manifest")
+            .unwrap();
+        let written = String::from_utf8(output.into_inner()).unwrap();
+        assert!(written.contains(&format!(
+            "<template id=\"c2pa\"><script type=\"{C2PA_SCRIPT_TYPE}\">{}</script></template>",
+            STANDARD.encode(b"template manifest")
+        )));
+        assert_eq!(
+            html_io.read_cai(&mut Cursor::new(&written)).unwrap(),
+            b"template manifest"
+        );
+    }
+
+    fn indenting_html_io() -> HtmlIO {
+        HtmlIO::with_config(HtmlIoConfig {
+            match_indentation: true,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_match_indentation() {
+        let html = "<!DOCTYPE html>\n<html>\n  <head>\n    <title>Sample</title>\n  </head>\n  <body>\n    <div>\n      <p>Hello</p>\n    </div>\n  </body>\n</html>\n";
+        let html_io = indenting_html_io();
+        let store = placeholder_store().unwrap();
+        let manifest_script = format!(
+            "<script type=\"{C2PA_SCRIPT_TYPE}\">{}</script>",
+            STANDARD.encode(&store)
+        );
+
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(html), &mut output, &store)
+            .unwrap();
+        let written = String::from_utf8(output.into_inner()).unwrap();
+        assert_eq!(
+            written,
+            html.replace(
+                "    </div>\n  </body>",
+                &format!("    </div>\n    {manifest_script}\n  </body>")
+            )
+        );
+
+        // rewriting keeps the layout
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(
+                &mut Cursor::new(&written),
+                &mut output,
+                b"longer manifest store",
+            )
+            .unwrap();
+        let rewritten = String::from_utf8(output.into_inner()).unwrap();
+        assert!(rewritten.contains(&format!(
+            "    </div>\n    <script type=\"{C2PA_SCRIPT_TYPE}\">{}</script>\n  </body>",
+            STANDARD.encode(b"longer manifest store")
+        )));
+
+        // tabs and CRLF line breaks, inserted into the head
+        let html = "<html>\r\n\t<head>\r\n\t\t<title>Tabs</title>\r\n\t</head>\r\n</html>\r\n";
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(html), &mut output, &store)
+            .unwrap();
+        let written = String::from_utf8(output.into_inner()).unwrap();
+        assert_eq!(
+            written,
+            html.replace("\t<head>", &format!("\t<head>\r\n\t\t{manifest_script}"))
+        );
+
+        for html in [SAMPLE_HTML, "<p>fragment</p>\n"] {
+            let predicted = html_io
+                .predict_added_size(&store, &mut Cursor::new(html))
+                .unwrap();
+            let mut output = Cursor::new(Vec::new());
+            html_io
+                .write_cai(&mut Cursor::new(html), &mut output, &store)
+                .unwrap();
+            assert_eq!(
+                predicted,
+                output.into_inner().len() as isize - html.len() as isize
+            );
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_async_read_write() {
+        let html_io = HtmlIO::default();
+        let store = placeholder_store().unwrap();
+
+        let mut reader = SAMPLE_HTML.as_bytes();
+        let mut written = Vec::new();
+        html_io
+            .write_cai_async(&mut reader, &mut written, &store)
+            .await
+            .unwrap();
+        assert_eq!(html_io.read_cai(&mut Cursor::new(&written)).unwrap(), store);
+
+        // through files
+        let temp_dir = tempdirectory().unwrap();
+        let path = temp_dir_path(&temp_dir, "async.html");
+        tokio::fs::write(&path, &written).await.unwrap();
+        let mut file = tokio::fs::File::open(&path).await.unwrap();
+        assert_eq!(html_io.read_cai_async(&mut file).await.unwrap(), store);
+
+        let limited = HtmlIO::with_config(HtmlIoConfig {
+            max_document_bytes: Some(written.len() - 1),
+            ..Default::default()
+        });
+        assert!(matches!(
+            limited.read_cai_async(&mut written.as_slice()).await,
+            Err(Error::InvalidAsset(_))
+        ));
+    }
+
+    #[test]
+    fn test_json_bootstrap_with_script_end_in_string() {
+        let store = placeholder_store().unwrap();
+        let manifest_b64 = STANDARD.encode(&store);
+        let html = SAMPLE_HTML.replace(
+            "</head>",
+            &format!(
+                "<script type=\"application/json\" id=\"__C2PA__\">{{\"note\":\"</script> \\\"</script>\\\"\",\"manifest\":\"{manifest_b64}\"}}</script></head>"
+            ),
+        );
+        let html_io = json_html_io();
+        assert_eq!(html_io.read_cai(&mut Cursor::new(&html)).unwrap(), store);
+
+        // the whole script stays in the hashed regions, except the field value
+        let positions = html_io
+            .get_object_locations_from_stream(&mut Cursor::new(&html))
+            .unwrap();
+        let cai = positions
+            .iter()
+            .find(|p| p.htype == HashBlockObjectType::Cai)
+            .unwrap();
+        assert_eq!(
+            &html[cai.offset..cai.offset + cai.length],
+            manifest_b64.as_str()
+        );
+
+        // scripts of other types end at the first end tag
+        let other = html.replace("application/json", "text/plain");
+        assert_eq!(
+            script_elements(&other)
+                .iter()
+                .find(|e| e.attribute("id") == Some("__C2PA__"))
+                .map(|e| &other[e.content_start..e.content_end]),
+            Some("{\"note\":\"")
+        );
+    }
+
+    #[test]
+    fn test_validate_regions() {
+        let region = |offset, length, htype| HashObjectPositions {
+            offset,
+            length,
+            htype,
+        };
+        let cai = HashBlockObjectType::Cai;
+        let other = HashBlockObjectType::Other;
+
+        let store = placeholder_store().unwrap();
+        let mut output = Cursor::new(Vec::new());
+        HtmlIO::default()
+            .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut output, &store)
+            .unwrap();
+        let written = output.into_inner();
+        let positions = HtmlIO::default()
+            .get_object_locations_from_stream(&mut Cursor::new(&written))
+            .unwrap();
+        assert!(validate_regions(&positions, written.len()).is_ok());
+
+        // the order does not matter and empty regions are allowed
+        assert!(validate_regions(
+            &[
+                region(0, 0, other),
+                region(10, 90, other),
+                region(0, 10, cai)
+            ],
+            100
+        )
+        .is_ok());
+
+        for broken in [
+            vec![region(0, 10, other), region(10, 90, other)], // no manifest region
+            vec![region(0, 0, cai), region(0, 100, other)],    // empty manifest region
+            vec![
+                region(0, 10, other),
+                region(12, 10, cai),
+                region(22, 78, other),
+            ], // gap
+            vec![
+                region(0, 10, other),
+                region(8, 10, cai),
+                region(18, 82, other),
+            ], // overlap
+            vec![
+                region(0, 10, other),
+                region(10, 10, cai),
+                region(20, 70, other),
+            ], // short
+            vec![
+                region(0, 10, other),
+                region(10, 10, cai),
+                region(20, 90, other),
+            ], // long
+        ] {
+            assert!(
+                matches!(validate_regions(&broken, 100), Err(Error::InvalidAsset(_))),
+                "{broken:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_manifest_from_object_data_uri() {
+        let store = placeholder_store().unwrap();
+        let html = SAMPLE_HTML.replace(
+            "<p>Hello</p>",
+            &format!(
+                r#"<p>Hello</p><object id="provenance" hidden data="data:Application/C2PA;base64,{}"></object><img src="data:image/png;base64,AAAA">"#,
+                STANDARD.encode(&store)
+            ),
+        );
+
+        // off by default
+        assert!(matches!(
+            HtmlIO::new("html").read_cai(&mut Cursor::new(&html)),
+            Err(Error::JumbfNotFound)
+        ));
+
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            read_data_uris: true,
+            ..Default::default()
+        });
+        assert_eq!(html_io.read_cai(&mut Cursor::new(&html)).unwrap(), store);
+        assert_eq!(
+            html_io.read_manifests(&mut Cursor::new(&html)).unwrap(),
+            vec![EmbeddedManifest {
+                id: Some("provenance".into()),
+                store_bytes: store.clone(),
+            }]
+        );
+
+        let temp_dir = tempdirectory().unwrap();
+        let path = temp_dir_path(&temp_dir, "object.html");
+        std::fs::write(&path, &html).unwrap();
+        assert_eq!(html_io.read_cai_store(&path).unwrap(), store);
+
+        // data URIs of other types and without base64 are ignored
+        assert_eq!(c2pa_data_uri_content("data:image/png;base64,AAAA"), None);
+        assert_eq!(c2pa_data_uri_content("data:application/c2pa,AAAA"), None);
+        assert_eq!(
+            c2pa_data_uri_content(" data:application/c2pa;v=1;base64,AA\n AA "),
+            Some("AAAA".into())
+        );
+    }
+
+    #[test]
+    fn test_custom_manifest_element_round_trip() {
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            manifest_element: Some("c2pa-manifest".into()),
+            ..Default::default()
+        });
+        let store = placeholder_store().unwrap();
+
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut output, &store)
+            .unwrap();
+        let written = String::from_utf8(output.into_inner()).unwrap();
+        let manifest_b64 = STANDARD.encode(&store);
+        assert!(written.contains(&format!(
+            "<c2pa-manifest type=\"application/c2pa-manifest\">{manifest_b64}</c2pa-manifest>"
+        )));
+        assert!(!written.contains("<script"));
+        assert_eq!(html_io.read_cai(&mut Cursor::new(&written)).unwrap(), store);
+
+        // the default configuration only knows scripts
+        assert!(matches!(
+            HtmlIO::default().read_cai(&mut Cursor::new(&written)),
+            Err(Error::JumbfNotFound)
+        ));
+
+        // replacing keeps a single element
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(&written), &mut output, &store)
+            .unwrap();
+        let rewritten = String::from_utf8(output.into_inner()).unwrap();
+        assert_eq!(rewritten, written);
+
+        let cai = html_io
+            .get_object_locations_from_stream(&mut Cursor::new(&written))
+            .unwrap()
+            .into_iter()
+            .find(|o| o.htype == HashBlockObjectType::Cai)
+            .unwrap();
+        assert_eq!(
+            &written[cai.offset..cai.offset + cai.length],
+            manifest_b64.as_str()
+        );
+
+        let temp_dir = tempdirectory().unwrap();
+        let path = temp_dir_path(&temp_dir, "custom.html");
+        std::fs::write(&path, &written).unwrap();
+        assert_eq!(html_io.read_cai_store(&path).unwrap(), store);
+        html_io.remove_cai_store(&path).unwrap();
+        assert!(!std::fs::read_to_string(&path)
+            .unwrap()
+            .contains("c2pa-manifest"));
+
+        // manifest scripts are still read, manifest markup inside scripts is not
+        let html = SAMPLE_HTML.replace(
+            "</head>",
+            &format!(
+                "<script>var m = '<c2pa-manifest type=\"application/c2pa-manifest\">AAAA</c2pa-manifest>';</script><script type=\"application/c2pa-manifest\">{manifest_b64}</script></head>"
+            ),
+        );
+        assert_eq!(html_io.read_cai(&mut Cursor::new(&html)).unwrap(), store);
+    }
+
+    #[test]
+    fn test_custom_manifest_element_rejects_invalid_name() {
+        for name in ["manifest", "C2PA-manifest", "c2pa manifest", "c2pa-<x>"] {
+            let html_io = HtmlIO::with_config(HtmlIoConfig {
+                manifest_element: Some(name.into()),
+                ..Default::default()
+            });
+            let result = html_io.write_cai(
+                &mut Cursor::new(SAMPLE_HTML),
+                &mut Cursor::new(Vec::new()),
+                b"manifest",
+            );
+            assert!(matches!(result, Err(Error::BadParam(_))), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_content_fingerprint_ignores_manifest() {
+        let html_io = HtmlIO::default();
+        let embed = |store: &[u8]| {
+            let mut output = Cursor::new(Vec::new());
+            html_io
+                .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut output, store)
+                .unwrap();
+            output.into_inner()
+        };
+
+        let placeholder = embed(&placeholder_store().unwrap());
+        let signed = sign_sample(SAMPLE_HTML);
+        assert_ne!(placeholder, signed);
+
+        let fingerprint = html_io
+            .content_fingerprint(&mut Cursor::new(&placeholder))
+            .unwrap();
+        assert_eq!(
+            html_io
+                .content_fingerprint(&mut Cursor::new(&signed))
+                .unwrap(),
+            fingerprint
+        );
+        assert_eq!(
+            html_io
+                .content_fingerprint(&mut Cursor::new(SAMPLE_HTML))
+                .unwrap(),
+            fingerprint
+        );
+
+        let edited = SAMPLE_HTML.replace("Hello", "Goodbye");
+        assert_ne!(
+            html_io
+                .content_fingerprint(&mut Cursor::new(&edited))
+                .unwrap(),
+            fingerprint
+        );
+    }
+
+    #[test]
+    fn test_self_closed_manifest_script_is_empty() {
+        let html = SAMPLE_HTML.replace(
+            "</head>",
+            "<script type=\"application/c2pa-manifest\" /><script>var a = 1;</script></head>",
+        );
+        let html_io = HtmlIO::default();
+
+        assert!(matches!(
+            html_io.read_cai(&mut Cursor::new(&html)),
+            Err(Error::JumbfNotFound)
+        ));
+        let temp_dir = tempdirectory().unwrap();
+        let path = temp_dir_path(&temp_dir, "self_closed.html");
+        std::fs::write(&path, &html).unwrap();
+        assert!(matches!(
+            html_io.read_cai_store(&path),
+            Err(Error::JumbfNotFound)
+        ));
+
+        // writing replaces the empty manifest and keeps the script after it
+        let store = placeholder_store().unwrap();
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(&html), &mut output, &store)
+            .unwrap();
+        let written = String::from_utf8(output.into_inner()).unwrap();
+        assert_eq!(html_io.read_cai(&mut Cursor::new(&written)).unwrap(), store);
+        assert!(written.contains("<script>var a = 1;</script>"));
+        assert_eq!(written.matches("application/c2pa-manifest").count(), 1);
+
+        html_io.remove_cai_store(&path).unwrap();
+        let removed = std::fs::read_to_string(&path).unwrap();
+        assert!(!removed.contains("application/c2pa-manifest"));
+        assert!(removed.contains("<script>var a = 1;</script>"));
+    }
+
+    #[test]
+    fn test_read_escaped_html() {
+        let signed = String::from_utf8(sign_sample(SAMPLE_HTML)).unwrap();
+        let escaped = signed
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;");
+        let page = format!(
+            "<html><head><title>Signing</title></head><body><pre><code>{escaped}</code></pre></body></html>"
+        );
+
+        assert!(matches!(
+            HtmlIO::default().read_cai(&mut Cursor::new(&page)),
+            Err(Error::JumbfNotFound)
+        ));
+
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            read_escaped_html: true,
+            ..Default::default()
+        });
+        let store = HtmlIO::default()
+            .read_cai(&mut Cursor::new(&signed))
+            .unwrap();
+        assert_eq!(html_io.read_cai(&mut Cursor::new(&page)).unwrap(), store);
+
+        let temp_dir = tempdirectory().unwrap();
+        let path = temp_dir_path(&temp_dir, "escaped.html");
+        std::fs::write(&path, &page).unwrap();
+        assert_eq!(html_io.read_cai_store(&path).unwrap(), store);
+
+        // unescaped documents are read as before
+        assert_eq!(html_io.read_cai(&mut Cursor::new(&signed)).unwrap(), store);
+    }
+
+    #[test]
+    fn test_locate_manifest_on_bytes() {
+        let html: &[u8] = b"<html><head><script type=\"application/c2pa-manifest+hex\">\n  0a0b\n</script></head></html>";
+        let config = HtmlIoConfig::default();
+        let location = locate_manifest(html, &config).unwrap();
+        assert_eq!(&html[location.text.clone()], b"0a0b");
+        assert_eq!(location.encoding, ManifestEncoding::Hex);
+        // the core and alloc only scanner gives the same location
+        assert_eq!(
+            html_scan::locate_manifest(html, ManifestLookup::default()),
+            Some(location)
+        );
+
+        assert_eq!(locate_manifest(b"<html><p>Hello</p></html>", &config), None);
+        assert_eq!(
+            locate_manifest(
+                b"<script type=\"application/c2pa-manifest\">\xff</script>",
+                &config
+            ),
+            None
+        );
+
+        let custom: &[u8] =
+            b"<c2pa-manifest type=\"application/c2pa-manifest\">AAAA</c2pa-manifest>";
+        assert_eq!(locate_manifest(custom, &config), None);
+        assert_eq!(
+            locate_manifest(
+                custom,
+                &HtmlIoConfig {
+                    manifest_element: Some("c2pa-manifest".into()),
+                    ..Default::default()
+                }
+            )
+            .map(|l| l.text),
+            Some(48..52)
+        );
+    }
+
+    #[test]
+    fn test_active_manifest_selection() {
+        let script = |store: &[u8], marker: &str| {
+            format!(
+                "<script type=\"application/c2pa-manifest\"{marker}>{}</script>",
+                STANDARD.encode(store)
+            )
+        };
+        let first = HtmlIO::default()
+            .read_cai(&mut Cursor::new(sign_sample(SAMPLE_HTML)))
+            .unwrap();
+        let second = placeholder_store().unwrap();
+        let marked = SAMPLE_HTML.replace(
+            "</body>",
+            &format!(
+                "{}{}</body>",
+                script(&first, " data-c2pa-active=\"true\""),
+                script(&second, "")
+            ),
+        );
+        let unmarked = marked.replace(" data-c2pa-active=\"true\"", "");
+
+        let with_active = |active| {
+            HtmlIO::with_config(HtmlIoConfig {
+                active_manifest: active,
+                ..Default::default()
+            })
+        };
+        let read = |html_io: &HtmlIO, html: &str| html_io.read_cai(&mut Cursor::new(html)).unwrap();
+
+        // the marked manifest, otherwise the last one
+        let html_io = HtmlIO::default();
+        assert_eq!(read(&html_io, &marked), first);
+        assert_eq!(read(&html_io, &unmarked), second);
+        assert_eq!(read(&with_active(ActiveManifest::Last), &marked), second);
+        assert_eq!(read(&with_active(ActiveManifest::First), &unmarked), first);
+
+        let temp_dir = tempdirectory().unwrap();
+        let path = temp_dir_path(&temp_dir, "active.html");
+        std::fs::write(&path, &marked).unwrap();
+        assert_eq!(html_io.read_cai_store(&path).unwrap(), first);
+
+        // the reader validates the marked manifest
+        let reader = Reader::from_stream("text/html", Cursor::new(marked.as_bytes())).unwrap();
+        assert!(reader.active_manifest().is_some());
+
+        // writing replaces the marked manifest and keeps it marked
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(&marked), &mut output, b"replacement")
+            .unwrap();
+        let written = String::from_utf8(output.into_inner()).unwrap();
+        assert_eq!(read(&html_io, &written), b"replacement");
+        assert!(written.contains(&script(&second, "")));
+    }
+
+    #[test]
+    fn test_remove_manifest_by_index_and_label() {
+        let store_of = |html: &str| {
+            HtmlIO::default()
+                .read_cai(&mut Cursor::new(sign_sample(html)))
+                .unwrap()
+        };
+        let first = store_of(SAMPLE_HTML);
+        let second = store_of(&SAMPLE_HTML.replace("Hello", "Goodbye"));
+        let label_of = |store: &[u8]| {
+            Store::from_jumbf(store, &mut StatusTracker::default())
+                .unwrap()
+                .provenance_label()
+                .unwrap()
+        };
+        let html = SAMPLE_HTML.replace(
+            "</body>",
+            &format!(
+                "<script type=\"application/c2pa-manifest\" id=\"a\">{}</script>\n<script type=\"application/c2pa-manifest\" id=\"b\">{}</script>\n</body>",
+                STANDARD.encode(&first),
+                STANDARD.encode(&second)
+            ),
+        );
+        let html_io = HtmlIO::default();
+        let remaining = |output: Cursor<Vec<u8>>| {
+            html_io
+                .read_manifests(&mut Cursor::new(output.into_inner()))
+                .unwrap()
+        };
+
+        // the first of two
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .remove_manifest_by_index(&mut Cursor::new(&html), &mut output, 0)
+            .unwrap();
+        assert_eq!(
+            remaining(output),
+            vec![EmbeddedManifest {
+                id: Some("b".into()),
+                store_bytes: second.clone(),
+            }]
+        );
+
+        // the second of two
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .remove_manifest_by_label(&mut Cursor::new(&html), &mut output, &label_of(&second))
+            .unwrap();
+        assert_eq!(
+            remaining(output),
+            vec![EmbeddedManifest {
+                id: Some("a".into()),
+                store_bytes: first.clone(),
+            }]
+        );
+
+        // by the other criterion each
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .remove_manifest_by_index(&mut Cursor::new(&html), &mut output, 1)
+            .unwrap();
+        assert_eq!(remaining(output)[0].store_bytes, first);
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .remove_manifest_by_label(&mut Cursor::new(&html), &mut output, &label_of(&first))
+            .unwrap();
+        assert_eq!(remaining(output)[0].store_bytes, second);
+
+        for result in [
+            html_io.remove_manifest_by_index(
+                &mut Cursor::new(&html),
+                &mut Cursor::new(Vec::new()),
+                2,
+            ),
+            html_io.remove_manifest_by_label(
+                &mut Cursor::new(&html),
+                &mut Cursor::new(Vec::new()),
+                "urn:uuid:unknown",
+            ),
+        ] {
+            assert!(matches!(result, Err(Error::JumbfNotFound)));
+        }
+    }
+
+    #[test]
+    fn test_write_reports_replaced_undecodable_manifest() {
+        let html_io = HtmlIO::default();
+        let store = placeholder_store().unwrap();
+        let with_manifest = |text: &str| {
+            SAMPLE_HTML.replace(
+                "</body>",
+                &format!("<script type=\"application/c2pa-manifest\">{text}</script></body>"),
+            )
+        };
+        let report = |html: &str| {
+            let mut output = Cursor::new(Vec::new());
+            let report = html_io
+                .write_cai_with_report(&mut Cursor::new(html), &mut output, &store)
+                .unwrap();
+            assert_eq!(html_io.read_cai(&mut output).unwrap(), store);
+            report.replaced_undecodable_manifest
+        };
+
+        assert!(report(&with_manifest("not*base64!")));
+        assert!(report(&with_manifest(
+            &STANDARD.encode(b"not a manifest store")
+        )));
+
+        assert!(!report(&with_manifest(&STANDARD.encode(&store))));
+        assert!(!report(&with_manifest("")));
+        assert!(!report(SAMPLE_HTML));
+    }
+
+    #[test]
+    fn test_write_manifest_json() {
+        let signed = sign_sample(SAMPLE_HTML);
+        let mut json = Vec::new();
+        HtmlIO::default()
+            .write_manifest_json(&mut Cursor::new(&signed), &mut json)
+            .unwrap();
+
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        let reader = Reader::from_stream("text/html", Cursor::new(&signed)).unwrap();
+        assert_eq!(value["active_manifest"].as_str(), reader.active_label());
+        assert!(value["manifests"].is_object());
+
+        assert!(matches!(
+            HtmlIO::default().write_manifest_json(&mut Cursor::new(SAMPLE_HTML), &mut Vec::new()),
+            Err(Error::JumbfNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_xml_declaration_encoding() {
+        let store = placeholder_store().unwrap();
+        let html_io = HtmlIO::default();
+        let write = |html: &[u8]| {
+            let mut output = Cursor::new(Vec::new());
+            html_io
+                .write_cai(&mut Cursor::new(html), &mut output, &store)
+                .unwrap();
+            output.into_inner()
+        };
+
+        // UTF-8, the declaration stays at the top
+        let written = write(SAMPLE_XHTML.as_bytes());
+        assert!(written.starts_with(b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<html"));
+        assert_eq!(html_io.read_cai(&mut Cursor::new(&written)).unwrap(), store);
+
+        // a declared single byte encoding is decoded and written back as such
+        let latin1 = SAMPLE_XHTML
+            .replace("UTF-8", "ISO-8859-1")
+            .replace("Hello", "Caf\u{e9}");
+        let (bytes, _, _) = encoding_rs::WINDOWS_1252.encode(&latin1);
+        assert!(bytes.contains(&0xe9));
+        assert_eq!(
+            TextEncoding::detect(&bytes),
+            TextEncoding::Declared(encoding_rs::WINDOWS_1252)
+        );
+        let written = write(&bytes);
+        assert!(written.starts_with(b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>"));
+        assert!(written.windows(5).any(|w| w == b"Caf\xe9<"));
+        assert_eq!(html_io.read_cai(&mut Cursor::new(&written)).unwrap(), store);
+
+        // the regions are byte offsets into the encoded document
+        let cai = html_io
+            .get_object_locations_from_stream(&mut Cursor::new(&written))
+            .unwrap()
+            .into_iter()
+            .find(|o| o.htype == HashBlockObjectType::Cai)
+            .unwrap();
+        assert_eq!(
+            &written[cai.offset..cai.offset + cai.length],
+            STANDARD.encode(&store).as_bytes()
+        );
+
+        // UTF-16 names and unknown labels keep the UTF-8 default
+        for label in ["UTF-16", "unknown"] {
+            let declared = SAMPLE_XHTML.replace("UTF-8", label);
+            assert_eq!(
+                TextEncoding::detect(declared.as_bytes()),
+                TextEncoding::Utf8
+            );
+        }
+    }
+
+    #[test]
+    fn test_max_tokens() {
+        let store = placeholder_store().unwrap();
+        let depth = 50_000;
+        let nested = format!(
+            "<html><head></head><body>{}{}</body></html>",
+            "<div>".repeat(depth),
+            "</div>".repeat(depth)
+        );
+
+        let mut signed = Cursor::new(Vec::new());
+        HtmlIO::default()
+            .write_cai(&mut Cursor::new(nested), &mut signed, &store)
+            .unwrap();
+
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            max_tokens: Some(1000),
+            ..Default::default()
+        });
+        let err = html_io.read_manifests(&mut signed).unwrap_err();
+        assert!(matches!(err, Error::InvalidAsset(ref m) if m == "document too complex"));
+        let err = html_io
+            .get_object_locations_from_stream(&mut signed)
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidAsset(ref m) if m == "document too complex"));
+
+        // the cap counts tokens, text with `<` that starts no tag is not one
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            max_tokens: Some(2 * depth + 16),
+            ..Default::default()
+        });
+        assert_eq!(html_io.read_manifests(&mut signed).unwrap().len(), 1);
+        let text = "<html><body>1 < 2</body></html>";
+        assert!(check_token_count(text, 4).is_ok());
+        assert!(check_token_count(text, 3).is_err());
+    }
+
+    #[test]
+    fn test_read_offset_matches_replaced_region() {
+        let store = placeholder_store().unwrap();
+        let encoded = STANDARD.encode(&store);
+        let html_io = HtmlIO::default();
+        let lookup = ManifestLookup::default();
+
+        for (before, after) in [("", ""), ("\n   ", "\t\n"), ("\r\n", "\r\n  "), (" ", "")] {
+            let manifest = format!(
+                "  <script type=\"{C2PA_SCRIPT_TYPE}\">{before}{encoded}{after}</script>\n"
+            );
+            let html = SAMPLE_HTML.replace("</head>", &format!("{manifest}</head>"));
+
+            // the tokenizer is the single source of the bounds for reading and writing
+            let location = locate_manifest(html.as_bytes(), html_io.config()).unwrap();
+            let element = find_manifest_element(&html, lookup).unwrap();
+            let block = element.block_range(&html, None);
+            assert_eq!(element.manifest_text(&html), location.text);
+            assert_eq!(&html[location.text.clone()], encoded);
+            assert!(block.start <= element.start && location.text.end <= block.end);
+            let scanned = scan_manifest_block(
+                &mut Cursor::new(&html),
+                ActiveManifest::default(),
+                false,
+                None,
+            )
+            .unwrap()
+            .unwrap();
+            assert_eq!(scanned.block, block);
+
+            let (_, offset, _) =
+                detect_manifest_location(&mut Cursor::new(&html), html_io.config()).unwrap();
+            assert_eq!(offset, location.text.start);
+
+            // writing replaces exactly that block and hashes the manifest where it reads it
+            let mut output = Cursor::new(Vec::new());
+            html_io
+                .write_cai(&mut Cursor::new(&html), &mut output, &store)
+                .unwrap();
+            let written = String::from_utf8(output.into_inner()).unwrap();
+            assert_eq!(written[..block.start], html[..block.start]);
+            let written_element = find_manifest_element(&written, lookup).unwrap();
+            assert_eq!(
+                written_element.block_range(&written, None).start,
+                block.start
+            );
+
+            let cai = html_io
+                .get_object_locations_from_stream(&mut Cursor::new(&written))
+                .unwrap()
+                .into_iter()
+                .find(|o| o.htype == HashBlockObjectType::Cai)
+                .unwrap();
+            let written_location = locate_manifest(written.as_bytes(), html_io.config()).unwrap();
+            assert_eq!(cai.offset, written_location.text.start);
+            assert_eq!(&written[cai.offset..cai.offset + cai.length], encoded);
+        }
+    }
+
+    #[test]
+    fn test_sign_html() {
+        let signer = test_signer(SigningAlg::Ps256);
+        let manifest_def =
+            r#"{"claim_generator_info": [{"name": "c2pa_test", "version": "1.0.0"}]}"#;
+
+        for (html, format) in [
+            (SAMPLE_HTML, "text/html"),
+            (SAMPLE_XHTML, "application/xhtml+xml"),
+        ] {
+            let signed = sign_html(html.as_bytes(), signer.as_ref(), manifest_def).unwrap();
+
+            HtmlIO::default()
+                .verify_binding(&mut Cursor::new(&signed))
+                .unwrap();
+            let reader = crate::Reader::from_stream(format, Cursor::new(signed)).unwrap();
+            assert!(reader.active_manifest().is_some());
+            assert_eq!(reader.validation_status(), None);
+        }
+
+        assert!(sign_html(SAMPLE_HTML.as_bytes(), signer.as_ref(), "not json").is_err());
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_read_cai_range() {
+        use httpmock::prelude::*;
+
+        let store = placeholder_store().unwrap();
+        let mut signed = Cursor::new(Vec::new());
+        HtmlIO::default()
+            .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut signed, &store)
+            .unwrap();
+        let cai = HtmlIO::default()
+            .get_object_locations_from_stream(&mut signed)
+            .unwrap()
+            .into_iter()
+            .find(|o| o.htype == HashBlockObjectType::Cai)
+            .unwrap();
+        let range = cai.offset..cai.offset + cai.length;
+        let document = signed.into_inner();
+
+        let server = MockServer::start();
+        let ranged = server.mock(|when, then| {
+            when.method(GET)
+                .path("/page.html")
+                .header("range", format!("bytes={}-{}", range.start, range.end - 1));
+            then.status(206).body(&document[range.clone()]);
+        });
+        let full = server.mock(|when, then| {
+            when.method(GET).path("/full.html");
+            then.status(200).body(&document);
+        });
+        server.mock(|when, then| {
+            when.method(GET).path("/missing.html");
+            then.status(404);
+        });
+
+        assert_eq!(
+            read_cai_range(&server.url("/page.html"), range.clone()).unwrap(),
+            store
+        );
+        ranged.assert();
+        assert_eq!(
+            read_cai_range(&server.url("/full.html"), range.clone()).unwrap(),
+            store
+        );
+        full.assert();
+
+        assert!(matches!(
+            read_cai_range(&server.url("/missing.html"), range.clone()),
+            Err(Error::RemoteManifestFetch(_))
+        ));
+        assert!(matches!(
+            read_cai_range(&server.url("/full.html"), 0..range.len()),
+            Err(Error::JumbfNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_embed_then_remove_restores_original_bytes() {
+        let store = placeholder_store().unwrap();
+        let html_io = HtmlIO::default();
+        let documents = [
+            SAMPLE_HTML.to_owned(),
+            SAMPLE_XHTML.to_owned(),
+            SAMPLE_FRAGMENT.to_owned(),
+            "<html><head><title>t</title></head><body><p>x</p></body></html>\n".to_owned(),
+            "<html>\r\n<head></head>\r\n<body>\r\n  <p>x</p>\r\n\r\n</body>\r\n</html>\r\n"
+                .to_owned(),
+            "<html><head></head></html>".to_owned(),
+            "<p>no newline at the end</p>".to_owned(),
+            "<p>trailing blank lines</p>\n\n\n".to_owned(),
+        ];
+        for html in documents {
+            let mut embedded = Cursor::new(Vec::new());
+            html_io
+                .write_cai(&mut Cursor::new(&html), &mut embedded, &store)
+                .unwrap();
+            assert_eq!(html_io.read_cai(&mut embedded).unwrap(), store);
+
+            let mut removed = Cursor::new(Vec::new());
+            html_io
+                .remove_cai_store_from_stream(&mut embedded, &mut removed)
+                .unwrap();
+            assert_eq!(
+                String::from_utf8(removed.into_inner()).unwrap(),
+                html,
+                "{html:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_require_head_placement() {
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            require_head_placement: true,
+            ..Default::default()
+        });
+
+        // without a `</body>` the manifest is written into the head
+        let head_placed = sign_sample("<html><head><title>t</title></head><p>x</p></html>");
+        assert!(head_range(&String::from_utf8_lossy(&head_placed)).is_some());
+        html_io
+            .verify_binding(&mut Cursor::new(&head_placed))
+            .unwrap();
+
+        for signed in [sign_sample(SAMPLE_HTML), sign_sample(SAMPLE_FRAGMENT)] {
+            assert!(matches!(
+                html_io.verify_binding(&mut Cursor::new(&signed)),
+                Err(Error::ManifestNotInHead)
+            ));
+            HtmlIO::default()
+                .verify_binding(&mut Cursor::new(&signed))
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_cdn_injected_manifest_attributes() {
+        let store = placeholder_store().unwrap();
+        let injected = |content: &str| {
+            let script = format!(
+                "<script data-cfasync=\"false\" type=\"{C2PA_SCRIPT_TYPE}\" nonce='a\"b' defer>{content}</script>"
+            );
+            SAMPLE_HTML.replace("</head>", &format!("{script}</head>"))
+        };
+
+        // reading matches on the type whatever else the tag carries
+        assert_eq!(
+            HtmlIO::default()
+                .read_cai(&mut Cursor::new(injected(&STANDARD.encode(&store))))
+                .unwrap(),
+            store
+        );
+
+        // a manifest of another size is replaced with a new open tag
+        let html = injected("QUJD");
+        let write = |html_io: HtmlIO| {
+            let mut output = Cursor::new(Vec::new());
+            html_io
+                .write_cai(&mut Cursor::new(&html), &mut output, &store)
+                .unwrap();
+            assert_eq!(html_io.read_cai(&mut output).unwrap(), store);
+            String::from_utf8(output.into_inner()).unwrap()
+        };
+
+        let stripped = write(HtmlIO::default());
+        assert!(stripped.contains(&format!("<script type=\"{C2PA_SCRIPT_TYPE}\">")));
+        assert!(!stripped.contains("data-cfasync"));
+
+        let kept = write(HtmlIO::with_config(HtmlIoConfig {
+            keep_manifest_attributes: true,
+            ..Default::default()
+        }));
+        assert!(kept.contains(&format!(
+            "<script type=\"{C2PA_SCRIPT_TYPE}\" data-cfasync=\"false\" nonce='a\"b' defer=\"\">"
+        )));
+    }
+
+    #[test]
+    fn test_supported_encodings() {
+        assert_eq!(HtmlIO::supported_encodings(), ["base64", "hex"]);
+
+        let store = placeholder_store().unwrap();
+        for name in HtmlIO::supported_encodings() {
+            let encoding = ManifestEncoding::from_name(name).unwrap();
+            assert_eq!(encoding.name(), *name);
+            assert_eq!(
+                ManifestEncoding::from_name(&name.to_uppercase()),
+                Some(encoding)
+            );
+
+            let html_io = HtmlIO::with_config(HtmlIoConfig {
+                manifest_encoding: encoding,
+                ..Default::default()
+            });
+            let mut output = Cursor::new(Vec::new());
+            html_io
+                .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut output, &store)
+                .unwrap();
+            assert_eq!(html_io.read_cai(&mut output).unwrap(), store);
+        }
+        assert_eq!(ManifestEncoding::from_name("brotli"), None);
+    }
+
+    #[test]
+    fn test_manifest_among_other_scripts() {
+        let store = placeholder_store().unwrap();
+        let encoded = STANDARD.encode(&store);
+        let json_ld = r#"<script type="application/ld+json">{"@context": "https://schema.org", "@type": "Article", "text": "<script type='application/c2pa-manifest'>"}</script>"#;
+        let analytics = r#"<script>window.dataLayer = []; document.write("<scr" + "ipt src='a.js'></scr" + "ipt>");</script>"#;
+        let manifest = format!("<script type=\"{C2PA_SCRIPT_TYPE}\">{encoded}</script>");
+        let before = format!("<html><head>{json_ld}\n{analytics}\n");
+        let html = format!(
+            "{before}{manifest}\n<script async src=\"gtag.js\"></script>\n{json_ld}</head><body></body></html>"
+        );
+
+        let text_start = before.len() + manifest.find(&encoded).unwrap();
+        let location = locate_manifest(html.as_bytes(), &HtmlIoConfig::default()).unwrap();
+        assert_eq!(location.text, text_start..text_start + encoded.len());
+
+        let element = find_manifest_element(&html, ManifestLookup::default()).unwrap();
+        assert_eq!(
+            element.start..element.end,
+            before.len()..before.len() + manifest.len()
+        );
+        assert_eq!(manifest_elements(&html, ManifestLookup::default()).len(), 1);
+        let scanned = scan_manifest_block(
+            &mut Cursor::new(&html),
+            ActiveManifest::default(),
+            false,
+            None,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(scanned.block, element.block_range(&html, None));
+        assert_eq!(&html[scanned.content], encoded);
+
+        assert_eq!(
+            HtmlIO::default().read_cai(&mut Cursor::new(&html)).unwrap(),
+            store
+        );
+    }
+
+    #[test]
+    fn test_provenance_tree() {
+        let parent = sign_sample(SAMPLE_HTML);
+
+        let mut builder = Builder::from_json(
+            r#"{"title": "child.html", "claim_generator_info": [{"name": "c2pa_test", "version": "1.0.0"}]}"#,
+        )
+        .unwrap();
+        builder
+            .add_ingredient_from_stream(
+                r#"{"title": "parent.html", "relationship": "parentOf"}"#,
+                "text/html",
+                &mut Cursor::new(&parent),
+            )
+            .unwrap();
+        let signer = test_signer(SigningAlg::Ps256);
+        let mut child = Cursor::new(Vec::new());
+        builder
+            .sign(
+                signer.as_ref(),
+                "text/html",
+                &mut Cursor::new(SAMPLE_HTML.replace("Hello", "Edited")),
+                &mut child,
+            )
+            .unwrap();
+
+        let tree = HtmlIO::default().provenance_tree(&mut child).unwrap();
+        assert!(tree.label.is_some());
+        assert_eq!(tree.title.as_deref(), Some("child.html"));
+        assert!(tree.valid);
+        assert_eq!(tree.children.len(), 1);
+
+        let ingredient = &tree.children[0];
+        assert_eq!(ingredient.title.as_deref(), Some("parent.html"));
+        assert!(ingredient.label.is_some());
+        assert_ne!(ingredient.label, tree.label);
+        assert!(ingredient.valid);
+        assert!(ingredient.children.is_empty());
+
+        let json = serde_json::to_value(&tree).unwrap();
+        assert_eq!(json["children"][0]["title"], "parent.html");
+
+        assert!(matches!(
+            HtmlIO::default().provenance_tree(&mut Cursor::new(SAMPLE_HTML)),
+            Err(Error::JumbfNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_truncated_html_rejected() {
+        let store = placeholder_store().unwrap();
+        let html_io = HtmlIO::default();
+        for truncated in [
+            "<body><div",
+            "<html><body><div class=\"a",
+            "<p>text</p><!-- c",
+        ] {
+            let mut output = Cursor::new(Vec::new());
+            let err = html_io
+                .write_cai(&mut Cursor::new(truncated), &mut output, &store)
+                .unwrap_err();
+            assert!(
+                matches!(err, Error::InvalidAsset(ref m) if m == "truncated HTML"),
+                "{truncated}"
+            );
+        }
+
+        // a `<` that opens no tag is text
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new("<p>1 < 2</p> 3 <"), &mut output, &store)
+            .unwrap();
+        assert_eq!(html_io.read_cai(&mut output).unwrap(), store);
+    }
+
+    #[test]
+    fn test_strip_comments() {
+        let html = SAMPLE_HTML
+            .replace("<head>", "<head><!-- build 42 -->")
+            .replace(
+                "</body>",
+                "<script>var s = '<!-- kept -->';</script><style>/* <!-- kept --> */</style><!--\nmulti\nline\n--></body>",
+            );
+        assert_eq!(
+            strip_html_comments(&html),
+            SAMPLE_HTML.replace(
+                "</body>",
+                "<script>var s = '<!-- kept -->';</script><style>/* <!-- kept --> */</style></body>"
+            )
+        );
+        assert_eq!(
+            strip_html_comments("<p>a</p><!-- open"),
+            "<p>a</p><!-- open"
+        );
+
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            strip_comments: true,
+            ..Default::default()
+        });
+        let mut builder = Builder::from_json(
+            r#"{"claim_generator_info": [{"name": "c2pa_test", "version": "1.0.0"}]}"#,
+        )
+        .unwrap();
+        let mut signed = Cursor::new(Vec::new());
+        let mut placeholder = Cursor::new(Vec::new());
+        html_io
+            .write_cai(
+                &mut Cursor::new(&html),
+                &mut placeholder,
+                &placeholder_store().unwrap(),
+            )
+            .unwrap();
+        builder
+            .sign(
+                test_signer(SigningAlg::Ps256).as_ref(),
+                "text/html",
+                &mut Cursor::new(placeholder.into_inner()),
+                &mut signed,
+            )
+            .unwrap();
+        let signed = String::from_utf8(signed.into_inner()).unwrap();
+        assert!(!signed.contains("build 42"));
+
+        // a consumer that strips comments leaves the signed document as it is
+        let consumed = strip_html_comments(&signed);
+        assert_eq!(consumed, signed);
+        HtmlIO::default()
+            .verify_binding(&mut Cursor::new(&consumed))
+            .unwrap();
+
+        // signing the stripped document again is stable
+        let mut first = Cursor::new(Vec::new());
+        let store = placeholder_store().unwrap();
+        html_io
+            .write_cai(&mut Cursor::new(&html), &mut first, &store)
+            .unwrap();
+        let mut second = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut first.clone(), &mut second, &store)
+            .unwrap();
+        assert_eq!(first.into_inner(), second.into_inner());
+    }
+
+    #[test]
+    fn test_manifest_encoding() {
+        let store = placeholder_store().unwrap();
+        let write = |html_io: &HtmlIO, html: &str| {
+            let mut output = Cursor::new(Vec::new());
+            html_io
+                .write_cai(&mut Cursor::new(html), &mut output, &store)
+                .unwrap();
+            output
+        };
+
+        for encoding in [ManifestEncoding::Base64, ManifestEncoding::Hex] {
+            let html_io = HtmlIO::with_config(HtmlIoConfig {
+                manifest_encoding: encoding,
+                ..Default::default()
+            });
+            let mut written = write(&html_io, SAMPLE_HTML);
+            assert_eq!(
+                HtmlIO::default().manifest_encoding(&mut written).unwrap(),
+                Some(encoding)
+            );
+        }
+
+        let html_io = json_html_io();
+        let bootstrap = SAMPLE_HTML.replace(
+            "</head>",
+            "<script type=\"application/json\" id=\"__C2PA__\">{\"manifest\": \"\"}</script></head>",
+        );
+        let mut written = write(&html_io, &bootstrap);
+        assert_eq!(
+            html_io.manifest_encoding(&mut written).unwrap(),
+            Some(ManifestEncoding::Base64)
+        );
+
+        assert_eq!(
+            HtmlIO::default()
+                .manifest_encoding(&mut Cursor::new(SAMPLE_HTML))
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_write_cai_in_place() {
+        use std::sync::{Arc, Mutex};
+
+        // two handles with their own positions over one buffer
+        struct Shared(Arc<Mutex<Vec<u8>>>, u64);
+        impl Read for Shared {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let data = self.0.lock().unwrap();
+                let mut cursor = Cursor::new(&data[..]);
+                cursor.set_position(self.1);
+                let read = cursor.read(buf)?;
+                self.1 = cursor.position();
+                Ok(read)
+            }
+        }
+        impl Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                let mut data = self.0.lock().unwrap();
+                let mut cursor = Cursor::new(&mut *data);
+                cursor.set_position(self.1);
+                let written = cursor.write(buf)?;
+                self.1 = cursor.position();
+                Ok(written)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        impl Seek for Shared {
+            fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+                let len = self.0.lock().unwrap().len() as u64;
+                self.1 = match pos {
+                    SeekFrom::Start(offset) => offset,
+                    SeekFrom::End(offset) => (len as i64 + offset) as u64,
+                    SeekFrom::Current(offset) => (self.1 as i64 + offset) as u64,
+                };
+                Ok(self.1)
+            }
+        }
+
+        let html_io = HtmlIO::default();
+        let store = placeholder_store().unwrap();
+        let fresh = |html: &[u8], store: &[u8]| {
+            let mut output = Cursor::new(Vec::new());
+            html_io
+                .write_cai(&mut Cursor::new(html), &mut output, store)
+                .unwrap();
+            output.into_inner()
+        };
+        let expected = fresh(SAMPLE_HTML.as_bytes(), &store);
+
+        // a growing document can be written through two handles of the same buffer
+        let buffer = Arc::new(Mutex::new(SAMPLE_HTML.as_bytes().to_vec()));
+        html_io
+            .write_cai(
+                &mut Shared(buffer.clone(), 0),
+                &mut Shared(buffer.clone(), 0),
+                &store,
+            )
+            .unwrap();
+        assert_eq!(*buffer.lock().unwrap(), expected);
+
+        // a shrinking one is only right when the buffer is replaced
+        let mut in_place = expected.clone();
+        html_io.write_cai_in_place(&mut in_place, b"small").unwrap();
+        assert_eq!(in_place, fresh(&expected, b"small"));
+        assert!(in_place.len() < expected.len());
+        assert_eq!(
+            html_io.read_cai(&mut Cursor::new(&in_place)).unwrap(),
+            b"small"
+        );
+    }
+
+    #[test]
+    fn test_signature_timestamp() {
+        // the test signer has no time stamp authority
+        let signed = sign_sample(SAMPLE_HTML);
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            timestamp_tolerance: Some(std::time::Duration::from_secs(60)),
+            ..Default::default()
+        });
+        let (reader, _) = html_io
+            .verify_and_extract(&mut Cursor::new(&signed))
+            .unwrap();
+        assert_eq!(
+            html_io.signature_timestamp(&reader),
+            TimestampReport::default()
+        );
+
+        let now = DateTime::parse_from_rfc3339("2025-06-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let validity = Some(now.timestamp() - 365 * 24 * 3600..=now.timestamp() + 365 * 24 * 3600);
+        let minute = Some(std::time::Duration::from_secs(60));
+
+        let report = timestamp_report(Some(now), validity.clone(), now, minute);
+        assert_eq!(report.time, Some(now));
+        assert!(!report.in_future && !report.outside_validity);
+
+        let ahead = now + chrono::Duration::seconds(30);
+        assert!(!timestamp_report(Some(ahead), validity.clone(), now, minute).in_future);
+        let future = now + chrono::Duration::hours(2);
+        assert!(timestamp_report(Some(future), validity.clone(), now, minute).in_future);
+        assert!(!timestamp_report(Some(future), validity.clone(), now, None).in_future);
+
+        let expired = now + chrono::Duration::days(400);
+        assert!(timestamp_report(Some(expired), validity.clone(), expired, None).outside_validity);
+
+        let json = serde_json::to_value(timestamp_report(Some(now), validity, now, None)).unwrap();
+        assert_eq!(json["time"], "2025-06-01T12:00:00Z");
+    }
+
+    /// A minified page is a single 20 MB line. Finding the manifest, the placement and the
+    /// byte offsets are byte searches over the document, so signing is linear in its size.
+    /// Timing is only meaningful in release builds:
+    /// `cargo test --release -p c2pa --lib test_sign_large_minified_html -- --ignored`
+    #[test]
+    #[ignore]
+    fn test_sign_large_minified_html() {
+        let item = "<div class=\"c\"><a href=\"/x\">link</a><span>text</span></div>";
+        let body = item.repeat(20 * 1024 * 1024 / item.len());
+        let html =
+            format!("<!DOCTYPE html><html><head><title>t</title></head><body>{body}</body></html>");
+        let signer = test_signer(SigningAlg::Ps256);
+        let def = r#"{"claim_generator_info": [{"name": "c2pa_test", "version": "1.0.0"}]}"#;
+
+        let start = std::time::Instant::now();
+        let signed = sign_html(html.as_bytes(), signer.as_ref(), def).unwrap();
+        let elapsed = start.elapsed();
+
+        HtmlIO::default()
+            .verify_binding(&mut Cursor::new(&signed))
+            .unwrap();
+        if !cfg!(debug_assertions) {
+            assert!(elapsed < std::time::Duration::from_secs(1), "{elapsed:?}");
+        }
+    }
+
+    #[test]
+    fn test_manifest_offset_mismatch() {
+        let store = placeholder_store().unwrap();
+        let encoded = STANDARD.encode(&store);
+        let html = SAMPLE_HTML.replace(
+            "</head>",
+            &format!("<script type=\"{C2PA_SCRIPT_TYPE}\">{encoded}</script></head>"),
+        );
+        let offset = html.find(&encoded).unwrap();
+        let check = |offset| {
+            check_manifest_offset(
+                html.as_bytes(),
+                offset,
+                encoded.len(),
+                &store,
+                ManifestEncoding::Base64,
+            )
+        };
+
+        assert!(check(offset).is_ok());
+        // one byte off in either direction, and past the end of the document
+        for offset in [offset - 1, offset + 1, html.len()] {
+            assert!(matches!(
+                check(offset),
+                Err(Error::InvalidAsset(msg)) if msg == "manifest offset mismatch"
+            ));
+        }
+
+        // the regions of a well formed document pass the check
+        let html_io = HtmlIO::default();
+        let positions = html_io
+            .get_object_locations_from_stream(&mut Cursor::new(&html))
+            .unwrap();
+        assert_eq!(positions[0].offset, offset);
+    }
+
+    #[test]
+    fn test_lossy_utf8() {
+        let signed = sign_sample(SAMPLE_HTML);
+        let mut html = signed.clone();
+        let manifest = memchr::memmem::find(&html, C2PA_SCRIPT_TYPE.as_bytes()).unwrap();
+        let at = html[..manifest].iter().rposition(|&b| b == b'<').unwrap();
+        html.insert(at, 0xff);
+
+        let strict = HtmlIO::default();
+        assert!(strict.read_cai(&mut Cursor::new(&html)).is_err());
+
+        let lossy = HtmlIO {
+            config: HtmlIoConfig {
+                lossy_utf8: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            lossy.read_cai(&mut Cursor::new(&html)).unwrap(),
+            strict.read_cai(&mut Cursor::new(&signed)).unwrap()
+        );
+
+        // the invalid byte would be replaced when writing, so writing refuses
+        let store = placeholder_store().unwrap();
+        let mut output = Cursor::new(Vec::new());
+        assert!(lossy
+            .write_cai(&mut Cursor::new(&html), &mut output, &store)
+            .is_err());
+        assert!(output.get_ref().is_empty());
+    }
+
+    #[test]
+    fn test_manifest_link_with_inline_fallback() {
+        let href = "https://cdn.example.com/manifests/page.c2pa";
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            manifest_link: Some(href.into()),
+            ..Default::default()
+        });
+        let store = placeholder_store().unwrap();
+
+        let mut written = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut written, &store)
+            .unwrap();
+        let html = String::from_utf8(written.get_ref().clone()).unwrap();
+        assert!(html.contains(&format!(
+            "<head><link rel=\"c2pa-manifest\" href=\"{href}\">"
+        )));
+
+        // the inline manifest is read first and the link matches the configured url
+        let default_io = HtmlIO::default();
+        assert_eq!(default_io.read_cai(&mut Cursor::new(&html)).unwrap(), store);
+        assert_eq!(
+            default_io
+                .remote_manifest_url(&mut Cursor::new(&html), None)
+                .unwrap()
+                .as_deref(),
+            Some(href)
+        );
+
+        // writing again keeps the link and the bytes around the manifest
+        let mut rewritten = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(&html), &mut rewritten, &store)
+            .unwrap();
+        assert_eq!(rewritten.get_ref().as_slice(), html.as_bytes());
+
+        // an existing link is pointed at the configured url
+        let stale = html.replace(href, "https://old.example.com/page.c2pa");
+        let mut updated = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(&stale), &mut updated, &store)
+            .unwrap();
+        assert_eq!(updated.get_ref().as_slice(), html.as_bytes());
+
+        // without the inline manifest the link is the provenance of the document
+        let mut removed = Cursor::new(Vec::new());
+        default_io
+            .remove_cai_store_from_stream(&mut Cursor::new(&html), &mut removed)
+            .unwrap();
+        removed.rewind().unwrap();
+        assert!(matches!(
+            default_io.read_cai(&mut removed),
+            Err(Error::JumbfNotFound)
+        ));
+        let xmp = default_io.read_xmp(&mut removed).unwrap();
+        assert_eq!(extract_provenance(&xmp).as_deref(), Some(href));
+
+        // a fragment has no <head> to hold the link
+        assert!(matches!(
+            html_io.write_cai(
+                &mut Cursor::new(SAMPLE_FRAGMENT),
+                &mut Cursor::new(Vec::new()),
+                &store
+            ),
+            Err(Error::BadParam(_))
+        ));
+    }
+
+    #[cfg(feature = "html_stats")]
+    #[test]
+    fn test_stats() {
+        // the counters are shared with the tests running in parallel, so only increments
+        // are checked
+        let before = HtmlIO::stats();
+        let store = placeholder_store().unwrap();
+        let html_io = HtmlIO::default();
+
+        let mut written = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut written, &store)
+            .unwrap();
+        // without </body> the manifest goes into <head>
+        let mut in_head = Cursor::new(Vec::new());
+        html_io
+            .write_cai(
+                &mut Cursor::new("<html><head><title>t</title></head></html>"),
+                &mut in_head,
+                &store,
+            )
+            .unwrap();
+        html_io.read_cai(&mut written).unwrap();
+        html_io.read_cai(&mut in_head).unwrap();
+        assert!(html_io.read_cai(&mut Cursor::new(SAMPLE_HTML)).is_err());
+
+        let after = HtmlIO::stats();
+        assert!(after.body > before.body);
+        assert!(after.head > before.head);
+        assert!(after.detected_in_head > before.detected_in_head);
+        assert!(after.detected_elsewhere > before.detected_elsewhere);
+        assert!(after.not_detected > before.not_detected);
+    }
+
+    #[test]
+    fn test_manifest_type_exact_match() {
+        let store = placeholder_store().unwrap();
+        let encoded = STANDARD.encode(&store);
+        let with_type = |script_type: &str| {
+            SAMPLE_HTML.replace(
+                "</body>",
+                &format!("<script type=\"{script_type}\">{encoded}</script></body>"),
+            )
+        };
+        let html_io = HtmlIO::default();
+
+        for decoy in [
+            "application/c2pa-manifest-extra",
+            "application/c2pa-manifest-index",
+            "application/c2pa-manifestx",
+            "application/c2pa",
+        ] {
+            let html = with_type(decoy);
+            assert!(
+                matches!(
+                    html_io.read_cai(&mut Cursor::new(&html)),
+                    Err(Error::JumbfNotFound)
+                ),
+                "{decoy}"
+            );
+            assert!(locate_manifest(html.as_bytes(), html_io.config()).is_none());
+        }
+
+        // parameters do not change the media type
+        let html = with_type("application/c2pa-manifest; charset=us-ascii");
+        assert_eq!(html_io.read_cai(&mut Cursor::new(&html)).unwrap(), store);
+        let hex = SAMPLE_HTML.replace(
+            "</body>",
+            &format!(
+                "<script type=\"{C2PA_HEX_SCRIPT_TYPE} ;v=1\">{}</script></body>",
+                hex::encode(&store)
+            ),
+        );
+        assert_eq!(
+            html_io.manifest_encoding(&mut Cursor::new(&hex)).unwrap(),
+            Some(ManifestEncoding::Hex)
+        );
+        assert_eq!(html_io.read_cai(&mut Cursor::new(&hex)).unwrap(), store);
+    }
+
+    #[test]
+    fn test_verify_directory() {
+        let temp_dir = tempdirectory().unwrap();
+        let signed = sign_sample(SAMPLE_HTML);
+        let tampered = String::from_utf8(signed.clone())
+            .unwrap()
+            .replace("<p>", "<p>edited ");
+        assert_ne!(tampered.as_bytes(), signed.as_slice());
+
+        let dir = temp_dir.path();
+        std::fs::write(dir.join("signed.html"), &signed).unwrap();
+        std::fs::write(dir.join("unsigned.html"), SAMPLE_HTML).unwrap();
+        std::fs::write(dir.join("tampered.html"), &tampered).unwrap();
+        std::fs::write(dir.join("broken.html"), b"\xff\xfe\x00").unwrap();
+        std::fs::write(dir.join("notes.txt"), &signed).unwrap();
+        std::fs::create_dir(dir.join("nested.html")).unwrap();
+
+        let report = HtmlIO::default().verify_directory(dir).unwrap();
+        let names: Vec<_> = report
+            .iter()
+            .map(|r| r.path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "broken.html",
+                "signed.html",
+                "tampered.html",
+                "unsigned.html"
+            ]
+        );
+
+        let [broken, signed, tampered, unsigned] = report.as_slice() else {
+            unreachable!()
+        };
+        assert!(broken.error.is_some() && !broken.has_manifest);
+        assert!(signed.has_manifest && signed.valid && signed.error.is_none());
+        assert!(signed.active_label.is_some());
+        assert_eq!(signed.timestamp, TimestampReport::default());
+        assert!(tampered.has_manifest && !tampered.valid);
+        assert!(!unsigned.has_manifest && !unsigned.valid && unsigned.error.is_none());
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json[1]["valid"], serde_json::Value::Bool(true));
+        assert!(json[1]["timestamp"].get("time").is_none());
+        assert!(json[3].get("error").is_none());
+
+        assert!(HtmlIO::default()
+            .verify_directory(&dir.join("missing"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_head_with_attributes() {
+        let store = placeholder_store().unwrap();
+        let script = format!(
+            "<script type=\"{C2PA_SCRIPT_TYPE}\">{}</script>",
+            STANDARD.encode(&store)
+        );
+
+        // no </body>, so the manifest goes right after the <head> open tag
+        for head in [
+            r#"<head lang="en" class="x">"#,
+            r#"<head data-note="a > b" class='x'>"#,
+            "<HEAD\n  lang=en\n  class=x\n>",
+            "<head/>",
+        ] {
+            let html =
+                format!("<!DOCTYPE html><html><header></header>{head}<title>t</title></html>");
+            let mut output = Cursor::new(Vec::new());
+            HtmlIO::default()
+                .write_cai(&mut Cursor::new(&html), &mut output, &store)
+                .unwrap();
+            let written = String::from_utf8(output.into_inner()).unwrap();
+            assert!(written.contains(&format!("{head}{script}")), "{written}");
+        }
+    }
+
+    #[test]
+    fn test_verify_binding_sha512() {
+        let signer = test_signer(SigningAlg::Ps256);
+        let mut builder = Builder::from_json(
+            r#"{"claim_generator_info": [{"name": "c2pa_test", "version": "1.0.0"}]}"#,
+        )
+        .unwrap();
+        let placeholder = builder
+            .data_hashed_placeholder(signer.reserve_size(), "text/html")
+            .unwrap();
+
+        let html_io = HtmlIO::default();
+        let mut reserved = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut reserved, &placeholder)
+            .unwrap();
+        let cai = html_io
+            .get_object_locations_from_stream(&mut reserved)
+            .unwrap()
+            .into_iter()
+            .find(|o| o.htype == HashBlockObjectType::Cai)
+            .unwrap();
+
+        // a claim whose data hash declares SHA-512 while the claim itself defaults to SHA-256
+        let mut data_hash = DataHash::new("jumbf manifest", "sha512");
+        data_hash.add_exclusion(HashRange::new(cai.offset, cai.length));
+        data_hash.gen_hash_from_stream(&mut reserved).unwrap();
+        let mut claim = create_test_claim().unwrap();
+        assert_ne!(claim.alg(), "sha512");
+        claim.add_assertion(&data_hash).unwrap();
+        let mut store = Store::new();
+        store.commit_claim(claim).unwrap();
+
+        let signed = reserved.into_inner();
+        check_data_hash(&store, &mut Cursor::new(&signed), |_| Ok(None)).unwrap();
+
+        // the mismatch is reported with SHA-512 digests
+        let tampered = String::from_utf8(signed)
+            .unwrap()
+            .replace("<p>", "<p>edited ");
+        match check_data_hash(&store, &mut Cursor::new(tampered), |_| Ok(None)) {
+            Err(Error::BindingMismatch {
+                expected, actual, ..
+            }) => {
+                assert_eq!(expected.len(), 64);
+                assert_eq!(actual.len(), 64);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_remove_with_sentinel_comments() {
+        let store = placeholder_store().unwrap();
+        let script = format!(
+            "<script type=\"{C2PA_SCRIPT_TYPE}\">{}</script>",
+            STANDARD.encode(&store)
+        );
+        let html_io = HtmlIO::default();
+        let utf16 = |html: &str| {
+            [0xff, 0xfe]
+                .into_iter()
+                .chain(html.encode_utf16().flat_map(u16::to_le_bytes))
+                .collect::<Vec<u8>>()
+        };
+
+        for bracketed in [
+            format!("\n<!--c2pa-begin--> {script} <!--c2pa-end-->"),
+            format!("\n  <!-- c2pa-begin -->\n  {script}\n  <!-- c2pa-end -->"),
+            // directly in front of the script the begin comment is part of the manifest block
+            format!("\n<!--c2pa-begin-->{script}<!--c2pa-end-->"),
+        ] {
+            let html = SAMPLE_HTML.replace("\n</body>", &format!("{bracketed}\n</body>"));
+            assert_eq!(html_io.read_cai(&mut Cursor::new(&html)).unwrap(), store);
+
+            let mut removed = Cursor::new(Vec::new());
+            html_io
+                .remove_cai_store_from_stream(&mut Cursor::new(&html), &mut removed)
+                .unwrap();
+            assert_eq!(
+                String::from_utf8(removed.into_inner()).unwrap(),
+                SAMPLE_HTML
+            );
+
+            // the decoded document path of UTF-16 documents
+            let mut removed = Cursor::new(Vec::new());
+            html_io
+                .remove_cai_store_from_stream(&mut Cursor::new(utf16(&html)), &mut removed)
+                .unwrap();
+            assert_eq!(removed.into_inner(), utf16(SAMPLE_HTML));
+        }
+
+        // an unmatched sentinel is left alone
+        let html = SAMPLE_HTML.replace(
+            "\n</body>",
+            &format!("\n<!--c2pa-begin--> {script}\n</body>"),
+        );
+        let mut removed = Cursor::new(Vec::new());
+        html_io
+            .remove_cai_store_from_stream(&mut Cursor::new(&html), &mut removed)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(removed.into_inner()).unwrap(),
+            SAMPLE_HTML.replace("\n</body>", "\n<!--c2pa-begin-->\n</body>")
+        );
+    }
+
+    #[test]
+    fn test_save_cai_store_rename_retry() {
+        let temp_dir = tempdirectory().unwrap();
+        let asset = temp_dir_path(&temp_dir, "sample.html");
+        let store = placeholder_store().unwrap();
+        let policy = RenameRetry::new(3, std::time::Duration::from_millis(1));
+        let temp_file = |content: &[u8]| {
+            let mut file = tempfile_builder("c2pa_temp").unwrap();
+            file.write_all(content).unwrap();
+            file
+        };
+
+        // a rename that fails twice (e.g. a locked file) then succeeds
+        std::fs::write(&asset, SAMPLE_HTML).unwrap();
+        let mut calls = 0;
+        let temp = temp_file(b"renamed");
+        let temp_path = temp.path().to_path_buf();
+        replace_with_retry(temp, &asset, policy, &mut |from, to| {
+            calls += 1;
+            match calls {
+                1 | 2 => Err(std::io::ErrorKind::PermissionDenied.into()),
+                _ => std::fs::rename(from, to),
+            }
+        })
+        .unwrap();
+        assert_eq!(calls, 3);
+        assert_eq!(std::fs::read(&asset).unwrap(), b"renamed");
+        assert!(!temp_path.exists());
+
+        // a cross device rename is not retried but copied
+        let mut calls = 0;
+        let temp = temp_file(b"copied");
+        let temp_path = temp.path().to_path_buf();
+        replace_with_retry(temp, &asset, policy, &mut |_, _| {
+            calls += 1;
+            Err(std::io::ErrorKind::CrossesDevices.into())
+        })
+        .unwrap();
+        assert_eq!(calls, 1);
+        assert_eq!(std::fs::read(&asset).unwrap(), b"copied");
+        assert!(!temp_path.exists());
+
+        // a rename that keeps failing falls back to the copy after the retries
+        let mut calls = 0;
+        replace_with_retry(temp_file(b"fallback"), &asset, policy, &mut |_, _| {
+            calls += 1;
+            Err(std::io::ErrorKind::PermissionDenied.into())
+        })
+        .unwrap();
+        assert_eq!(calls, 4);
+        assert_eq!(std::fs::read(&asset).unwrap(), b"fallback");
+
+        // when the copy fails too the written document is kept and the rename error reported
+        let dir_asset = temp_dir_path(&temp_dir, "directory.html");
+        std::fs::create_dir(&dir_asset).unwrap();
+        let temp = temp_file(b"kept");
+        let temp_path = temp.path().to_path_buf();
+        let err = replace_with_retry(temp, &dir_asset, policy, &mut |_, _| {
+            Err(std::io::ErrorKind::PermissionDenied.into())
+        })
+        .unwrap_err();
+        let Error::IoError(err) = err else {
+            panic!("expected an IO error");
+        };
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+        assert!(err.to_string().contains("rename failed"));
+        assert!(err.to_string().contains(&temp_path.display().to_string()));
+        assert_eq!(std::fs::read(&temp_path).unwrap(), b"kept");
+        std::fs::remove_file(&temp_path).unwrap();
+
+        // save_cai_store with the policy
+        std::fs::write(&asset, SAMPLE_HTML).unwrap();
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            rename_retry: Some(policy),
+            ..Default::default()
+        });
+        html_io.save_cai_store(&asset, &store).unwrap();
+        assert_eq!(html_io.read_cai_store(&asset).unwrap(), store);
+    }
+
+    #[test]
+    fn test_write_cai_if_changed() {
+        let html_io = HtmlIO::default();
+        let store = placeholder_store().unwrap();
+        let mut signed = Vec::new();
+        html_io
+            .write_cai(
+                &mut Cursor::new(SAMPLE_HTML.as_bytes()),
+                &mut Cursor::new(&mut signed),
+                &store,
+            )
+            .unwrap();
+        let signed = String::from_utf8(signed).unwrap();
+
+        // identical bytes are detected without copying the document
+        assert!(matches!(
+            html_io.updated_html(&signed, &store).unwrap(),
+            Cow::Borrowed(_)
+        ));
+        let mut output = Vec::new();
+        assert!(!html_io
+            .write_cai_if_changed(
+                &mut Cursor::new(signed.as_bytes()),
+                &mut Cursor::new(&mut output),
+                &store,
+            )
+            .unwrap());
+        assert!(output.is_empty());
+
+        let mut other = store.clone();
+        *other.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            html_io.updated_html(&signed, &other).unwrap(),
+            Cow::Owned(_)
+        ));
+        assert!(html_io
+            .write_cai_if_changed(
+                &mut Cursor::new(signed.as_bytes()),
+                &mut Cursor::new(&mut output),
+                &other,
+            )
+            .unwrap());
+        assert_eq!(html_io.read_cai(&mut Cursor::new(&output)).unwrap(), other);
+
+        // stripping comments copies the document, a signed one is still unchanged
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            strip_comments: true,
+            ..Default::default()
+        });
+        let mut signed = Vec::new();
+        html_io
+            .write_cai(
+                &mut Cursor::new(SAMPLE_HTML.as_bytes()),
+                &mut Cursor::new(&mut signed),
+                &store,
+            )
+            .unwrap();
+        let mut output = Vec::new();
+        assert!(!html_io
+            .write_cai_if_changed(
+                &mut Cursor::new(&signed),
+                &mut Cursor::new(&mut output),
+                &store,
+            )
+            .unwrap());
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_read_sse_framed() {
+        let signed = String::from_utf8(sign_sample(SAMPLE_HTML)).unwrap();
+        let store = HtmlIO::default()
+            .read_cai(&mut Cursor::new(&signed))
+            .unwrap();
+
+        // chunks of the document as events, a newline inside a chunk starts another data line
+        let mut framed = String::from(": cached stream\n\n");
+        for chunk in signed.as_bytes().chunks(64) {
+            framed.push_str("event: chunk\n");
+            for line in std::str::from_utf8(chunk).unwrap().split('\n') {
+                framed.push_str(&format!("data: {line}\n"));
+            }
+            framed.push('\n');
+        }
+
+        assert!(HtmlIO::default()
+            .read_cai(&mut Cursor::new(&framed))
+            .is_err());
+
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            read_sse_framed: true,
+            ..Default::default()
+        });
+        assert_eq!(html_io.read_cai(&mut Cursor::new(&framed)).unwrap(), store);
+
+        let temp_dir = tempdirectory().unwrap();
+        let path = temp_dir_path(&temp_dir, "framed.html");
+        std::fs::write(&path, &framed).unwrap();
+        assert_eq!(html_io.read_cai_store(&path).unwrap(), store);
+
+        // unframed documents are read as before
+        assert_eq!(html_io.read_cai(&mut Cursor::new(&signed)).unwrap(), store);
+    }
+
+    #[test]
+    fn test_manifest_open_tag() {
+        let html_io = HtmlIO::default();
+        let store = STANDARD.encode(placeholder_store().unwrap());
+        let open_tag =
+            "<SCRIPT nonce='r4nd0m'\n    type=\"application/c2pa-manifest\" data-x=\"a>b\">";
+        let html = SAMPLE_HTML.replace("<p>", &format!("{open_tag}{store}</script><p>"));
+
+        assert_eq!(
+            html_io
+                .manifest_open_tag(&mut Cursor::new(&html))
+                .unwrap()
+                .as_deref(),
+            Some(open_tag)
+        );
+        assert_eq!(
+            html_io
+                .manifest_open_tag(&mut Cursor::new(SAMPLE_HTML))
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_insertion_context() {
+        let html_io = HtmlIO::default();
+        let store = placeholder_store().unwrap();
+
+        // scripts are allowed in tables and selects, the document is restored without them
+        for html in [
+            "<html><head></head><body><table><tr><td>cell</td></tr>\n</body></html>",
+            "<html><head></head><body><table><tbody><tr>\n</body></html>",
+            "<html><head></head><body><select><option>a</option>\n</body></html>",
+            "<html><head></head><body><ul><li><textarea>x</textarea>\n</body></html>",
+        ] {
+            let placement = plan_manifest_placement(html, ManifestLookup::default()).unwrap();
+            assert_eq!(insertion_context_warning(html, placement), None, "{html}");
+
+            let mut signed = Vec::new();
+            html_io
+                .write_cai(
+                    &mut Cursor::new(html),
+                    &mut Cursor::new(&mut signed),
+                    &store,
+                )
+                .unwrap();
+            assert_eq!(html_io.read_cai(&mut Cursor::new(&signed)).unwrap(), store);
+            let mut removed = Vec::new();
+            html_io
+                .remove_cai_store_from_stream(
+                    &mut Cursor::new(&signed),
+                    &mut Cursor::new(&mut removed),
+                )
+                .unwrap();
+            assert_eq!(removed, html.as_bytes());
+        }
+
+        // text content left open by a malformed document
+        for (html, context) in [
+            (
+                "<html><head></head><body><textarea>draft\n</body></html>",
+                "textarea",
+            ),
+            ("<html><head></head><body><TITLE>t\n</body></html>", "title"),
+            (
+                "<html><head></head><body><!-- unterminated\n</body></html>",
+                "a comment",
+            ),
+        ] {
+            let placement = plan_manifest_placement(html, ManifestLookup::default()).unwrap();
+            let warning = insertion_context_warning(html, placement).unwrap();
+            assert!(warning.contains(&format!("inside {context} ")), "{warning}");
+        }
+    }
+
+    #[test]
+    fn test_wrap_base64() {
+        let store = placeholder_store().unwrap();
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            wrap_base64: Some(16),
+            ..Default::default()
+        });
+        let mut output = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(SAMPLE_HTML), &mut output, &store)
+            .unwrap();
+        let written = String::from_utf8(output.get_ref().clone()).unwrap();
+
+        let b64 = STANDARD.encode(&store);
+        assert!(b64.len() > 32);
+        let wrapped = b64
+            .as_bytes()
+            .chunks(16)
+            .map(|line| std::str::from_utf8(line).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(written.contains(&format!(">{wrapped}</script>")));
+
+        // both readers ignore the line breaks
+        assert_eq!(HtmlIO::default().read_cai(&mut output).unwrap(), store);
+        let temp_dir = tempdirectory().unwrap();
+        let path = temp_dir_path(&temp_dir, "wrapped.html");
+        std::fs::write(&path, &written).unwrap();
+        assert_eq!(HtmlIO::default().read_cai_store(&path).unwrap(), store);
+
+        // the excluded region covers the wrapped text with its line breaks
+        let positions = HtmlIO::default()
+            .get_object_locations_from_stream(&mut output)
+            .unwrap();
+        let cai = positions
+            .iter()
+            .find(|p| p.htype == HashBlockObjectType::Cai)
+            .unwrap();
+        assert_eq!(&written[cai.offset..cai.offset + cai.length], wrapped);
+
+        // a reserved manifest of the wrapped size is filled in place
+        let mut filled = Cursor::new(Vec::new());
+        html_io
+            .write_cai(&mut Cursor::new(&written), &mut filled, &store)
+            .unwrap();
+        assert_eq!(filled.into_inner(), written.as_bytes());
+    }
+
+    #[test]
+    fn test_save_cai_store_rejects_binary() {
+        let temp_dir = tempdirectory().unwrap();
+        let path = temp_dir_path(&temp_dir, "image.html");
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x01\0\0\0\x01\x08\x06\0\0\0";
+        std::fs::write(&path, png).unwrap();
+
+        let store = placeholder_store().unwrap();
+        assert!(matches!(
+            HtmlIO::new("html").save_cai_store(&path, &store),
+            Err(Error::InvalidAsset(_))
+        ));
+        assert_eq!(std::fs::read(&path).unwrap(), png);
+
+        // text that only looks odd is still written
+        let mut output = Cursor::new(Vec::new());
+        HtmlIO::default()
+            .write_cai(&mut Cursor::new("plain\x0ctext"), &mut output, &store)
+            .unwrap();
+        assert!(HtmlIO::default()
+            .write_cai(
+                &mut Cursor::new(b"<html>\0</html>".to_vec()),
+                &mut output,
+                &store
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_script_ranges() {
+        let store = STANDARD.encode(placeholder_store().unwrap());
+        let scripts = [
+            "<script src=\"/app.js\"></script>".to_owned(),
+            "<script type=\"module\" nonce=\"r4nd0m\">import './m.js';</script>".to_owned(),
+            format!("<script type=\"application/c2pa-manifest\">{store}</script>"),
+            "<SCRIPT type=\"application/json\" id=\"state\">{}</SCRIPT>".to_owned(),
+        ];
+        let html = SAMPLE_HTML.replace("<p>", &format!("{}<p>", scripts.concat()));
+
+        let found = HtmlIO::default()
+            .script_ranges(&mut Cursor::new(&html))
+            .unwrap();
+        assert_eq!(found.len(), scripts.len());
+        for (info, script) in found.iter().zip(&scripts) {
+            assert_eq!(&html[info.range.clone()], script);
+        }
+        assert_eq!(
+            found
+                .iter()
+                .map(|info| (
+                    info.script_type.as_deref(),
+                    info.has_nonce,
+                    info.is_manifest
+                ))
+                .collect::<Vec<_>>(),
+            [
+                (None, false, false),
+                (Some("module"), true, false),
+                (Some("application/c2pa-manifest"), false, true),
+                (Some("application/json"), false, false),
+            ]
+        );
+
+        // the JSON bootstrap script holds the manifest when configured
+        let html_io = HtmlIO::with_config(HtmlIoConfig {
+            json_bootstrap: Some(JsonBootstrap::new("state", "manifest")),
+            ..Default::default()
+        });
+        let found = html_io.script_ranges(&mut Cursor::new(&html)).unwrap();
+        assert!(found[3].is_manifest);
+
+        // offsets are stream offsets in UTF-16 documents
+        let utf16 = utf16le(&html);
+        let found = HtmlIO::default()
+            .script_ranges(&mut Cursor::new(&utf16))
+            .unwrap();
+        assert_eq!(utf16[found[0].range.clone()], utf16le(&scripts[0])[2..]);
+    }
+
+    #[test]
+    fn test_body_without_end_tag() {
+        let html_io = HtmlIO::default();
+        let html = "<!DOCTYPE html>\n<html><head><title>t</title></head>\n<body>\n<p>Hello</p>\n<script src=\"app.js\"></script>\n";
+        let placement = html_io.manifest_placement(&mut Cursor::new(html)).unwrap();
+        assert_eq!(
+            placement,
+            ManifestPlacement::Body {
+                offset: html.trim_end().len()
+            }
+        );
+
+        // an omitted </body> before </html> ends the body there
+        let with_html_end = format!("{}\n</html>\n", html.trim_end());
+        assert_eq!(
+            html_io
+                .manifest_placement(&mut Cursor::new(&with_html_end))
+                .unwrap(),
+            ManifestPlacement::Body {
+                offset: html.trim_end().len()
+            }
+        );
+
+        // the signed document keeps the content in front of the manifest and is bound
+        let signed = String::from_utf8(sign_sample(html)).unwrap();
+        let manifest = signed
+            .find("<script type=\"application/c2pa-manifest\"")
+            .unwrap();
+        assert!(manifest > signed.find("app.js").unwrap());
+        html_io.verify_binding(&mut Cursor::new(&signed)).unwrap();
+
+        let positions = html_io
+            .get_object_locations_from_stream(&mut Cursor::new(&signed))
+            .unwrap();
+        let cai = positions
+            .iter()
+            .find(|p| p.htype == HashBlockObjectType::Cai)
+            .unwrap();
+        assert_eq!(
+            STANDARD
+                .decode(&signed[cai.offset..cai.offset + cai.length])
+                .unwrap(),
+            html_io.read_cai(&mut Cursor::new(&signed)).unwrap()
+        );
+        assert_eq!(
+            positions.iter().map(|p| p.length).sum::<usize>(),
+            signed.len()
+        );
+    }
+
+    #[test]
+    fn test_verify_trust() {
+        let signed = sign_sample(SAMPLE_HTML);
+        let with_anchors = |anchors: &str| {
+            HtmlIO::with_config(HtmlIoConfig {
+                trust_anchors: Some(anchors.to_owned()),
+                ..Default::default()
+            })
+        };
+
+        // the test signer chains to one of the test roots
+        let in_set = with_anchors(include_str!(
+            "../../tests/fixtures/crypto/raw_signature/test_cert_root_bundle.pem"
+        ));
+        assert!(in_set.verify_trust(&mut Cursor::new(&signed)).unwrap());
+
+        // certificates of another signer do not make it trusted, the manifest stays valid
+        let out_of_set = with_anchors(include_str!(
+            "../../tests/fixtures/crypto/raw_signature/es256.pub"
+        ));
+        assert!(!out_of_set.verify_trust(&mut Cursor::new(&signed)).unwrap());
+
+        let temp_dir = tempdirectory().unwrap();
+        std::fs::write(temp_dir.path().join("signed.html"), &signed).unwrap();
+        let [report] = in_set
+            .verify_directory(temp_dir.path())
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert!(report.valid && report.trusted);
+        let [report] = out_of_set
+            .verify_directory(temp_dir.path())
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert!(report.valid && !report.trusted && report.error.is_none());
+
+        assert!(matches!(
+            with_anchors("-----BEGIN CERTIFICATE-----\nnot base64\n")
+                .verify_trust(&mut Cursor::new(&signed)),
+            Err(Error::BadParam(_))
+        ));
+    }
+
+    #[test]
+    fn test_whitespace_only_manifest() {
+        let html_io = HtmlIO::default();
+        let empty = "<script type=\"application/c2pa-manifest\">\n\n</script>";
+        let html = SAMPLE_HTML.replace("<p>", &format!("{empty}\n<p>"));
+
+        assert!(matches!(
+            html_io.read_cai(&mut Cursor::new(&html)),
+            Err(Error::JumbfNotFound)
+        ));
+        assert!(!html_io.has_manifest(&mut Cursor::new(&html)).unwrap());
+
+        // the placeholder and the signed manifest go into the existing element
+        let mut prepared = Vec::new();
+        add_required_segs_to_stream(
+            &html_io,
+            &mut Cursor::new(&html),
+            &mut Cursor::new(&mut prepared),
+        )
+        .unwrap();
+        let prepared = String::from_utf8(prepared).unwrap();
+        assert_eq!(prepared.matches("application/c2pa-manifest").count(), 1);
+        let position = html.find(empty).unwrap();
+        assert_eq!(
+            prepared.find("<script"),
+            Some(html[..position].trim_end().len())
+        );
+        assert!(prepared.ends_with(&html[position + empty.len()..]));
+
+        let signed = String::from_utf8(sign_sample(&html)).unwrap();
+        assert_eq!(signed.matches("application/c2pa-manifest").count(), 1);
+        html_io.verify_binding(&mut Cursor::new(&signed)).unwrap();
+    }
+
+    #[test]
+    fn test_is_html_path() {
+        for path in [
+            "page.html",
+            "index.htm",
+            "doc.xhtml",
+            "doc.xht",
+            "dir/PAGE.HTML",
+        ] {
+            assert!(is_html_path(Path::new(path)), "{path}");
+        }
+        for path in [
+            "image.png",
+            "notes.txt",
+            "html",
+            "page.html.gz",
+            "archive.c2pa",
+        ] {
+            assert!(!is_html_path(Path::new(path)), "{path}");
+        }
+    }
+
+    #[test]
+    fn test_manifest_region_at_end() {
+        let store = STANDARD.encode(placeholder_store().unwrap());
+        // a manifest script cut off right after its content
+        let html = format!("<script type=\"application/c2pa-manifest\">{store}");
+        let start = html.len() - store.len();
+
+        let positions = manifest_regions(start, store.len(), html.len());
+        assert_eq!(positions.len(), 2);
+        assert!(positions.iter().all(|p| p.length > 0));
+        assert_eq!(positions[0].htype, HashBlockObjectType::Cai);
+        assert_eq!(positions[0].offset + positions[0].length, html.len());
+        validate_regions(&positions, html.len()).unwrap();
+
+        // such a document cannot be signed, the manifest would be appended inside the script
+        assert!(matches!(
+            HtmlIO::default().get_object_locations_from_stream(&mut Cursor::new(&html)),
+            Err(Error::InvalidAsset(_))
+        ));
+
+        // a manifest at the start of the stream has no region before it
+        let positions = manifest_regions(0, 8, 20);
+        assert_eq!(
+            positions
+                .iter()
+                .map(|p| (p.offset, p.length))
+                .collect::<Vec<_>>(),
+            [(0, 8), (8, 12)]
+        );
+    }
+}
@@ -0,0 +1,448 @@
+//! The byte scanner that finds manifest elements in HTML. It works on string slices only
+//! and uses nothing but `core` and `alloc`, so it can be used without I/O, e.g. in WASM or
+//! embedded builds; [`super::html_io`] wraps it with the stream handling.
+
+#![deny(clippy::std_instead_of_core, clippy::std_instead_of_alloc)]
+
+use alloc::{format, vec::Vec};
+use core::ops::Range;
+
+use super::html_io::{ActiveManifest, ManifestEncoding};
+
+// type of the later into the HTML injected script tag
+// <script type="application/c2pa-manifest">BASE64_ENCODED_MANIFEST</script> // this is the format
+pub(crate) const C2PA_SCRIPT_TYPE: &str = "application/c2pa-manifest";
+// <script type="application/c2pa-manifest+hex">HEX_ENCODED_MANIFEST</script> // with ManifestEncoding::Hex
+pub(crate) const C2PA_HEX_SCRIPT_TYPE: &str = "application/c2pa-manifest+hex";
+
+// <script type="application/json" id="...">{"manifest": "BASE64_ENCODED_MANIFEST"}</script> // with HtmlIoConfig::json_bootstrap
+pub(crate) const JSON_SCRIPT_TYPE: &str = "application/json";
+
+// <script type="application/c2pa-manifest" data-c2pa-active="true"> marks the active one of several manifests
+pub(crate) const C2PA_ACTIVE_ATTRIBUTE: &str = "data-c2pa-active";
+
+// tag markers used by the tokenizer
+pub(crate) const SCRIPT_OPEN: &[u8] = b"<script";
+pub(crate) const SCRIPT_CLOSE: &[u8] = b"</script";
+pub(crate) const TEMPLATE_OPEN: &[u8] = b"<template";
+pub(crate) const TEMPLATE_CLOSE: &[u8] = b"</template";
+
+// manifests in XHTML documents are wrapped in a CDATA section
+pub(crate) const CDATA_OPEN: &str = "<![CDATA[";
+pub(crate) const CDATA_CLOSE: &str = "]]>";
+
+// text of the comments some tools bracket the manifest with, <!--c2pa-begin--> ... <!--c2pa-end-->
+pub(crate) const SENTINEL_BEGIN: &[u8] = b"c2pa-begin";
+pub(crate) const SENTINEL_END: &[u8] = b"c2pa-end";
+
+/// Where the encoded manifest is in an HTML document, see [`locate_manifest`](crate::locate_manifest).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ManifestLocation {
+    /// Byte range of the base64 (or hex) text, without surrounding white space or CDATA.
+    pub text: Range<usize>,
+    /// Encoding of the text, given by the type of the manifest element.
+    pub encoding: ManifestEncoding,
+}
+
+/// the active manifest of a UTF-8 document found with `lookup`, `None` for documents
+/// without a manifest or that are not UTF-8, see [`locate_manifest`](crate::locate_manifest)
+pub(crate) fn locate_manifest(html: &[u8], lookup: ManifestLookup<'_>) -> Option<ManifestLocation> {
+    let html = core::str::from_utf8(html).ok()?;
+    find_manifest_element(html, lookup).map(|element| ManifestLocation {
+        text: element.manifest_text(html),
+        encoding: element.encoding(),
+    })
+}
+
+/// a `<script>` (or custom manifest) element found by the tokenizer, all offsets are byte offsets into the html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ScriptElement<'a> {
+    pub(crate) start: usize,         // start of the `<script` open tag
+    pub(crate) content_start: usize, // first byte after the open tag
+    pub(crate) content_end: usize,   // start of the `</script` end tag
+    pub(crate) end: usize,           // first byte after the end tag
+    pub(crate) attributes: Vec<(&'a str, &'a str)>,
+}
+
+impl ScriptElement<'_> {
+    /// value of the named attribute, attribute names are case insensitive
+    pub(crate) fn attribute(&self, name: &str) -> Option<&str> {
+        find_attribute(&self.attributes, name)
+    }
+
+    pub(crate) fn is_manifest(&self) -> bool {
+        is_manifest_type(self.attribute("type"))
+    }
+
+    pub(crate) fn encoding(&self) -> ManifestEncoding {
+        manifest_type_encoding(self.attribute("type")).unwrap_or_default()
+    }
+
+    /// range of the encoded manifest inside the script content without the surrounding
+    /// whitespace and the CDATA section written in [`MarkupMode::Xhtml`](crate::MarkupMode::Xhtml)
+    pub(crate) fn manifest_text(&self, html: &str) -> Range<usize> {
+        let text = manifest_text_in(&html[self.content_start..self.content_end]);
+        self.content_start + text.start..self.content_start + text.end
+    }
+
+    /// start of an HTML comment directly in front of the element that belongs to the
+    /// manifest block, see [`is_block_comment`]
+    pub(crate) fn comment_start(&self, html: &str, comment: Option<&str>) -> Option<usize> {
+        let before = html[..self.start].strip_suffix("-->")?;
+        let start = before.rfind("<!--")?;
+        is_block_comment(&before[start + 4..], comment).then_some(start)
+    }
+
+    /// range of the element including the whitespace in front of it and a directly
+    /// preceding block comment, this is the region that is replaced when writing and dropped
+    /// when removing. Written blocks start with their whitespace, so dropping it gives back
+    /// the document as it was before the manifest was embedded.
+    pub(crate) fn block_range(&self, html: &str, comment: Option<&str>) -> Range<usize> {
+        let start = self.comment_start(html, comment).unwrap_or(self.start);
+        let before = html[..start].trim_end_matches(|c: char| c.is_ascii_whitespace());
+        before.len()..self.end
+    }
+}
+
+/// value of the named attribute, attribute names are case insensitive
+pub(crate) fn find_attribute<'a>(attributes: &[(&'a str, &'a str)], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, v)| *v)
+}
+
+/// `true` for the script types of manifest scripts in either encoding
+pub(crate) fn is_manifest_type(script_type: Option<&str>) -> bool {
+    manifest_type_encoding(script_type).is_some()
+}
+
+/// the manifest encoding named by a script type, `None` for other types. Parameters
+/// (`; charset=...`) are ignored, the media type itself has to match exactly so types that
+/// merely start like a manifest type, e.g. `application/c2pa-manifest-index`, do not.
+pub(crate) fn manifest_type_encoding(script_type: Option<&str>) -> Option<ManifestEncoding> {
+    let media_type = script_type?.split(';').next().unwrap_or_default().trim();
+    match media_type {
+        C2PA_SCRIPT_TYPE => Some(ManifestEncoding::Base64),
+        C2PA_HEX_SCRIPT_TYPE => Some(ManifestEncoding::Hex),
+        _ => None,
+    }
+}
+
+/// case insensitive search for an ascii needle in the haystack starting at `from`
+pub(crate) fn find_ascii_ci(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    if needle.is_empty() || haystack.len() < needle.len() {
+        return None;
+    }
+    // jump between the candidates for the first byte, documents are mostly text
+    let first = needle[0];
+    let candidates = memchr::memchr2_iter(
+        first.to_ascii_lowercase(),
+        first.to_ascii_uppercase(),
+        haystack.get(from..)?,
+    );
+    candidates.map(|i| from + i).find(|&i| {
+        haystack
+            .get(i..i + needle.len())
+            .is_some_and(|candidate| candidate.eq_ignore_ascii_case(needle))
+    })
+}
+
+/// a tag name ends at whitespace, `/` or `>` so `<scripts>` is not a script
+pub(crate) fn is_tag_name_end(byte: Option<&u8>) -> bool {
+    matches!(byte, Some(b) if b.is_ascii_whitespace() || *b == b'/' || *b == b'>')
+}
+
+/// parse the attributes of an open tag starting right after the tag name,
+/// returns the attributes and the offset after the closing `>`
+pub(crate) fn parse_tag_attributes(html: &str, from: usize) -> Option<(Vec<(&str, &str)>, usize)> {
+    let bytes = html.as_bytes();
+    let mut attributes = Vec::new();
+    let mut pos = from;
+
+    loop {
+        while pos < bytes.len() && (bytes[pos].is_ascii_whitespace() || bytes[pos] == b'/') {
+            pos += 1;
+        }
+        match bytes.get(pos) {
+            None => return None, // unterminated tag
+            Some(b'>') => return Some((attributes, pos + 1)),
+            _ => (),
+        }
+
+        let name_start = pos;
+        while pos < bytes.len()
+            && !bytes[pos].is_ascii_whitespace()
+            && !matches!(bytes[pos], b'/' | b'>' | b'=')
+        {
+            pos += 1;
+        }
+        let name = &html[name_start..pos];
+
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if bytes.get(pos) != Some(&b'=') {
+            attributes.push((name, ""));
+            continue;
+        }
+        pos += 1;
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+
+        let value = match bytes.get(pos) {
+            Some(quote @ (b'"' | b'\'')) => {
+                let value_start = pos + 1;
+                let value_end =
+                    value_start + bytes[value_start..].iter().position(|b| b == quote)?;
+                pos = value_end + 1;
+                &html[value_start..value_end]
+            }
+            _ => {
+                let value_start = pos;
+                while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() && bytes[pos] != b'>' {
+                    pos += 1;
+                }
+                &html[value_start..pos]
+            }
+        };
+        attributes.push((name, value));
+    }
+}
+
+/// find the end of the script raw text starting at `from`, following the HTML raw text
+/// rules the content ends at the first `</script` followed by whitespace, `/` or `>`
+/// returns the start of the end tag and the offset after its closing `>`
+pub(crate) fn find_script_end(
+    bytes: &[u8],
+    from: usize,
+    tag_close: &[u8],
+) -> Option<(usize, usize)> {
+    let mut pos = from;
+    while let Some(close) = find_ascii_ci(bytes, tag_close, pos) {
+        let name_end = close + tag_close.len();
+        if is_tag_name_end(bytes.get(name_end)) {
+            let end = name_end + bytes[name_end..].iter().position(|b| *b == b'>')? + 1;
+            return Some((close, end));
+        }
+        pos = name_end;
+    }
+    None
+}
+
+/// find the end of a JSON script starting at `from`, a `</script` inside a JSON string
+/// is part of the value and does not end the content. Browsers end the element there, so
+/// the JSON bootstrap written here escapes it as `<\/script`, but documents from other
+/// producers may not. Falls back to [`find_script_end`] if the strings never close.
+pub(crate) fn find_json_script_end(bytes: &[u8], from: usize) -> Option<(usize, usize)> {
+    let mut in_string = false;
+    let mut pos = from;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'\\' if in_string => pos += 1,
+            b'"' => in_string = !in_string,
+            b'<' if !in_string => {
+                if let Some(end) =
+                    find_script_end(bytes, pos, SCRIPT_CLOSE).filter(|(close, _)| *close == pos)
+                {
+                    return Some(end);
+                }
+            }
+            _ => {}
+        }
+        pos += 1;
+    }
+    find_script_end(bytes, from, SCRIPT_CLOSE)
+}
+
+/// tokenize the `<script>` elements of the document, the content of a script is raw text
+/// so markup inside of it (including other script tags) is never treated as an element
+pub(crate) fn script_elements(html: &str) -> Vec<ScriptElement<'_>> {
+    elements_named(html, SCRIPT_OPEN, SCRIPT_CLOSE)
+}
+
+/// tokenize the elements opened by `tag_open` (e.g. `<script`) and closed by `tag_close`,
+/// their content is treated as raw text like the content of a script
+pub(crate) fn elements_named<'a>(
+    html: &'a str,
+    tag_open: &[u8],
+    tag_close: &[u8],
+) -> Vec<ScriptElement<'a>> {
+    let bytes = html.as_bytes();
+    let mut elements = Vec::new();
+    let mut pos = 0;
+
+    while let Some(start) = find_ascii_ci(bytes, tag_open, pos) {
+        let name_end = start + tag_open.len();
+        if !is_tag_name_end(bytes.get(name_end)) {
+            pos = name_end;
+            continue;
+        }
+
+        let Some((attributes, content_start)) = parse_tag_attributes(html, name_end) else {
+            break;
+        };
+        let is_json = tag_close == SCRIPT_CLOSE
+            && find_attribute(&attributes, "type")
+                .is_some_and(|t| t.trim().eq_ignore_ascii_case(JSON_SCRIPT_TYPE));
+        // a self-closed manifest element (`<script type="application/c2pa-manifest" />`) is
+        // present but empty, its end is not searched for in the rest of the document
+        let self_closed = bytes[..content_start].ends_with(b"/>")
+            && is_manifest_type(find_attribute(&attributes, "type"));
+        let script_end = if self_closed {
+            Some((content_start, content_start))
+        } else if is_json {
+            find_json_script_end(bytes, content_start)
+        } else {
+            find_script_end(bytes, content_start, tag_close)
+        };
+        let Some((content_end, end)) = script_end else {
+            break;
+        };
+
+        elements.push(ScriptElement {
+            start,
+            content_start,
+            content_end,
+            end,
+            attributes,
+        });
+        pos = end;
+    }
+
+    elements
+}
+
+/// the range of the encoded manifest in the text of a manifest script, without the
+/// surrounding whitespace and CDATA section
+pub(crate) fn manifest_text_in(content: &str) -> Range<usize> {
+    let trimmed = content.trim();
+    let mut start = content.len() - content.trim_start().len();
+    let text = match trimmed
+        .strip_prefix(CDATA_OPEN)
+        .and_then(|t| t.strip_suffix(CDATA_CLOSE))
+    {
+        Some(inner) => {
+            start += CDATA_OPEN.len() + (inner.len() - inner.trim_start().len());
+            inner.trim()
+        }
+        None => trimmed,
+    };
+    start..start + text.len()
+}
+
+/// `true` when the text of a comment is the configured `comment` (see
+/// [`HtmlIoConfig::manifest_comment`](crate::HtmlIoConfig::manifest_comment)) or the
+/// `c2pa-begin` sentinel, only such a comment directly in front of a manifest belongs to its
+/// block, any other comment is left in the document
+pub(crate) fn is_block_comment(text: &str, comment: Option<&str>) -> bool {
+    let text = text.trim_matches(|c: char| c.is_ascii_whitespace());
+    text.as_bytes() == SENTINEL_BEGIN || comment.is_some_and(|comment| comment.trim() == text)
+}
+
+/// how the manifest elements of a document are found, see [`HtmlIoConfig::manifest_element`](crate::HtmlIoConfig::manifest_element)
+/// and [`HtmlIoConfig::active_manifest`](crate::HtmlIoConfig::active_manifest)
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct ManifestLookup<'a> {
+    pub(crate) element: Option<&'a str>,
+    pub(crate) active: ActiveManifest,
+    pub(crate) templates: bool, // see [`HtmlIoConfig::read_from_template`]
+    pub(crate) comment: Option<&'a str>, // see [`is_block_comment`]
+}
+
+/// all c2pa manifest script elements in the html in document order, along with the custom
+/// manifest elements of [`HtmlIoConfig::manifest_element`](crate::HtmlIoConfig::manifest_element) outside of scripts
+pub(crate) fn manifest_elements<'a>(
+    html: &'a str,
+    lookup: ManifestLookup<'_>,
+) -> Vec<ScriptElement<'a>> {
+    // the type attribute is matched verbatim, without it there is nothing to tokenize for
+    if memchr::memmem::find(html.as_bytes(), C2PA_SCRIPT_TYPE.as_bytes()).is_none() {
+        return Vec::new();
+    }
+    let mut elements = script_elements(html);
+    if let Some(name) = lookup.element {
+        let tag_open = format!("<{name}");
+        let tag_close = format!("</{name}");
+        let custom: Vec<_> = elements_named(html, tag_open.as_bytes(), tag_close.as_bytes())
+            .into_iter()
+            .filter(|c| {
+                !elements
+                    .iter()
+                    .any(|s| s.start <= c.start && c.start < s.end)
+            })
+            .collect();
+        elements.extend(custom);
+        elements.sort_by_key(|e| e.start);
+    }
+    let templates = match lookup.templates {
+        true => Vec::new(),
+        false => template_ranges(html, &elements),
+    };
+    elements.retain(|e| e.is_manifest() && !templates.iter().any(|t| t.contains(&e.start)));
+    elements
+}
+
+/// the ranges of the outermost `<template>` elements, from their open tag to after their
+/// end tag or the end of the document, template tags in raw text are skipped
+pub(crate) fn template_ranges(html: &str, raw_text: &[ScriptElement<'_>]) -> Vec<Range<usize>> {
+    // open and end tags are found by their name, `<` or `</` in front of it tells them apart
+    let tag_name = &TEMPLATE_OPEN[1..];
+    let bytes = html.as_bytes();
+    let mut ranges = Vec::new();
+    let mut depth = 0usize;
+    let mut outer_start = 0;
+    let mut pos = 0;
+
+    while let Some(name) = find_ascii_ci(bytes, tag_name, pos) {
+        pos = name + tag_name.len();
+        if !is_tag_name_end(bytes.get(pos))
+            || raw_text
+                .iter()
+                .any(|e| (e.content_start..e.content_end).contains(&name))
+        {
+            continue;
+        }
+        if bytes[..name].ends_with(b"</") {
+            if depth == 1 {
+                let end = memchr::memchr(b'>', &bytes[pos..]).map_or(html.len(), |i| pos + i + 1);
+                ranges.push(outer_start..end);
+            }
+            depth = depth.saturating_sub(1);
+        } else if bytes[..name].ends_with(b"<") {
+            if depth == 0 {
+                outer_start = name - 1;
+            }
+            depth += 1;
+        }
+    }
+    if depth > 0 {
+        ranges.push(outer_start..html.len());
+    }
+    ranges
+}
+
+/// find the active c2pa manifest element in the html, see [`ActiveManifest`]
+pub(crate) fn find_manifest_element<'a>(
+    html: &'a str,
+    lookup: ManifestLookup<'_>,
+) -> Option<ScriptElement<'a>> {
+    let elements = manifest_elements(html, lookup);
+    match lookup.active {
+        ActiveManifest::First => elements.into_iter().next(),
+        ActiveManifest::Last => elements.into_iter().last(),
+        ActiveManifest::MarkedOrLast => {
+            let marked = elements
+                .iter()
+                .position(|e| is_active_marker(e.attribute(C2PA_ACTIVE_ATTRIBUTE)));
+            let index = marked.unwrap_or(elements.len().saturating_sub(1));
+            elements.into_iter().nth(index)
+        }
+    }
+}
+
+/// value of the `data-c2pa-active` attribute that marks the active manifest
+pub(crate) fn is_active_marker(value: Option<&str>) -> bool {
+    value.is_some_and(|v| v.trim().eq_ignore_ascii_case("true"))
+}
@@ -21,6 +21,9 @@ pub mod riff_io;
 pub mod svg_io;
 pub mod tiff_io;
 pub mod html_io;
+pub(crate) mod html_scan;
+#[cfg(feature = "html_email")]
+pub mod html_email_io;
 
 #[cfg(feature = "pdf")]
 pub(crate) mod pdf;
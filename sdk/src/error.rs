@@ -244,6 +244,22 @@ pub enum Error {
     #[error("hash verification( {0} )")]
     HashMismatch(String),
 
+    /// The hard binding recomputed over the asset differs from the data hash stored in the manifest.
+    #[error("hard binding mismatch (first difference at {first_diff_offset:?})")]
+    BindingMismatch {
+        expected: Vec<u8>,
+        actual: Vec<u8>,
+        first_diff_offset: Option<usize>,
+    },
+
+    /// An embedded manifest decodes to more bytes than the configured limit.
+    #[error("embedded manifest of {size} bytes exceeds the limit of {limit} bytes")]
+    ManifestTooLarge { size: usize, limit: usize },
+
+    /// The embedded manifest is not inside the document `<head>` although the placement policy requires it.
+    #[error("embedded manifest is not inside the document head")]
+    ManifestNotInHead,
+
     #[error("claim verification failure: {0}")]
     ClaimVerification(String),
 
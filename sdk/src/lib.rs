@@ -90,6 +90,9 @@ pub const NAME: &str = "c2pa-rs";
 /// The version of this C2PA SDK
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+// for code that only uses `core` and `alloc`, e.g. the HTML manifest scanner
+extern crate alloc;
+
 // Public modules
 /// The assertions module contains the definitions for the assertions that are part of the C2PA specification.
 pub mod assertions;
@@ -132,8 +135,24 @@ pub mod validation_status;
 // Public exports
 #[doc(inline)]
 pub use assertions::Relationship;
+pub use asset_handlers::html_io::{
+    is_html_path, locate_manifest, sign_html, validate_regions, ActiveManifest, BindingReport,
+    EmbeddedManifest, FileVerification, HtmlIO, HtmlIoConfig, JsonBootstrap, ManifestBindingReport,
+    ManifestEncoding, ManifestLocation, ManifestPlacement, MarkupMode, PageMeta, PlacementTarget,
+    PreWriteTransform, ProvenanceNode, QuoteStyle, RenameRetry, ScriptInfo, TimestampReport,
+    WriteReport,
+};
+#[cfg(feature = "html_email")]
+pub use asset_handlers::html_email_io::HtmlEmailIO;
+#[cfg(feature = "html_verification_cache")]
+pub use asset_handlers::html_io::VerificationCache;
+#[cfg(feature = "html_stats")]
+pub use asset_handlers::html_io::Stats;
+#[cfg(not(target_arch = "wasm32"))]
+pub use asset_handlers::html_io::read_cai_range;
 #[cfg(feature = "v1_api")]
 pub use asset_io::{CAIRead, CAIReadWrite};
+pub use asset_io::{HashBlockObjectType, HashObjectPositions};
 pub use builder::{Builder, ManifestDefinition};
 pub use callback_signer::{CallbackFunc, CallbackSigner};
 pub use claim_generator_info::ClaimGeneratorInfo;
@@ -42,7 +42,8 @@ pub fn extension_to_mime(extension: &str) -> Option<&'static str> {
         "arw" => "image/x-sony-arw",
         "nef" => "image/x-nikon-nef",
         "c2pa" | "application/x-c2pa-manifest-store" | "application/c2pa" => "application/c2pa",
-        "html" => "text/html",
+        "html" | "htm" => "text/html",
+        "xhtml" | "xht" => "application/xhtml+xml",
         _ => return None,
     })
 }
@@ -90,7 +91,8 @@ pub fn format_to_extension(format: &str) -> Option<&'static str> {
         "arw" | "image/x-sony-arw" => "arw",
         "nef" | "image/x-nikon-nef" => "nef",
         "c2pa" | "application/x-c2pa-manifest-store" | "application/c2pa" => "c2pa",
-        "html" | "text/html" => "html",
+        "html" | "htm" | "text/html" => "html",
+        "xhtml" | "xht" | "application/xhtml+xml" => "xhtml",
         _ => return None,
     })
 }